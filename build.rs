@@ -0,0 +1,121 @@
+//! With the `bundled-engine` feature, builds the vendored Stockfish with its
+//! own Makefile and hands the binary's path to the app as
+//! `BUNDLED_ENGINE_PATH`.
+//!
+//! Environment variables:
+//! - `STOCKFISH_ARCH`: `ARCH` passed to make, `native` by default
+//! - `STOCKFISH_NET_DIR`: directory holding the NNUE network files, for
+//!   building offline. Without it the Makefile tries to download them.
+
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+const VENDORED_DIR: &str = "src/chess/stockfish";
+
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+    if env::var_os("CARGO_FEATURE_BUNDLED_ENGINE").is_none() {
+        return;
+    }
+    println!("cargo:rerun-if-changed={}/src", VENDORED_DIR);
+    println!("cargo:rerun-if-changed={}/scripts", VENDORED_DIR);
+    println!("cargo:rerun-if-env-changed=STOCKFISH_ARCH");
+    println!("cargo:rerun-if-env-changed=STOCKFISH_NET_DIR");
+
+    // Build in a copy so the vendored tree stays free of objects and nets
+    let out_dir = PathBuf::from(env::var("OUT_DIR").expect("cargo sets OUT_DIR"));
+    let build_dir = out_dir.join("stockfish-src");
+    copy_dir(Path::new(VENDORED_DIR), &build_dir).expect("Failed to copy the vendored Stockfish sources");
+    let src_dir = build_dir.join("src");
+
+    let nets = network_names(&src_dir);
+    let embed_nets = fetch_networks(&src_dir, &nets);
+
+    let arch = env::var("STOCKFISH_ARCH").unwrap_or_else(|_| "native".to_string());
+    let jobs = env::var("NUM_JOBS").unwrap_or_else(|_| "1".to_string());
+    let mut make = Command::new("make");
+    make.current_dir(&src_dir).arg(format!("-j{}", jobs)).arg(format!("ARCH={}", arch)).arg("all");
+    if !embed_nets {
+        make.arg("EXTRACXXFLAGS=-DNNUE_EMBEDDING_OFF");
+    }
+    let status = make.status().expect("Failed to run make; building the bundled engine needs make and a C++17 compiler");
+    assert!(status.success(), "Building Stockfish with ARCH={} failed ({})", arch, status);
+
+    let exe = if env::var("CARGO_CFG_TARGET_OS").as_deref() == Ok("windows") { "stockfish.exe" } else { "stockfish" };
+    let engine_path = out_dir.join(exe);
+    fs::copy(src_dir.join(exe), &engine_path).expect("Failed to copy the Stockfish binary");
+    println!("cargo:rustc-env=BUNDLED_ENGINE_PATH={}", engine_path.display());
+}
+
+/// File names of the networks Stockfish embeds, from `evaluate.h`.
+fn network_names(src_dir: &Path) -> Vec<String> {
+    let header = fs::read_to_string(src_dir.join("evaluate.h")).expect("Failed to read evaluate.h");
+    header
+        .lines()
+        .filter(|line| line.starts_with("#define EvalFileDefaultName"))
+        .filter_map(|line| line.split('"').nth(1))
+        .map(String::from)
+        .collect()
+}
+
+/// Puts the networks next to the sources so they get embedded. Returns
+/// false, with a warning, when they can't be found or downloaded; the engine
+/// is then built without them and needs `EvalFile` and `EvalFileSmall` set at
+/// runtime.
+fn fetch_networks(src_dir: &Path, nets: &[String]) -> bool {
+    if let Some(net_dir) = env::var_os("STOCKFISH_NET_DIR") {
+        for net in nets {
+            let cached = Path::new(&net_dir).join(net);
+            if cached.exists() {
+                fs::copy(&cached, src_dir.join(net)).expect("Failed to copy NNUE network");
+            }
+        }
+    }
+
+    // A failed download can leave an empty file behind
+    let missing = |nets: &[String]| {
+        nets.iter()
+            .filter(|net| fs::metadata(src_dir.join(net)).map_or(true, |meta| meta.len() == 0))
+            .cloned()
+            .collect::<Vec<_>>()
+    };
+    if missing(nets).is_empty() {
+        return true;
+    }
+
+    // The Makefile's own download script, which needs network access
+    let _ = Command::new("make").current_dir(src_dir).arg("net").status();
+    let still_missing = missing(nets);
+    if still_missing.is_empty() {
+        return true;
+    }
+
+    for net in &still_missing {
+        let _ = fs::remove_file(src_dir.join(net));
+    }
+    println!(
+        "cargo:warning=NNUE networks {} are missing and could not be downloaded. \
+         Building Stockfish without embedded networks: set EvalFile and EvalFileSmall \
+         in the Engine options window (or engine_options.cfg), or put the files next to the engine. \
+         To embed them, download them once and point STOCKFISH_NET_DIR at their directory.",
+        still_missing.join(", ")
+    );
+    false
+}
+
+fn copy_dir(from: &Path, to: &Path) -> io::Result<()> {
+    fs::create_dir_all(to)?;
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        let target = to.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            copy_dir(&entry.path(), &target)?;
+        } else {
+            fs::copy(entry.path(), target)?;
+        }
+    }
+    Ok(())
+}
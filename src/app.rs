@@ -1,88 +1,259 @@
 use eframe::{egui, Frame};
-use egui::{Color32, Pos2, Rect, Sense, Stroke, Vec2, FontId, Align2};
+use egui::{Color32, Pos2, Rect, Sense, Shape, Stroke, Vec2, FontId, Align2};
 use strum::IntoEnumIterator;
-use crate::chess::{Board, ChessMove, GameState, Piece, PieceColor, PieceType, Position};
-use crate::game_mode::{Difficulty, GameMode, PlayerColor};
-use crate::stockfish::Stockfish;
+use crate::board_editor::{BoardEditor, EditorAction};
+use crate::chess::{format_numbered_line, write_pgn, Board, ChessMove, Game, GameState, Piece, PieceColor, PieceType, Position, Termination};
+use crate::clock::ChessClock;
+use crate::clock_display::{self, ClockSetup, CLOCK_HEIGHT, CLOCK_WIDTH};
+use crate::difficulty::{self, DifficultyProfile, EngineStrength, PROFILES_PATH};
+use crate::game_controller::{GameController, GameEvent};
+use crate::game_mode::{Difficulty, EngineKind, GameMode, PlayerColor};
+use crate::player;
+use crate::engine::BUILTIN_ENGINE;
+use crate::engine_console::EngineConsole;
+use crate::engine_options::{self, OptionsEditor, SavedOptions, ENGINE_OPTIONS_PATH};
+use crate::engine_match::{EngineMatch, MatchSetup, MAX_PLIES};
+use crate::eval_bar::{self, EVAL_BAR_WIDTH};
+use crate::eval_graph::{self, GraphMarker};
+use crate::move_input::MoveInput;
+use crate::review::{self, GameReview, MoveClass, ReviewBudget, ReviewMessage};
+use crate::stockfish::{BackgroundSearch, EngineEvent, Score, SearchInfo, SearchLimits, Stockfish, Wdl};
+use std::ops::RangeInclusive;
+use std::path::Path;
+use std::sync::mpsc::{Receiver, TryRecvError};
+use std::time::Duration;
+
+#[cfg(feature = "bundled-engine")]
+const STOCKFISH_PATH: &str = env!("BUNDLED_ENGINE_PATH");
+#[cfg(not(feature = "bundled-engine"))]
+const STOCKFISH_PATH: &str = "./src/chess/stockfish/stockfish-ubuntu-x86-64-avx2";
+
+/// Searches in a row the engine must see itself lost in before resigning,
+/// so one pessimistic search doesn't end the game.
+const RESIGN_AFTER_SEARCHES: u32 = 3;
+/// The engine takes a draw unless it thinks it is better by more than this.
+const DRAW_ACCEPT_CP: i32 = 25;
+
+/// Where the app is: picking a mode, setting up a position or a game,
+/// playing it, looking at the result, or going through the finished game.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Screen {
+    Menu,
+    Editor,
+    Setup,
+    Playing,
+    GameOver,
+    Review,
+}
 
 pub struct ChessApp {
-    board: Board,
+    screen: Screen,
+    game_mode: GameMode,
+    // The game being played; rules, clock and result live here
+    controller: GameController,
+    // Position games start from, when set up in the editor
+    start_position: Option<Board>,
+    editor: BoardEditor,
     selected_position: Option<Position>,
     possible_moves: Vec<ChessMove>,
     board_flipped: bool,
-    game_state: GameState,
-    game_mode: Option<GameMode>,
-    difficulty: Difficulty,
+    move_input: MoveInput,
+    clock_setup: ClockSetup,
+    // Centipawns down at which the engine resigns, if it ever does
+    resign_threshold: Option<i32>,
+    // Consecutive engine searches at or below the resign threshold
+    hopeless_searches: u32,
+    // Built-in levels first, then the custom ones from the profiles file
+    profiles: Vec<DifficultyProfile>,
+    profile_index: usize,
+    profile_draft: DifficultyProfile,
     player_color: PlayerColor,
+    engine_kind: EngineKind,
+    // The `UCI_Elo` range the last engine started advertised, and its kind
+    engine_elo_range: Option<(EngineKind, RangeInclusive<u32>)>,
     stockfish: Option<Stockfish>,
-    is_thinking: bool,
+    // Latest problem with the engine process, shown until dismissed
+    engine_banner: Option<String>,
+    match_setup: MatchSetup,
+    engine_match: Option<EngineMatch>,
+    console: EngineConsole,
+    saved_engine_options: SavedOptions,
+    options_editor: OptionsEditor,
+    // The engine's search for its move, running on a thread of its own;
+    // the engine comes back with the result
+    engine_search: Option<BackgroundSearch>,
+    // Analysis of the position after these moves, also on its own thread
+    analysis: Option<(Vec<ChessMove>, BackgroundSearch)>,
+    // Moves the player queued during the engine's turn, played one per
+    // turn as long as they stay legal
+    premoves: Vec<ChessMove>,
+    show_eval: bool,
+    show_wdl: bool,
+    // Latest engine evaluation, from White's point of view
+    eval: Option<Score>,
+    wdl: Option<Wdl>,
+    show_lines: bool,
+    multipv: usize,
+    analysis_limits: SearchLimits,
+    // Candidate lines for the current position and the side they were computed for
+    candidate_lines: Vec<SearchInfo>,
+    candidate_side: PieceColor,
+    // Board shown instead of the game while stepping through a candidate line
+    preview: Option<Board>,
+    hints_enabled: bool,
+    hints_used: u32,
+    hint: Option<ChessMove>,
+    ponder_enabled: bool,
+    // The reply the engine is currently pondering on
+    ponder_expected: Option<ChessMove>,
+    review_budget: ReviewBudget,
+    review: Option<GameReview>,
+    review_receiver: Option<Receiver<ReviewMessage>>,
+    review_progress: (usize, usize),
+    show_moves: bool,
+    // Graph value per position index, filled in by live analysis or a scan
+    ply_evals: Vec<Option<f32>>,
+    // Game ply shown by the preview when it was opened from the move list
+    viewed_ply: Option<usize>,
 }
 
 impl ChessApp {
     pub fn new(_cc: &eframe::CreationContext<'_>) -> Self {
+        let mut profiles: Vec<DifficultyProfile> = Difficulty::iter().map(Difficulty::profile).collect();
+        match difficulty::load_profiles(Path::new(PROFILES_PATH)) {
+            Ok(custom) => profiles.extend(custom),
+            Err(err) => eprintln!("Failed to read {}: {}", PROFILES_PATH, err),
+        }
+        let saved_engine_options = engine_options::load_engine_options(Path::new(ENGINE_OPTIONS_PATH)).unwrap_or_else(|err| {
+            eprintln!("Failed to read {}: {}", ENGINE_OPTIONS_PATH, err);
+            SavedOptions::new()
+        });
+
         Self {
-            board: Board::new(),
+            screen: Screen::Menu,
+            game_mode: GameMode::TwoPlayer,
+            controller: GameController::new(Game::new(), None),
+            start_position: None,
+            editor: BoardEditor::default(),
             selected_position: None,
             possible_moves: Vec::new(),
-            board_flipped: true,
-            game_state: GameState::InProgress,
-            game_mode: None,
-            difficulty: Difficulty::Medium,
+            board_flipped: false,
+            move_input: MoveInput::default(),
+            clock_setup: ClockSetup::default(),
+            resign_threshold: Some(800),
+            hopeless_searches: 0,
+            profiles,
+            profile_index: Difficulty::Medium as usize,
+            profile_draft: DifficultyProfile::new("Custom"),
             player_color: PlayerColor::White,
+            // Without the Stockfish binary only the built-in engine can play
+            engine_kind: if Path::new(STOCKFISH_PATH).exists() { EngineKind::Stockfish } else { EngineKind::BuiltIn },
+            engine_elo_range: None,
             stockfish: None,
-            is_thinking: false,
+            engine_banner: None,
+            match_setup: MatchSetup::default(),
+            engine_match: None,
+            console: EngineConsole::default(),
+            saved_engine_options,
+            options_editor: OptionsEditor::default(),
+            engine_search: None,
+            analysis: None,
+            premoves: Vec::new(),
+            show_eval: false,
+            show_wdl: false,
+            eval: None,
+            wdl: None,
+            show_lines: false,
+            multipv: 3,
+            analysis_limits: SearchLimits::movetime(1000),
+            candidate_lines: Vec::new(),
+            candidate_side: PieceColor::White,
+            preview: None,
+            hints_enabled: true,
+            hints_used: 0,
+            hint: None,
+            ponder_enabled: false,
+            ponder_expected: None,
+            review_budget: ReviewBudget::Depth(14),
+            review: None,
+            review_receiver: None,
+            review_progress: (0, 0),
+            show_moves: true,
+            ply_evals: Vec::new(),
+            viewed_ply: None,
         }
     }
 
     fn draw_board(&mut self, ui: &mut egui::Ui) {
         let available_size = ui.available_size();
-        let board_size = available_size.x.min(available_size.y);
+        let bar_space = if self.show_eval { EVAL_BAR_WIDTH + 8.0 } else { 0.0 };
+        let clock_space = if self.controller.clock().is_some() { CLOCK_WIDTH + 8.0 } else { 0.0 };
+        let board_size = (available_size.x - bar_space - clock_space).min(available_size.y);
         let square_size = board_size / 8.0;
 
         let board_rect = Rect::from_min_size(
             Pos2::new(
-                (available_size.x - board_size) / 2.0,
+                (available_size.x - board_size + bar_space - clock_space) / 2.0,
                 (available_size.y - board_size) / 2.0,
             ),
             Vec2::new(board_size, board_size),
         );
 
+        if self.show_eval {
+            let bar_rect = Rect::from_min_size(
+                Pos2::new(board_rect.left() - bar_space, board_rect.top()),
+                Vec2::new(EVAL_BAR_WIDTH, board_size),
+            );
+            eval_bar::draw_eval_bar(ui, bar_rect, self.eval, self.board_flipped);
+        }
+
+        if let Some(clock) = self.controller.clock() {
+            // Each clock sits level with its own side of the board
+            let (top, bottom) = if self.board_flipped {
+                (PieceColor::White, PieceColor::Black)
+            } else {
+                (PieceColor::Black, PieceColor::White)
+            };
+            let left = board_rect.right() + 8.0;
+            for (side, y) in [(top, board_rect.top()), (bottom, board_rect.bottom() - CLOCK_HEIGHT)] {
+                let rect = Rect::from_min_size(Pos2::new(left, y), Vec2::new(CLOCK_WIDTH, CLOCK_HEIGHT));
+                let running = clock.running_side() == Some(side);
+                let flagged = self.controller.outcome().is_some_and(|outcome| {
+                    matches!(outcome.termination, Termination::Timeout | Termination::TimeoutVsInsufficientMaterial)
+                        && self.controller.board().current_turn() == side
+                });
+                clock_display::draw_clock(ui, rect, clock.remaining(side), running, flagged, clock.delay_left());
+            }
+        }
+
         let painter = ui.painter();
+        let board = self.preview.as_ref().unwrap_or(self.controller.board());
 
         // Draw board squares
         for rank in 0..8 {
             for file in 0..8 {
-                let visual_rank = if !self.board_flipped { 7 - rank } else { rank };
-                let visual_file = if !self.board_flipped { 7 - file } else { file };
-
                 let color = if (rank + file) % 2 == 0 {
                     Color32::from_rgb(240, 217, 181)
                 } else {
                     Color32::from_rgb(181, 136, 99)
                 };
 
-                let pos = Pos2::new(
-                    board_rect.left() + visual_file as f32 * square_size,
-                    board_rect.top() + visual_rank as f32 * square_size,
-                );
-
-                let rect = Rect::from_min_size(pos, Vec2::new(square_size, square_size));
+                let rect = self.square_rect(board_rect, Position::new(rank, file));
                 painter.rect_filled(rect, 0.0, color);
 
                 // Highlight king in check
-                if matches!(self.game_state, GameState::Check | GameState::Checkmate) {
-                    if let Some(king_pos) = self.board.get_king_position(self.board.current_turn()) {
-                        if king_pos.rank == rank && king_pos.file == file {
-                            painter.rect_filled(rect, 0.0, Color32::from_rgba_premultiplied(255, 0, 0, 60));
-                        }
-                    }
+                if self.preview.is_none()
+                    && matches!(self.controller.state(), GameState::Check | GameState::Checkmate)
+                    && let Some(king_pos) = board.get_king_position(board.current_turn())
+                    && king_pos.rank == rank
+                    && king_pos.file == file
+                {
+                    painter.rect_filled(rect, 0.0, Color32::from_rgba_premultiplied(255, 0, 0, 60));
                 }
 
                 // Highlight selected square
-                if let Some(selected) = self.selected_position {
-                    if selected.rank == rank && selected.file == file {
-                        painter.rect_stroke(rect, 0.0, Stroke::new(2.0, Color32::YELLOW));
-                    }
+                if let Some(selected) = self.selected_position && selected.rank == rank && selected.file == file {
+                    painter.rect_stroke(rect, 0.0, Stroke::new(2.0, Color32::YELLOW));
                 }
 
                 // Highlight possible moves
@@ -93,55 +264,62 @@ impl ChessApp {
                 }
 
                 // Draw pieces
-                if let Some(piece) = self.board.get_piece(Position::new(rank, file)) {
-                    self.draw_piece(painter, rect, piece);
+                if let Some(piece) = board.get_piece(Position::new(rank, file)) {
+                    draw_piece(painter, rect, piece);
                 }
             }
         }
 
-        // Handle clicks
+        if self.preview.is_none() {
+            for premove in &self.premoves {
+                let from = self.square_rect(board_rect, premove.from).center();
+                let to = self.square_rect(board_rect, premove.to).center();
+                draw_arrow(painter, from, to, square_size, Color32::from_rgba_unmultiplied(60, 110, 220, 170));
+            }
+        }
+
+        if let Some(hint) = self.hint && self.preview.is_none() {
+            let from = self.square_rect(board_rect, hint.from).center();
+            let to = self.square_rect(board_rect, hint.to).center();
+            draw_arrow(painter, from, to, square_size, Color32::from_rgba_unmultiplied(40, 160, 60, 180));
+        }
+
+        // Handle clicks (the board is read-only while previewing a line)
         let response = ui.allocate_rect(board_rect, Sense::click());
-        if response.clicked() {
-            if let Some(mouse_pos) = response.interact_pointer_pos() {
-                let file = ((mouse_pos.x - board_rect.left()) / square_size) as usize;
-                let rank = ((mouse_pos.y - board_rect.top()) / square_size) as usize;
+        if response.secondary_clicked() {
+            self.cancel_premoves();
+        }
+        if response.clicked() && self.preview.is_none() && let Some(mouse_pos) = response.interact_pointer_pos() {
+            let file = ((mouse_pos.x - board_rect.left()) / square_size) as usize;
+            let rank = ((mouse_pos.y - board_rect.top()) / square_size) as usize;
 
-                let actual_rank = if !self.board_flipped { 7 - rank } else { rank };
-                let actual_file = if !self.board_flipped { 7 - file } else { file };
+            let actual_rank = if !self.board_flipped { 7 - rank } else { rank };
+            let actual_file = if !self.board_flipped { 7 - file } else { file };
 
-                self.handle_square_click(Position::new(actual_rank, actual_file));
-            }
+            self.handle_square_click(Position::new(actual_rank, actual_file));
         }
     }
 
-    fn draw_piece(&self, painter: &egui::Painter, rect: Rect, piece: Piece) {
-        let text = match piece.piece_type {
-            PieceType::King => "♚",
-            PieceType::Queen => "♛",
-            PieceType::Rook => "♜",
-            PieceType::Bishop => "♝",
-            PieceType::Knight => "♞",
-            PieceType::Pawn => "♟",
-        };
+    fn square_rect(&self, board_rect: Rect, pos: Position) -> Rect {
+        let square_size = board_rect.width() / 8.0;
+        let visual_rank = if !self.board_flipped { 7 - pos.rank } else { pos.rank };
+        let visual_file = if !self.board_flipped { 7 - pos.file } else { pos.file };
 
-        let font_size = rect.height() * 0.8;
-        let font = FontId::monospace(font_size);
-        
-        painter.text(
-            rect.center(),
-            Align2::CENTER_CENTER,
-            text,
-            font,
-            if piece.color == PieceColor::White {
-                Color32::WHITE
-            } else {
-                Color32::BLACK
-            },
-        );
+        Rect::from_min_size(
+            Pos2::new(
+                board_rect.left() + visual_file as f32 * square_size,
+                board_rect.top() + visual_rank as f32 * square_size,
+            ),
+            Vec2::new(square_size, square_size),
+        )
     }
 
     fn handle_square_click(&mut self, pos: Position) {
-        if self.game_state == GameState::Checkmate {
+        if self.screen != Screen::Playing || self.controller.is_over() || self.game_mode == GameMode::EngineVsEngine {
+            return;
+        }
+        if self.premoving() {
+            self.handle_premove_click(pos);
             return;
         }
 
@@ -149,148 +327,1557 @@ impl ChessApp {
             if let Some(mv) = self.possible_moves.iter()
                 .find(|m| m.from == selected_pos && m.to == pos) 
             {
-                self.board.make_move(*mv);
-                self.game_state = self.board.check_game_state();
+                self.apply_move(*mv);
             }
             self.selected_position = None;
             self.possible_moves.clear();
-        } else if let Some(piece) = self.board.get_piece(pos) {
-            if piece.color == self.board.current_turn() {
-                self.selected_position = Some(pos);
-                self.possible_moves = self.board.get_valid_moves(pos);
+        } else if let Some(piece) = self.controller.board().get_piece(pos)
+            && piece.color == self.controller.board().current_turn()
+        {
+            self.selected_position = Some(pos);
+            self.possible_moves = self.controller.board().get_valid_moves(pos);
+        }
+    }
+
+    /// Whether clicks on the board queue premoves: against the engine,
+    /// while it is the engine's turn.
+    fn premoving(&self) -> bool {
+        self.game_mode == GameMode::VsStockfish && self.controller.side_to_move() != self.player_color.to_piece_color()
+    }
+
+    /// The position as it would be after the queued premoves, for choosing
+    /// the next one.
+    fn premove_board(&self) -> Board {
+        let mut board = self.controller.board().clone();
+        for premove in &self.premoves {
+            board.make_move(*premove);
+        }
+        board
+    }
+
+    fn handle_premove_click(&mut self, pos: Position) {
+        if let Some(selected_pos) = self.selected_position {
+            if let Some(mv) = self.possible_moves.iter().find(|m| m.from == selected_pos && m.to == pos) {
+                self.premoves.push(*mv);
             }
+            self.selected_position = None;
+            self.possible_moves.clear();
+            return;
+        }
+        let board = self.premove_board();
+        if board.get_piece(pos).is_some_and(|piece| piece.color == self.player_color.to_piece_color()) {
+            self.selected_position = Some(pos);
+            self.possible_moves = board.premoves(pos);
         }
     }
 
-    fn draw_game_status(&self, ui: &mut egui::Ui) {
-        ui.vertical(|ui| {
+    fn cancel_premoves(&mut self) {
+        self.premoves.clear();
+        if self.premoving() {
+            self.selected_position = None;
+            self.possible_moves.clear();
+        }
+    }
+
+    /// Plays the first queued premove now that it is the player's turn. If
+    /// it turns out to be illegal, the whole queue goes.
+    fn play_premove(&mut self) {
+        if self.premoves.is_empty() || self.premoving() || self.controller.is_over() {
+            return;
+        }
+        let premove = self.premoves.remove(0);
+        if self.controller.board().get_valid_moves(premove.from).contains(&premove) {
+            self.selected_position = None;
+            self.possible_moves.clear();
+            self.apply_move(premove);
+        } else {
+            self.premoves.clear();
+        }
+    }
+
+    fn apply_move(&mut self, mv: ChessMove) {
+        if self.controller.make_move(mv).is_err() {
+            return;
+        }
+        self.candidate_lines.clear();
+        self.preview = None;
+        self.hint = None;
+    }
+
+    /// A fresh clock for the chosen time control, if there is one.
+    fn new_clock(&self) -> Option<ChessClock> {
+        self.clock_setup.time_controls().map(|(white, black)| ChessClock::new(white, black))
+    }
+
+    /// Once the controller has recorded a result: stops whatever was
+    /// running for the game and moves on to the result screen.
+    fn finish_game(&mut self) {
+        self.selected_position = None;
+        self.possible_moves.clear();
+        self.hint = None;
+        self.premoves.clear();
+        if let Some(stockfish) = &mut self.stockfish {
+            stockfish.stop_ponder();
+        }
+        self.ponder_expected = None;
+        self.screen = Screen::GameOver;
+    }
+
+    /// Asks the computer opponent whether it takes a draw. It runs a short
+    /// search of the current position and accepts unless it thinks it is
+    /// better.
+    fn offer_draw_to_engine(&mut self) {
+        let player = self.player_color.to_piece_color();
+        let Some(stockfish) = &mut self.stockfish else {
+            return;
+        };
+        self.controller.offer_draw(player);
+        // The search below ends any pondering
+        self.ponder_expected = None;
+        stockfish.set_position_with_moves(&self.controller.game().start_board().to_fen(), self.controller.game().moves());
+        stockfish.get_best_move(&SearchLimits::movetime(300));
+        let Some(info) = stockfish.last_info() else {
+            return;
+        };
+        // The search was from the player's side, so the engine's score is the opposite
+        let engine_better = match info.score {
+            Score::Cp(cp) => -cp > DRAW_ACCEPT_CP,
+            Score::Mate(n) => n < 0,
+        };
+        if !engine_better {
+            self.controller.accept_draw(player.opposite());
+        }
+    }
+
+    /// Limits for the computer opponent's next search: its profile's, or
+    /// the game clock when there is one.
+    fn engine_limits(&self) -> SearchLimits {
+        let profile = &self.profiles[self.profile_index];
+        let Some(clock) = self.controller.clock() else {
+            return profile.search_limits();
+        };
+        let engine_side = self.player_color.to_piece_color().opposite();
+        player::clock_limits(clock, engine_side)
+            .with_depth(profile.depth)
+            .with_nodes(profile.nodes)
+    }
+
+    /// Runs a short full-strength search for the human's side and shows the
+    /// result as an arrow. The opponent's skill level is restored before its
+    /// next move, which sets it every time.
+    fn request_hint(&mut self) {
+        let Some(stockfish) = &mut self.stockfish else {
+            return;
+        };
+        stockfish.set_strength(EngineStrength::Skill(20));
+        stockfish.set_position_with_moves(&self.controller.game().start_board().to_fen(), self.controller.game().moves());
+        if let Some(mv) = stockfish.get_best_move(&SearchLimits::movetime(300)) {
+            self.hint = self.controller.board().find_legal_move(&mv);
+            if self.hint.is_some() {
+                self.hints_used += 1;
+            }
+        }
+    }
+
+    fn engine_path(&self) -> &'static str {
+        match self.engine_kind {
+            EngineKind::Stockfish => STOCKFISH_PATH,
+            EngineKind::BuiltIn => BUILTIN_ENGINE,
+        }
+    }
+
+    fn start_engine(&mut self) {
+        match Stockfish::new(self.engine_path()) {
+            Ok(mut stockfish) => {
+                let rejected = engine_options::apply_saved(&mut stockfish, &self.saved_engine_options);
+                stockfish.set_show_wdl(self.show_wdl);
+                stockfish.set_ponder(self.ponder_enabled);
+                self.engine_elo_range = stockfish.elo_range().map(|range| (self.engine_kind, range));
+                self.stockfish = Some(stockfish);
+                self.engine_banner = (!rejected.is_empty())
+                    .then(|| format!("Ignored saved engine options: {}", rejected.join("; ")));
+            }
+            Err(err) => self.engine_banner = Some(format!("Could not start the engine: {}", err)),
+        }
+    }
+
+    /// Options edited in the options window that the game also has toggles for.
+    fn sync_engine_toggles(&mut self) {
+        let Some(stockfish) = &self.stockfish else {
+            return;
+        };
+        if let Some(value) = stockfish.option_value("UCI_ShowWDL") {
+            self.show_wdl = value == "true";
+        }
+        if let Some(value) = stockfish.option_value("Ponder") {
+            self.ponder_enabled = value == "true";
+        }
+    }
+
+    /// Turns supervisor events into the banner text.
+    fn poll_engine_events(&mut self) {
+        let mut events = Vec::new();
+        if let Some(stockfish) = &mut self.stockfish {
+            events.extend(stockfish.take_events());
+        }
+        if let Some(engine_match) = &mut self.engine_match {
+            events.extend(engine_match.take_events());
+        }
+        for event in events {
+            match (&event, self.engine_banner.take()) {
+                (EngineEvent::Restarted, previous) => {
+                    // Whatever the engine was pondering was lost with it
+                    self.ponder_expected = None;
+                    self.engine_banner = Some(match previous {
+                        Some(cause) => format!("{} - restarted", cause),
+                        None => event.to_string(),
+                    });
+                }
+                _ => self.engine_banner = Some(event.to_string()),
+            }
+        }
+    }
+
+    fn draw_engine_banner(&mut self, ctx: &egui::Context) {
+        let Some(banner) = self.engine_banner.clone() else {
+            return;
+        };
+        let engine_dead = self.stockfish.as_ref().is_some_and(|stockfish| !stockfish.is_alive());
+        egui::TopBottomPanel::top("engine_banner").show(ctx, |ui| {
             ui.horizontal(|ui| {
-                ui.label(format!("Current turn: {}", self.board.current_turn()));
-                
-                match self.game_state {
-                    GameState::Check => { ui.label("Check!"); }
-                    GameState::Checkmate => { ui.label("Checkmate! Click to restart"); }
-                    GameState::Stalemate => { ui.label("Stalemate!"); }
-                    GameState::InProgress => {}
+                ui.colored_label(Color32::from_rgb(220, 80, 60), banner);
+                if engine_dead && ui.button("Restart engine").clicked() {
+                    self.start_engine();
+                }
+                if ui.small_button("✖").clicked() {
+                    self.engine_banner = None;
                 }
             });
-            
-            ui.label(format!("FEN: {}", self.board.to_fen()));
         });
     }
 
-    fn reset_game(&mut self) {
-        self.board = Board::new();
-        self.board_flipped = true;
-        self.game_state = GameState::InProgress;
+    /// Starts the engine's search for its move on a thread of its own, so
+    /// the board stays usable while it thinks. The result is picked up by
+    /// [`ChessApp::poll_engine_move`].
+    fn start_engine_move(&mut self) {
+        let limits = self.engine_limits();
+        let strength = self.profiles[self.profile_index].strength;
+        let Some(stockfish) = self.stockfish.take() else {
+            return;
+        };
+        let start_fen = self.controller.game().start_board().to_fen();
+        let moves = self.controller.game().moves().to_vec();
+
+        // If the engine pondered on the move that was just played, its
+        // search is already under way
+        let expected = self.ponder_expected.take();
+        let ponder_hit = stockfish.is_pondering() && expected.is_some() && moves.last() == expected.as_ref();
+
+        self.engine_search = Some(BackgroundSearch::spawn(stockfish, move |stockfish| {
+            if ponder_hit {
+                stockfish.ponder_hit()
+            } else {
+                stockfish.set_strength(strength);
+                stockfish.set_position_with_moves(&start_fen, &moves);
+                stockfish.get_best_move(&limits)
+            }
+        }));
+    }
+
+    /// Stops a search still running on the engine thread and takes the
+    /// engine back, dropping the move or lines it found.
+    fn recall_engine(&mut self) {
+        if let Some(search) = self.engine_search.take() {
+            self.stockfish = search.cancel();
+        }
+        if let Some((_, search)) = self.analysis.take() {
+            self.stockfish = search.cancel();
+        }
+    }
+
+    /// Takes the engine back once its search is done and plays its move,
+    /// then the player's next premove if one is queued.
+    fn poll_engine_move(&mut self) {
+        let Some(search) = &self.engine_search else {
+            return;
+        };
+        let (stockfish, best) = match search.try_finish() {
+            Ok(result) => result,
+            Err(TryRecvError::Empty) => return,
+            Err(TryRecvError::Disconnected) => {
+                self.engine_search = None;
+                self.engine_banner = Some("The engine thread stopped unexpectedly".to_string());
+                return;
+            }
+        };
+        self.engine_search = None;
+        self.stockfish = Some(stockfish);
+        // The game may have ended on the clock while the engine thought
+        if self.screen != Screen::Playing || !self.engine_to_move() {
+            return;
+        }
+
+        // A search that comes back without a legal move would only be
+        // started again on the next frame, so the engine forfeits instead
+        let side = self.controller.board().current_turn();
+        let mv = match &best {
+            Some(uci) => {
+                let mv = self.controller.board().find_legal_move(uci);
+                mv.ok_or_else(|| format!("The engine played the illegal move {}", uci))
+            }
+            None => Err("The engine didn't return a move".to_string()),
+        };
+        let mv = match mv {
+            Ok(mv) => mv,
+            Err(failure) => {
+                self.engine_banner = Some(format!("{} and forfeits the game", failure));
+                self.controller.resign(side);
+                return;
+            }
+        };
+        let Some(stockfish) = &self.stockfish else {
+            return;
+        };
+        let engine_score = stockfish.last_info().map(|info| info.score);
+        let info = stockfish
+            .last_info()
+            .map(|info| (info.score.for_white(side), info.wdl.map(|wdl| wdl.for_white(side))));
+        let ponder_move = stockfish.ponder_move().map(String::from);
+
+        let hopeless = match (engine_score, self.resign_threshold) {
+            (Some(Score::Cp(cp)), Some(threshold)) => cp <= -threshold,
+            (Some(Score::Mate(n)), Some(_)) => n < 0,
+            _ => false,
+        };
+        self.hopeless_searches = if hopeless { self.hopeless_searches + 1 } else { 0 };
+        if self.hopeless_searches >= RESIGN_AFTER_SEARCHES {
+            self.controller.resign(side);
+            return;
+        }
+
+        if let Some((score, wdl)) = info {
+            self.eval = Some(score);
+            self.wdl = wdl;
+            self.record_eval(self.controller.game().moves().len(), score);
+        }
+        self.apply_move(mv);
+        // A half-chosen premove was picked from the old position
         self.selected_position = None;
         self.possible_moves.clear();
-        self.is_thinking = false;
+
+        if self.ponder_enabled {
+            self.start_pondering(ponder_move);
+        }
+        self.play_premove();
     }
-    
-    fn show_difficulty_selection(&mut self, ui: &mut egui::Ui) {
-        ui.label("Select Difficulty:");
-        for difficulty in Difficulty::iter() {
-            if ui.radio(self.difficulty == difficulty, format!("{:?}", difficulty)).clicked() {
-                self.difficulty = difficulty;
+
+    /// Lets the engine search the position after its expected reply while
+    /// the human thinks.
+    fn start_pondering(&mut self, ponder_move: Option<String>) {
+        if self.controller.outcome().is_some() {
+            return;
+        }
+        let Some(expected) = ponder_move.and_then(|uci| self.controller.board().find_legal_move(&uci)) else {
+            return;
+        };
+        let limits = self.engine_limits();
+        let Some(stockfish) = &mut self.stockfish else {
+            return;
+        };
+
+        let mut moves = self.controller.game().moves().to_vec();
+        moves.push(expected);
+        stockfish.set_position_with_moves(&self.controller.game().start_board().to_fen(), &moves);
+        stockfish.start_ponder(&limits);
+        self.ponder_expected = Some(expected);
+    }
+
+    /// Starts a MultiPV search of the current position at full strength,
+    /// whatever the opponent plays at, on a thread of its own. The lines
+    /// are picked up by [`ChessApp::poll_analysis`].
+    fn analyze_position(&mut self) {
+        if let Some((_, search)) = self.analysis.take() {
+            self.stockfish = search.cancel();
+        }
+        if self.stockfish.is_none() {
+            self.start_engine();
+        }
+        let Some(stockfish) = self.stockfish.take() else {
+            return;
+        };
+        let show_wdl = self.show_wdl;
+        let multipv = self.multipv;
+        let limits = self.analysis_limits.clone();
+        let strength = self.profiles[self.profile_index].strength;
+        let start_fen = self.controller.game().start_board().to_fen();
+        let moves = self.controller.game().moves().to_vec();
+        let search_moves = moves.clone();
+        let search = BackgroundSearch::spawn(stockfish, move |stockfish| {
+            stockfish.set_strength(EngineStrength::Skill(20));
+            stockfish.set_show_wdl(show_wdl);
+            stockfish.set_multipv(multipv);
+            stockfish.set_position_with_moves(&start_fen, &search_moves);
+            let best = stockfish.get_best_move(&limits);
+            // Games against the engine are played with a single line, at the profile's strength
+            stockfish.set_multipv(1);
+            stockfish.set_strength(strength);
+            best
+        });
+        self.analysis = Some((moves, search));
+    }
+
+    /// Takes the engine back once the analysis is done and shows its
+    /// lines, unless the game has moved on meanwhile.
+    fn poll_analysis(&mut self) {
+        let Some((_, search)) = &self.analysis else {
+            return;
+        };
+        let stockfish = match search.try_finish() {
+            Ok((stockfish, _)) => stockfish,
+            Err(TryRecvError::Empty) => return,
+            Err(TryRecvError::Disconnected) => {
+                self.analysis = None;
+                self.engine_banner = Some("The engine thread stopped unexpectedly".to_string());
+                return;
+            }
+        };
+        let Some((moves, _)) = self.analysis.take() else {
+            return;
+        };
+        if moves == self.controller.game().moves() {
+            self.candidate_lines = stockfish.lines().to_vec();
+            self.candidate_side = self.controller.board().current_turn();
+            if let Some(best) = self.candidate_lines.first() {
+                let score = best.score.for_white(self.candidate_side);
+                self.record_eval(moves.len(), score);
             }
         }
+        self.stockfish = Some(stockfish);
     }
-    
-    fn show_color_selection(&mut self, ui: &mut egui::Ui) {
-        ui.label("Select Your Color:");
-        for color in PlayerColor::iter() {
-            if ui.radio(self.player_color == color, format!("{:?}", color)).clicked() {
-                self.player_color = color;
+
+    /// The move entry under the board, usable whenever a click on the
+    /// board could make a move.
+    fn draw_move_input(&mut self, ui: &mut egui::Ui) {
+        let enabled = self.screen == Screen::Playing
+            && self.preview.is_none()
+            && !self.controller.is_over()
+            && self.game_mode != GameMode::EngineVsEngine
+            && !self.engine_to_move();
+        let moves = self.move_input.show(ui, self.controller.board(), enabled);
+        if moves.is_empty() {
+            return;
+        }
+        self.selected_position = None;
+        self.possible_moves.clear();
+        for mv in moves {
+            self.apply_move(mv);
+            if self.controller.is_over() {
+                break;
             }
         }
     }
-}
 
-impl eframe::App for ChessApp {
-    fn update(&mut self, ctx: &egui::Context, _frame: &mut Frame) {
-        egui::CentralPanel::default().show(ctx, |ui| {
-            if let Some(game_mode) = self.game_mode {
-                // Show configuration if in Stockfish mode but not initialized
-                if game_mode == GameMode::VsStockfish && self.stockfish.is_none() {
-                    ui.vertical_centered(|ui| {
-                        ui.heading("Configure Computer Opponent");
-                        ui.separator();
-                        self.show_difficulty_selection(ui);
-                        self.show_color_selection(ui);
-                        
-                        if ui.button("Start Game").clicked() {
-                            self.board_flipped = self.player_color == PlayerColor::Black;
-                            self.stockfish = Some(Stockfish::new("./src/chess/stockfish/stockfish-ubuntu-x86-64-avx2"));
-                        }
-                    });
-                } else {
-                    // Game in progress
-                    self.draw_game_status(ui);
-                    ui.separator();
-                    self.draw_board(ui);
-                
-                    if self.game_state == GameState::Checkmate && ui.button("New Game").clicked() {
-                        self.reset_game();
+    fn play_line(&mut self, pv: &[String]) {
+        for uci in pv {
+            match self.controller.board().find_legal_move(uci) {
+                Some(mv) => self.apply_move(mv),
+                None => break,
+            }
+            if self.controller.outcome().is_some() {
+                break;
+            }
+        }
+    }
+
+    fn draw_lines_panel(&mut self, ui: &mut egui::Ui) {
+        ui.heading("Engine lines");
+        ui.add(egui::Slider::new(&mut self.multipv, 1..=5).text("lines"));
+        ui.horizontal(|ui| {
+            let limits = &mut self.analysis_limits;
+            let kind = if limits.mate.is_some() {
+                "Mate in"
+            } else if limits.depth.is_some() {
+                "Depth"
+            } else if limits.nodes.is_some() {
+                "Nodes"
+            } else {
+                "Time"
+            };
+            egui::ComboBox::from_id_source("analysis_limit")
+                .selected_text(kind)
+                .show_ui(ui, |ui| {
+                    if ui.selectable_label(kind == "Time", "Time").clicked() {
+                        *limits = SearchLimits::movetime(1000);
                     }
-                }
-                
-                // Handle AI move
-                if let Some(stockfish) = &mut self.stockfish {
-                    if game_mode == GameMode::VsStockfish 
-                        && self.board.current_turn() != self.player_color.to_piece_color()
-                        && self.game_state == GameState::InProgress
-                        && !self.is_thinking {
-                        self.is_thinking = true;
-                        let board = self.board.clone();
-                        stockfish.set_skill_level(match self.difficulty {
-                            Difficulty::Easy => 5,
-                            Difficulty::Medium => 15,
-                            Difficulty::Hard => 20,
-                        });
-                        stockfish.set_position(&board.to_fen());
-                        
-                        if let Some(mv) = stockfish.get_best_move(1000) {
-                            self.board.make_move(ChessMove::from_uci(&mv).unwrap());
-                            self.game_state = self.board.check_game_state();
+                    if ui.selectable_label(kind == "Depth", "Depth").clicked() {
+                        *limits = SearchLimits::depth(18);
+                    }
+                    if ui.selectable_label(kind == "Nodes", "Nodes").clicked() {
+                        *limits = SearchLimits::nodes(1_000_000);
+                    }
+                    if ui.selectable_label(kind == "Mate in", "Mate in").clicked() {
+                        // Capped by time, as a mate search may never finish otherwise
+                        *limits = SearchLimits { movetime_ms: Some(5000), ..SearchLimits::mate(3) };
+                    }
+                });
+            if let Some(ms) = &mut limits.movetime_ms {
+                ui.add(egui::DragValue::new(ms).speed(50).clamp_range(50..=60_000).suffix(" ms"));
+            }
+            if let Some(depth) = &mut limits.depth {
+                ui.add(egui::DragValue::new(depth).clamp_range(1..=60));
+            }
+            if let Some(nodes) = &mut limits.nodes {
+                ui.add(egui::DragValue::new(nodes).speed(10_000).clamp_range(1_000..=100_000_000));
+            }
+            if let Some(moves) = &mut limits.mate {
+                ui.add(egui::DragValue::new(moves).clamp_range(1..=20));
+            }
+
+            let idle = self.engine_search.is_none() && self.analysis.is_none();
+            if ui.add_enabled(idle, egui::Button::new("Analyze")).clicked() {
+                self.preview = None;
+                self.analyze_position();
+            }
+            if self.analysis.is_some() {
+                ui.spinner();
+            }
+        });
+
+        if self.preview.is_some() && ui.button("Back to game").clicked() {
+            self.preview = None;
+        }
+        ui.separator();
+
+        let mut preview = None;
+        let mut line_to_play = None;
+
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            for info in &self.candidate_lines {
+                let score = info.score.for_white(self.candidate_side);
+                let sans = self.controller.board().uci_line_to_san(&info.pv);
+
+                ui.horizontal(|ui| {
+                    ui.strong(format!("#{}  {}", info.multipv, eval_bar::score_label(score)));
+                    ui.label(format!("depth {}", info.depth));
+                    if ui.small_button("Play this line").clicked() {
+                        line_to_play = Some(info.pv.clone());
+                    }
+                });
+
+                // Each move is clickable and previews the line up to that point
+                ui.horizontal_wrapped(|ui| {
+                    let numbered = format_numbered_line(
+                        &sans,
+                        self.controller.board().fullmove_number(),
+                        self.controller.board().current_turn() == PieceColor::White,
+                    );
+                    let mut ply = 0;
+                    for token in numbered.split(' ') {
+                        if token.ends_with('.') {
+                            ui.label(token);
+                            continue;
+                        }
+                        if ui.link(token).clicked() {
+                            preview = Some(info.pv[..=ply].to_vec());
                         }
-                        self.is_thinking = false;
+                        ply += 1;
                     }
-                }
-            } else {
-                // Game mode selection
-                ui.vertical_centered(|ui| {
-                    ui.heading("Select Game Mode");
-                    ui.add_space(20.0);
-                    
-                    egui::Grid::new("game_mode_grid")
-                        .spacing([40.0, 20.0])
-                        .show(ui, |ui| {
-                            if ui.button("Two Players (Local)").on_hover_text("Play against another person on this device").clicked() {
-                                self.game_mode = Some(GameMode::TwoPlayer);
-                                self.board_flipped=false;
-                            }
-                            
-                            if ui.button("Play vs Stockfish").on_hover_text("Challenge the computer opponent").clicked() {
-                                self.game_mode = Some(GameMode::VsStockfish);
-                                // Reset stockfish instance when selecting mode
-                                self.stockfish = None;
-                            }
-                            ui.end_row();
-                        });
                 });
+                ui.separator();
             }
         });
+
+        if let Some(moves) = preview {
+            let mut board = self.controller.board().clone();
+            for uci in &moves {
+                if let Some(mv) = board.find_legal_move(uci) {
+                    board.make_move(mv);
+                }
+            }
+            self.preview = Some(board);
+        }
+        if let Some(pv) = line_to_play {
+            self.play_line(&pv);
+        }
+    }
+
+    fn start_review(&mut self) {
+        self.review = None;
+        self.review_progress = (0, self.controller.game().moves().len() + 1);
+        self.review_receiver = Some(review::spawn_review(self.engine_path(), self.saved_engine_options.clone(), self.controller.game().clone(), self.review_budget));
+    }
+
+    fn poll_review(&mut self, ctx: &egui::Context) {
+        let Some(receiver) = &self.review_receiver else {
+            return;
+        };
+
+        loop {
+            match receiver.try_recv() {
+                Ok(ReviewMessage::Progress { done, total }) => self.review_progress = (done, total),
+                Ok(ReviewMessage::Finished(review)) => {
+                    self.ply_evals = review.evals.iter().map(|eval| Some(eval.graph_value())).collect();
+                    self.review = Some(review);
+                    self.review_receiver = None;
+                    return;
+                }
+                Ok(ReviewMessage::Failed(reason)) => {
+                    self.engine_banner = Some(format!("Review stopped: {}", reason));
+                    self.review_receiver = None;
+                    return;
+                }
+                Err(std::sync::mpsc::TryRecvError::Empty) => break,
+                Err(std::sync::mpsc::TryRecvError::Disconnected) => {
+                    // The worker panicked
+                    self.review_receiver = None;
+                    return;
+                }
+            }
+        }
+        ctx.request_repaint();
+    }
+
+    fn player_names(&self) -> (String, String) {
+        match self.game_mode {
+            GameMode::VsStockfish => {
+                let engine = format!("{} ({})", self.engine_kind, self.profiles[self.profile_index].name);
+                match self.player_color {
+                    PlayerColor::White => ("Player".to_string(), engine),
+                    PlayerColor::Black => (engine, "Player".to_string()),
+                }
+            }
+            GameMode::EngineVsEngine => match &self.engine_match {
+                Some(engine_match) => {
+                    let white = engine_match.white_side();
+                    let name = |index: usize| format!("{} ({})", self.engine_kind, engine_match.sides[index].profile.name);
+                    (name(white), name(1 - white))
+                }
+                None => ("White".to_string(), "Black".to_string()),
+            },
+            _ => ("White".to_string(), "Black".to_string()),
+        }
     }
-}
 
-async fn get_stockfish_move(board: Board, difficulty: Difficulty) -> String {
-    let mut stockfish = Stockfish::new("./src/chess/stockfish/stockfish-ubuntu-x86-64-avx2");
-    stockfish.set_skill_level(match difficulty {
-        Difficulty::Easy => 5,
-        Difficulty::Medium => 15,
-        Difficulty::Hard => 20,
+    fn export_pgn(&self, ctx: &egui::Context) {
+        let (white, black) = self.player_names();
+        let mut tags = vec![("White", white), ("Black", black)];
+        if let Some(outcome) = self.controller.outcome() {
+            tags.push(("Result", outcome.result().to_string()));
+            tags.push(("Termination", outcome.termination.pgn_tag().to_string()));
+        }
+        if let Some(clock) = self.controller.clock() {
+            let (white, black) = (clock.control(PieceColor::White), clock.control(PieceColor::Black));
+            // PGN has no way to give each side its own control
+            let control = if white == black { white.pgn_tag() } else { "?".to_string() };
+            tags.push(("TimeControl", control));
+        }
+        if self.game_mode == GameMode::VsStockfish && self.hints_enabled {
+            tags.push(("Hints", self.hints_used.to_string()));
+        }
+        let annotations = self.review.as_ref().map(|r| r.annotations()).unwrap_or_default();
+        let pgn = write_pgn(self.controller.game(), &tags, &annotations);
+
+        if let Err(err) = std::fs::write("game.pgn", &pgn) {
+            eprintln!("Failed to write game.pgn: {}", err);
+        }
+        ctx.output_mut(|o| o.copied_text = pgn);
+    }
+
+    fn draw_review_window(&mut self, ctx: &egui::Context) {
+        let mut open = true;
+        let mut preview_ply = None;
+
+        egui::Window::new("Game review")
+            .open(&mut open)
+            .default_width(320.0)
+            .show(ctx, |ui| {
+                if self.review_receiver.is_some() {
+                    let (done, total) = self.review_progress;
+                    ui.add(egui::ProgressBar::new(done as f32 / total.max(1) as f32)
+                        .text(format!("Analysing position {}/{}", done, total)));
+                    return;
+                }
+
+                ui.horizontal(|ui| {
+                    let by_depth = matches!(self.review_budget, ReviewBudget::Depth(_));
+                    if ui.radio(by_depth, "Depth").clicked() {
+                        self.review_budget = ReviewBudget::Depth(14);
+                    }
+                    if ui.radio(!by_depth, "Nodes").clicked() {
+                        self.review_budget = ReviewBudget::Nodes(500_000);
+                    }
+                    match &mut self.review_budget {
+                        ReviewBudget::Depth(depth) => ui.add(egui::DragValue::new(depth).clamp_range(1..=30)),
+                        ReviewBudget::Nodes(nodes) => ui.add(egui::DragValue::new(nodes).speed(10_000).clamp_range(1_000..=50_000_000)),
+                    };
+                });
+                ui.horizontal(|ui| {
+                    if ui.button(if self.review.is_some() { "Re-run review" } else { "Start review" }).clicked() {
+                        self.start_review();
+                    }
+                    if ui.button("Export PGN").on_hover_text("Saves game.pgn and copies it to the clipboard").clicked() {
+                        self.export_pgn(ui.ctx());
+                    }
+                    if self.preview.is_some() && ui.button("Back to game").clicked() {
+                        self.preview = None;
+                    }
+                });
+
+                let Some(review) = &self.review else {
+                    return;
+                };
+                let start = self.controller.game().start_board();
+                let black_first = start.current_turn() == PieceColor::Black;
+                ui.separator();
+                ui.label(format!("Accuracy  White {:.1}%  Black {:.1}%", review.white_accuracy, review.black_accuracy));
+                for class in [MoveClass::Inaccuracy, MoveClass::Mistake, MoveClass::Blunder] {
+                    let count = |color: usize| {
+                        review.moves.iter().skip(color ^ black_first as usize).step_by(2).filter(|m| m.class == class).count()
+                    };
+                    ui.label(format!("{}s  White {}  Black {}", class, count(0), count(1)));
+                }
+                ui.separator();
+
+                egui::ScrollArea::vertical().max_height(300.0).show(ui, |ui| {
+                    for (ply, reviewed) in review.moves.iter().enumerate() {
+                        let number = start.fullmove_number() + (ply + black_first as usize) / 2;
+                        let white_move = (ply + black_first as usize).is_multiple_of(2);
+                        let prefix = if white_move { format!("{}.", number) } else { format!("{}...", number) };
+                        let color = match reviewed.class {
+                            MoveClass::Inaccuracy => Color32::from_rgb(230, 200, 60),
+                            MoveClass::Mistake => Color32::from_rgb(230, 140, 40),
+                            MoveClass::Blunder => Color32::from_rgb(220, 60, 60),
+                            _ => ui.visuals().text_color(),
+                        };
+
+                        ui.horizontal(|ui| {
+                            let text = egui::RichText::new(format!("{} {}{}", prefix, reviewed.san, reviewed.class.symbol())).color(color);
+                            if ui.link(text).clicked() {
+                                preview_ply = Some(ply + 1);
+                            }
+                            ui.label(format!("{}", reviewed.class))
+                                .on_hover_text(format!("Winning chances dropped by {:.1}%", reviewed.win_drop));
+                            if let Some(best) = &reviewed.best_san {
+                                ui.label(format!("best: {}", best));
+                            }
+                        });
+                    }
+                });
+            });
+
+        if !open {
+            self.screen = Screen::GameOver;
+        }
+        if let Some(ply) = preview_ply {
+            self.view_ply(ply);
+        }
+    }
+
+    fn record_eval(&mut self, ply: usize, score: Score) {
+        if self.ply_evals.len() <= ply {
+            self.ply_evals.resize(ply + 1, None);
+        }
+        self.ply_evals[ply] = Some(eval_graph::graph_value(score));
+    }
+
+    /// Shows the game position after `ply` half-moves, or the live game
+    /// when that is the latest position.
+    fn view_ply(&mut self, ply: usize) {
+        if ply >= self.controller.game().moves().len() {
+            self.preview = None;
+        } else {
+            self.preview = Some(self.controller.game().board_at(ply));
+            self.viewed_ply = Some(ply);
+        }
+    }
+
+    fn current_ply(&self) -> usize {
+        match self.viewed_ply {
+            Some(ply) if self.preview.is_some() => ply,
+            _ => self.controller.game().moves().len(),
+        }
+    }
+
+    fn draw_moves_panel(&mut self, ui: &mut egui::Ui) {
+        ui.heading("Moves");
+        if self.preview.is_some() && ui.button("Back to game").clicked() {
+            self.preview = None;
+        }
+
+        let mut jump_to = None;
+        let start = self.controller.game().start_board();
+        let black_first = start.current_turn() == PieceColor::Black;
+        let current_ply = self.current_ply();
+
+        egui::ScrollArea::vertical().max_height(ui.available_height() - 200.0).show(ui, |ui| {
+            egui::Grid::new("move_list").num_columns(3).show(ui, |ui| {
+                if black_first {
+                    ui.label(format!("{}.", start.fullmove_number()));
+                    ui.label("...");
+                }
+                for (ply, san) in self.controller.game().san_moves().iter().enumerate() {
+                    let white_move = (ply + black_first as usize).is_multiple_of(2);
+                    if white_move {
+                        ui.label(format!("{}.", start.fullmove_number() + (ply + black_first as usize) / 2));
+                    }
+                    if ui.selectable_label(current_ply == ply + 1, san).clicked() {
+                        jump_to = Some(ply + 1);
+                    }
+                    if !white_move {
+                        ui.end_row();
+                    }
+                }
+            });
+        });
+
+        ui.separator();
+        ui.horizontal(|ui| {
+            ui.strong("Evaluation");
+            if self.review_receiver.is_some() {
+                let (done, total) = self.review_progress;
+                ui.label(format!("scanning {}/{}", done, total));
+            } else if ui.small_button("Scan game").on_hover_text("Evaluate every position in the background").clicked() {
+                self.start_review();
+            }
+        });
+
+        let mut values = self.ply_evals.clone();
+        values.resize(self.controller.game().moves().len() + 1, None);
+        let markers: Vec<GraphMarker> = self
+            .review
+            .iter()
+            .flat_map(|review| review.moves.iter().enumerate())
+            .filter_map(|(ply, reviewed)| match reviewed.class {
+                MoveClass::Blunder => Some(GraphMarker { ply: ply + 1, color: Color32::from_rgb(220, 60, 60) }),
+                MoveClass::Mistake => Some(GraphMarker { ply: ply + 1, color: Color32::from_rgb(230, 140, 40) }),
+                _ => None,
+            })
+            .collect();
+
+        if let Some(ply) = eval_graph::draw_eval_graph(ui, &values, &markers, current_ply) {
+            jump_to = Some(ply);
+        }
+
+        if let Some(ply) = jump_to {
+            self.view_ply(ply);
+        }
+    }
+
+    fn draw_game_status(&mut self, ui: &mut egui::Ui) {
+        ui.vertical(|ui| {
+            ui.horizontal(|ui| {
+                ui.label(format!("Current turn: {}", self.controller.board().current_turn()));
+                
+                match (self.controller.outcome(), self.controller.state()) {
+                    (Some(outcome), _) => { ui.label(outcome.to_string()); }
+                    (None, GameState::Check) => { ui.label("Check!"); }
+                    _ => {}
+                }
+            });
+            
+            ui.label(format!("FEN: {}", self.controller.board().to_fen()));
+
+            if self.screen == Screen::Playing && self.game_mode != GameMode::EngineVsEngine {
+                self.draw_game_actions(ui);
+            }
+            if matches!(self.screen, Screen::GameOver | Screen::Review) {
+                ui.horizontal(|ui| {
+                    if ui.button("New Game").clicked() {
+                        self.start_game();
+                    }
+                    if self.screen == Screen::GameOver && ui.button("Review game").clicked() {
+                        self.screen = Screen::Review;
+                    }
+                    if ui.button("Main menu").clicked() {
+                        self.return_to_menu();
+                    }
+                });
+            }
+
+            if self.game_mode == GameMode::VsStockfish && (self.stockfish.is_some() || self.engine_search.is_some()) {
+                ui.horizontal(|ui| {
+                    let players_turn = self.controller.board().current_turn() == self.player_color.to_piece_color();
+                    let can_hint = self.hints_enabled && players_turn && self.controller.outcome().is_none();
+                    if ui.add_enabled(can_hint, egui::Button::new("Hint")).clicked() {
+                        self.request_hint();
+                    }
+                    if self.hints_enabled {
+                        ui.label(format!("Hints used: {}", self.hints_used));
+                    } else {
+                        ui.label("Hints disabled for this game");
+                    }
+                    if self.stockfish.as_ref().is_some_and(Stockfish::is_pondering) {
+                        ui.label("Engine is thinking on your time");
+                    }
+                    if !self.premoves.is_empty() {
+                        ui.label(format!("{} premove(s) queued, right-click the board to cancel", self.premoves.len()));
+                    }
+                });
+            }
+
+            ui.horizontal(|ui| {
+                ui.checkbox(&mut self.show_moves, "Moves");
+                ui.checkbox(&mut self.show_lines, "Engine lines");
+                ui.checkbox(&mut self.show_eval, "Show evaluation")
+                    .on_hover_text("Hidden by default so the engine's opinion doesn't spoil the game");
+                if ui.checkbox(&mut self.show_wdl, "Show W/D/L").changed()
+                    && let Some(stockfish) = &mut self.stockfish
+                {
+                    stockfish.set_show_wdl(self.show_wdl);
+                }
+                ui.checkbox(&mut self.console.open, "Engine console");
+                ui.checkbox(&mut self.options_editor.open, "Engine options");
+            });
+
+            if self.show_eval {
+                ui.horizontal(|ui| {
+                    match self.eval {
+                        Some(score) => ui.label(format!("Eval: {}", eval_bar::score_label(score))),
+                        None => ui.label("Eval: -"),
+                    };
+                    if self.show_wdl && let Some(wdl) = self.wdl {
+                        ui.label(eval_bar::wdl_label(wdl));
+                    }
+                });
+            }
+        });
+    }
+
+    /// Resign, draw offer and draw claim buttons. In a local game they act
+    /// for the side to move.
+    fn draw_game_actions(&mut self, ui: &mut egui::Ui) {
+        let vs_engine = self.game_mode == GameMode::VsStockfish;
+        let actor = if vs_engine { self.player_color.to_piece_color() } else { self.controller.board().current_turn() };
+
+        ui.horizontal(|ui| {
+            if ui.button("Resign").clicked() {
+                self.controller.resign(actor);
+                return;
+            }
+
+            match self.controller.draw_offer() {
+                Some(_) if vs_engine => {
+                    ui.label("The engine declined your draw offer");
+                }
+                Some(offerer) if offerer == actor => {
+                    ui.label("Draw offered");
+                }
+                Some(offerer) => {
+                    ui.label(format!("{} offers a draw", offerer));
+                    if ui.button("Accept").clicked() {
+                        self.controller.accept_draw(actor);
+                        return;
+                    }
+                    if ui.button("Decline").clicked() {
+                        self.controller.decline_draw();
+                    }
+                }
+                None => {
+                    // The engine answers offers between its own moves
+                    let can_offer = !vs_engine || self.engine_search.is_none();
+                    if ui.add_enabled(can_offer, egui::Button::new("Offer draw")).clicked() {
+                        if vs_engine {
+                            self.offer_draw_to_engine();
+                        } else {
+                            self.controller.offer_draw(actor);
+                        }
+                    }
+                }
+            }
+
+            if let Some(claim) = self.controller.claimable_draw() {
+                let reason = match claim {
+                    Termination::ThreefoldRepetition => "The position has occurred three times",
+                    _ => "50 moves by each side without a capture or pawn move",
+                };
+                if ui.button("Claim draw").on_hover_text(reason).clicked() {
+                    self.controller.claim_draw();
+                }
+            }
+
+            if ui.button("Main menu").on_hover_text("Abandons this game").clicked() {
+                self.return_to_menu();
+            }
+        });
+    }
+
+    /// Clears everything left over from the last game and sets up a new
+    /// one from the starting position, or the one set up in the editor.
+    fn reset_game(&mut self, clock: Option<ChessClock>) {
+        self.recall_engine();
+        let start = self.start_position.clone().unwrap_or_default();
+        self.controller = GameController::new(Game::from_board(start), clock);
+        self.selected_position = None;
+        self.possible_moves.clear();
+        self.move_input.clear();
+        self.premoves.clear();
+        self.eval = None;
+        self.wdl = None;
+        self.candidate_lines.clear();
+        self.preview = None;
+        self.hint = None;
+        self.hints_used = 0;
+        self.ponder_expected = None;
+        self.hopeless_searches = 0;
+        self.review = None;
+        self.review_receiver = None;
+        self.ply_evals.clear();
+    }
+    
+    fn show_difficulty_selection(&mut self, ui: &mut egui::Ui) {
+        ui.label("Select Difficulty:");
+        for (index, profile) in self.profiles.iter().enumerate() {
+            let strength = match profile.strength {
+                EngineStrength::Skill(level) => format!("skill {}", level),
+                EngineStrength::Elo(elo) => format!("{} Elo", elo),
+            };
+            if ui.radio(self.profile_index == index, format!("{} ({})", profile.name, strength)).clicked() {
+                self.profile_index = index;
+            }
+        }
+
+        let builtin_count = Difficulty::iter().count();
+        if self.profile_index >= builtin_count && ui.button("Delete selected profile").clicked() {
+            self.profiles.remove(self.profile_index);
+            self.profile_index = Difficulty::Medium as usize;
+            self.save_custom_profiles();
+        }
+
+        egui::CollapsingHeader::new("Custom profile").show(ui, |ui| {
+            self.show_profile_editor(ui);
+            if ui.button("Save profile").clicked() && !self.profile_draft.name.trim().is_empty() {
+                let draft = self.profile_draft.clone();
+                match self.profiles[builtin_count..].iter().position(|p| p.name == draft.name) {
+                    Some(existing) => {
+                        self.profiles[builtin_count + existing] = draft;
+                        self.profile_index = builtin_count + existing;
+                    }
+                    None => {
+                        self.profiles.push(draft);
+                        self.profile_index = self.profiles.len() - 1;
+                    }
+                }
+                self.save_custom_profiles();
+            }
+        });
+    }
+
+    /// Ratings the selected engine accepts: what it advertised when it last
+    /// started, or what it is known to advertise.
+    fn elo_range(&self) -> RangeInclusive<u32> {
+        match &self.engine_elo_range {
+            Some((kind, range)) if *kind == self.engine_kind => range.clone(),
+            _ => self.engine_kind.default_elo_range(),
+        }
+    }
+
+    fn show_profile_editor(&mut self, ui: &mut egui::Ui) {
+        let elo_range = self.elo_range();
+        let draft = &mut self.profile_draft;
+        egui::Grid::new("profile_editor").num_columns(2).show(ui, |ui| {
+            ui.label("Name");
+            ui.text_edit_singleline(&mut draft.name);
+            ui.end_row();
+
+            ui.label("Strength");
+            ui.horizontal(|ui| {
+                let by_elo = matches!(draft.strength, EngineStrength::Elo(_));
+                if ui.radio(by_elo, "Elo").clicked() && !by_elo {
+                    draft.strength = EngineStrength::Elo(1500);
+                }
+                if ui.radio(!by_elo, "Skill level").clicked() && by_elo {
+                    draft.strength = EngineStrength::Skill(10);
+                }
+            });
+            ui.end_row();
+
+            match &mut draft.strength {
+                EngineStrength::Elo(elo) => {
+                    ui.label("Target Elo");
+                    ui.add(egui::Slider::new(elo, elo_range.clone()));
+                }
+                EngineStrength::Skill(level) => {
+                    ui.label("Skill level");
+                    ui.add(egui::Slider::new(level, 0..=20));
+                }
+            }
+            ui.end_row();
+
+            ui.label("Move time (ms)");
+            ui.add(egui::DragValue::new(&mut draft.movetime_ms).speed(50).clamp_range(50..=60_000));
+            ui.end_row();
+
+            ui.label("Max depth");
+            optional_value(ui, &mut draft.depth, 10, 1..=60);
+            ui.end_row();
+
+            ui.label("Max nodes");
+            optional_value(ui, &mut draft.nodes, 100_000, 1..=100_000_000);
+            ui.end_row();
+
+            ui.label("Random think time");
+            ui.horizontal(|ui| {
+                let mut randomize = draft.think_time_ms.is_some();
+                if ui.checkbox(&mut randomize, "").changed() {
+                    draft.think_time_ms = randomize.then_some((draft.movetime_ms / 2, draft.movetime_ms * 2));
+                }
+                if let Some((min, max)) = &mut draft.think_time_ms {
+                    ui.add(egui::DragValue::new(min).speed(50).suffix(" ms"));
+                    ui.label("to");
+                    ui.add(egui::DragValue::new(max).speed(50).suffix(" ms"));
+                }
+            });
+            ui.end_row();
+        });
+    }
+
+    fn save_custom_profiles(&self) {
+        let custom = &self.profiles[Difficulty::iter().count()..];
+        if let Err(err) = difficulty::save_profiles(Path::new(PROFILES_PATH), custom) {
+            eprintln!("Failed to write {}: {}", PROFILES_PATH, err);
+        }
+    }
+    
+    fn draw_match_setup(&mut self, ui: &mut egui::Ui) {
+        ui.vertical_centered(|ui| {
+            ui.heading("Configure Engine Match");
+            ui.separator();
+            self.show_engine_selection(ui);
+
+            egui::Grid::new("match_setup_grid").num_columns(2).show(ui, |ui| {
+                for (side, label) in ["Engine A (White first)", "Engine B"].into_iter().enumerate() {
+                    ui.label(label);
+                    let shown = self.match_setup.profile(side, &self.profiles).name.clone();
+                    let selected = &mut self.match_setup.profiles[side];
+                    egui::ComboBox::from_id_source(("match_profile", side))
+                        .selected_text(shown)
+                        .show_ui(ui, |ui| {
+                            for profile in &self.profiles {
+                                ui.selectable_value(selected, profile.name.clone(), profile.name.as_str());
+                            }
+                        });
+                    ui.end_row();
+                }
+                ui.label("Games");
+                ui.add(egui::DragValue::new(&mut self.match_setup.games).clamp_range(1..=100))
+                    .on_hover_text("Colors alternate between games");
+                ui.end_row();
+                ui.label("Delay between moves");
+                ui.add(egui::DragValue::new(&mut self.match_setup.delay_ms).clamp_range(0..=10_000).suffix(" ms"));
+                ui.end_row();
+            });
+
+            if ui.button("Start Match").clicked() {
+                let profiles = [0, 1].map(|side| self.match_setup.profile(side, &self.profiles).clone());
+                match EngineMatch::start(self.engine_path(), &self.saved_engine_options, profiles, &self.match_setup) {
+                    Ok(engine_match) => {
+                        self.reset_game(None);
+                        self.board_flipped = false;
+                        self.engine_match = Some(engine_match);
+                        self.screen = Screen::Playing;
+                    }
+                    Err(err) => self.engine_banner = Some(format!("Could not start the engines: {}", err)),
+                }
+            }
+        });
+    }
+
+    fn draw_match_status(&mut self, ui: &mut egui::Ui) {
+        let mut end_match = false;
+        let Some(engine_match) = &mut self.engine_match else {
+            return;
+        };
+
+        ui.separator();
+        let [a, b] = &engine_match.sides;
+        ui.label(format!(
+            "{} {} - {} {}",
+            a.profile.name, a.points, b.points, b.profile.name
+        ));
+        if engine_match.is_finished() {
+            ui.label(format!("Match over after {} games", engine_match.results.len()));
+        } else {
+            ui.label(format!("Game {} of {}", engine_match.results.len() + 1, engine_match.games));
+        }
+
+        ui.horizontal(|ui| {
+            let pause_label = if engine_match.paused { "Resume" } else { "Pause" };
+            if ui.button(pause_label).clicked() {
+                engine_match.paused = !engine_match.paused;
+            }
+            if ui.add_enabled(engine_match.paused, egui::Button::new("Step")).clicked() {
+                engine_match.step();
+            }
+            ui.label("Delay:");
+            ui.add(egui::DragValue::new(&mut engine_match.delay_ms).clamp_range(0..=10_000).suffix(" ms"));
+            end_match = ui.button("End match").clicked();
+        });
+
+        let white = engine_match.white_side();
+        for (index, side) in engine_match.sides.iter().enumerate() {
+            let color = if index == white { "White" } else { "Black" };
+            let score = side.score.map(eval_bar::score_label).unwrap_or_else(|| "-".to_string());
+            ui.label(format!("{} ({}): {} {}", side.profile.name, color, score, side.pv));
+        }
+
+        if !engine_match.results.is_empty() {
+            ui.label(format!("Results: {}", engine_match.results.join(", ")));
+        }
+
+        if end_match {
+            self.engine_match = None;
+            self.reset_game(None);
+            self.screen = Screen::Setup;
+        }
+    }
+
+    /// Starts the next engine search once the delay has passed, plays its
+    /// move when it is done, and moves on to the next game of the series
+    /// when one ends.
+    fn tick_match(&mut self, ctx: &egui::Context) {
+        let Some(engine_match) = &mut self.engine_match else {
+            return;
+        };
+        if engine_match.is_finished() {
+            return;
+        }
+
+        if engine_match.is_searching() {
+            ctx.request_repaint_after(Duration::from_millis(50));
+            let Some((mv, score)) = engine_match.poll_move() else {
+                return;
+            };
+            if let Some(score) = score {
+                self.eval = Some(score);
+                self.record_eval(self.controller.game().moves().len(), score);
+            }
+            self.apply_move(mv);
+            // Engines never claim draws themselves, so the match does it for them
+            self.controller.claim_draw();
+            if self.match_game_over() {
+                // Adjudicated games are draws
+                let result = self.controller.outcome().map_or("1/2-1/2", |outcome| outcome.result());
+                if let Some(engine_match) = &mut self.engine_match {
+                    engine_match.record_result(result);
+                }
+            }
+            return;
+        }
+
+        ctx.request_repaint_after(engine_match.delay());
+        if !engine_match.move_due() {
+            return;
+        }
+        if self.match_game_over() {
+            if let Some(engine_match) = &mut self.engine_match {
+                engine_match.start_next_game();
+            }
+            self.reset_game(None);
+            return;
+        }
+        if let Some(engine_match) = &mut self.engine_match {
+            engine_match.start_move(self.controller.game());
+        }
+    }
+
+    /// Whether the game on the board is over for the match: finished, or
+    /// adjudicated a draw because neither side can mate or it ran too long.
+    fn match_game_over(&self) -> bool {
+        self.controller.is_over()
+            || self.controller.board().is_insufficient_material()
+            || self.controller.game().moves().len() >= MAX_PLIES
+    }
+
+    fn engine_to_move(&self) -> bool {
+        self.game_mode == GameMode::VsStockfish
+            && (self.engine_search.is_some() || self.stockfish.as_ref().is_some_and(Stockfish::is_alive))
+            && self.controller.side_to_move() != self.player_color.to_piece_color()
+            && !self.controller.is_over()
+    }
+
+    /// Starts a game with the current setup.
+    fn start_game(&mut self) {
+        self.reset_game(self.new_clock());
+        self.board_flipped = self.game_mode == GameMode::VsStockfish && self.player_color == PlayerColor::Black;
+        if let Some(stockfish) = &mut self.stockfish {
+            stockfish.new_game();
+        }
+        self.screen = Screen::Playing;
+    }
+
+    /// Abandons whatever is going on and goes back to mode selection.
+    fn return_to_menu(&mut self) {
+        // A search still running ends with its thread
+        self.engine_search = None;
+        self.analysis = None;
+        self.stockfish = None;
+        self.engine_match = None;
+        self.start_position = None;
+        self.reset_game(None);
+        self.screen = Screen::Menu;
+    }
+
+    fn draw_menu(&mut self, ui: &mut egui::Ui) {
+        ui.vertical_centered(|ui| {
+            ui.heading("Select Game Mode");
+            ui.add_space(20.0);
+
+            egui::Grid::new("game_mode_grid")
+                .spacing([40.0, 20.0])
+                .show(ui, |ui| {
+                    let modes = [
+                        (GameMode::TwoPlayer, "Two Players (Local)", "Play against another person on this device"),
+                        (GameMode::VsStockfish, "Play vs Computer", "Challenge Stockfish or the built-in engine"),
+                        (GameMode::EngineVsEngine, "Engine vs Engine", "Watch two engine profiles play each other"),
+                    ];
+                    for (mode, label, hover) in modes {
+                        if ui.button(label).on_hover_text(hover).clicked() {
+                            self.game_mode = mode;
+                            self.screen = Screen::Setup;
+                        }
+                    }
+                    if ui.button("Set Up Position").on_hover_text("Place pieces to play or analyze from any position").clicked() {
+                        self.screen = Screen::Editor;
+                    }
+                    ui.end_row();
+                });
+        });
+    }
+
+    /// Carries out what was asked for in the editor: a game or analysis
+    /// from its position, or going back to the menu.
+    fn editor_action(&mut self, action: EditorAction) {
+        let mode = match action {
+            EditorAction::Back => {
+                self.return_to_menu();
+                return;
+            }
+            EditorAction::PlayComputer => GameMode::VsStockfish,
+            EditorAction::PlayTwoPlayer | EditorAction::Analyze => GameMode::TwoPlayer,
+        };
+        self.game_mode = mode;
+        self.start_position = Some(self.editor.board().clone());
+        if action != EditorAction::Analyze {
+            self.screen = Screen::Setup;
+            return;
+        }
+
+        // Analysis is a local game without a clock, with the lines open
+        self.reset_game(None);
+        self.board_flipped = self.editor.flipped();
+        self.screen = Screen::Playing;
+        self.show_lines = true;
+        self.analyze_position();
+    }
+
+    fn draw_setup(&mut self, ui: &mut egui::Ui) {
+        if let Some(start) = &self.start_position {
+            ui.vertical_centered(|ui| {
+                ui.label(format!("Starting from {}", start.to_fen()));
+            });
+        }
+        match self.game_mode {
+            GameMode::EngineVsEngine => self.draw_match_setup(ui),
+            GameMode::TwoPlayer => {
+                ui.vertical_centered(|ui| {
+                    ui.heading("Configure Two-Player Game");
+                    ui.separator();
+                    clock_display::show_clock_setup(ui, &mut self.clock_setup);
+
+                    if ui.button("Start Game").clicked() {
+                        self.start_game();
+                    }
+                });
+            }
+            GameMode::VsStockfish => {
+                ui.vertical_centered(|ui| {
+                    ui.heading("Configure Computer Opponent");
+                    ui.separator();
+                    self.show_engine_selection(ui);
+                    self.show_difficulty_selection(ui);
+                    self.show_color_selection(ui);
+                    ui.checkbox(&mut self.hints_enabled, "Allow hints")
+                        .on_hover_text("Turn off for rated or serious games");
+                    ui.checkbox(&mut self.ponder_enabled, "Engine thinks on your time")
+                        .on_hover_text("Pondering: noticeably stronger play, especially at short time controls");
+                    ui.horizontal(|ui| {
+                        ui.label("Engine resigns when down by (cp)");
+                        optional_value(ui, &mut self.resign_threshold, 800, 200..=5000);
+                    });
+                    clock_display::show_clock_setup(ui, &mut self.clock_setup);
+
+                    if ui.button("Start Game").clicked() {
+                        self.start_engine();
+                        if self.stockfish.is_some() {
+                            self.start_game();
+                        }
+                    }
+                });
+            }
+        }
+        ui.vertical_centered(|ui| {
+            if ui.button("Back").clicked() {
+                self.return_to_menu();
+            }
+        });
+    }
+
+    fn show_engine_selection(&mut self, ui: &mut egui::Ui) {
+        ui.label("Select Engine:");
+        for kind in EngineKind::iter() {
+            if ui.radio(self.engine_kind == kind, kind.to_string()).clicked() {
+                self.engine_kind = kind;
+            }
+        }
+        if self.engine_kind == EngineKind::Stockfish && !Path::new(STOCKFISH_PATH).exists() {
+            ui.colored_label(Color32::from_rgb(220, 80, 60), format!("{} not found", STOCKFISH_PATH));
+        }
+    }
+
+    fn show_color_selection(&mut self, ui: &mut egui::Ui) {
+        ui.label("Select Your Color:");
+        for color in PlayerColor::iter() {
+            if ui.radio(self.player_color == color, format!("{:?}", color)).clicked() {
+                self.player_color = color;
+            }
+        }
+    }
+}
+
+impl eframe::App for ChessApp {
+    fn update(&mut self, ctx: &egui::Context, _frame: &mut Frame) {
+        self.poll_review(ctx);
+        self.poll_engine_events();
+        self.poll_engine_move();
+        self.poll_analysis();
+        if self.engine_search.is_some() || self.analysis.is_some() {
+            ctx.request_repaint_after(Duration::from_millis(50));
+        }
+        if self.screen == Screen::Playing {
+            self.controller.tick();
+            if self.controller.clock().is_some_and(|clock| clock.running_side().is_some()) {
+                ctx.request_repaint_after(Duration::from_millis(100));
+            }
+        }
+        self.draw_engine_banner(ctx);
+        let busy_log = self.engine_search.as_ref().or(self.analysis.as_ref().map(|(_, search)| search)).map(BackgroundSearch::log);
+        self.console.show(ctx, self.stockfish.as_mut(), busy_log);
+        if self.options_editor.show(ctx, self.stockfish.as_mut(), &mut self.saved_engine_options) {
+            self.sync_engine_toggles();
+        }
+        if self.screen == Screen::Review {
+            self.draw_review_window(ctx);
+        }
+
+        let in_game = matches!(self.screen, Screen::Playing | Screen::GameOver | Screen::Review);
+        if in_game && self.show_moves {
+            egui::SidePanel::left("moves")
+                .default_width(220.0)
+                .show(ctx, |ui| self.draw_moves_panel(ui));
+        }
+        if in_game && self.show_lines {
+            egui::SidePanel::right("engine_lines")
+                .default_width(260.0)
+                .show(ctx, |ui| self.draw_lines_panel(ui));
+        }
+
+        if self.screen == Screen::Editor {
+            let action = egui::SidePanel::left("editor_controls")
+                .default_width(300.0)
+                .show(ctx, |ui| egui::ScrollArea::vertical().show(ui, |ui| self.editor.show_controls(ui)).inner)
+                .inner;
+            if let Some(action) = action {
+                self.editor_action(action);
+            }
+        }
+        if self.screen == Screen::Playing {
+            egui::TopBottomPanel::bottom("move_input").show(ctx, |ui| self.draw_move_input(ui));
+        }
+
+        egui::CentralPanel::default().show(ctx, |ui| match self.screen {
+            Screen::Menu => self.draw_menu(ui),
+            Screen::Editor => self.editor.show_board(ui),
+            Screen::Setup => self.draw_setup(ui),
+            Screen::Playing | Screen::GameOver | Screen::Review => {
+                self.draw_game_status(ui);
+                if self.engine_match.is_some() {
+                    self.draw_match_status(ui);
+                }
+                ui.separator();
+                self.draw_board(ui);
+            }
+        });
+
+        if self.screen == Screen::Playing {
+            self.tick_match(ctx);
+            if self.engine_to_move() && self.engine_search.is_none() {
+                self.start_engine_move();
+            }
+        }
+
+        let game_over = self.controller.take_events().iter().any(|event| matches!(event, GameEvent::GameOver(_)));
+        // A match moves on to its next game by itself
+        if game_over && self.screen == Screen::Playing && self.game_mode != GameMode::EngineVsEngine {
+            self.finish_game();
+            ctx.request_repaint();
+        }
+    }
+}
+
+/// A piece glyph filling most of `rect`.
+pub fn draw_piece(painter: &egui::Painter, rect: Rect, piece: Piece) {
+    let text = match piece.piece_type {
+        PieceType::King => "♚",
+        PieceType::Queen => "♛",
+        PieceType::Rook => "♜",
+        PieceType::Bishop => "♝",
+        PieceType::Knight => "♞",
+        PieceType::Pawn => "♟",
+    };
+
+    let font_size = rect.height() * 0.8;
+    let font = FontId::monospace(font_size);
+
+    painter.text(
+        rect.center(),
+        Align2::CENTER_CENTER,
+        text,
+        font,
+        if piece.color == PieceColor::White {
+            Color32::WHITE
+        } else {
+            Color32::BLACK
+        },
+    );
+}
+
+fn draw_arrow(painter: &egui::Painter, from: Pos2, to: Pos2, square_size: f32, color: Color32) {
+    let direction = (to - from).normalized();
+    let head_length = square_size * 0.35;
+    let head_width = square_size * 0.3;
+    let shaft_end = to - direction * head_length;
+    let normal = Vec2::new(-direction.y, direction.x);
+
+    painter.line_segment([from, shaft_end], Stroke::new(square_size * 0.12, color));
+    painter.add(Shape::convex_polygon(
+        vec![to, shaft_end + normal * head_width, shaft_end - normal * head_width],
+        color,
+        Stroke::NONE,
+    ));
+}
+
+/// A checkbox that enables an optional limit, plus its value when enabled.
+fn optional_value<T>(ui: &mut egui::Ui, value: &mut Option<T>, default: T, range: std::ops::RangeInclusive<T>)
+where
+    T: egui::emath::Numeric,
+{
+    ui.horizontal(|ui| {
+        let mut enabled = value.is_some();
+        if ui.checkbox(&mut enabled, "").changed() {
+            *value = enabled.then_some(default);
+        }
+        if let Some(value) = value {
+            ui.add(egui::DragValue::new(value).clamp_range(range));
+        }
     });
-    stockfish.set_position(&board.to_fen());
-    stockfish.get_best_move(1000).unwrap_or_default()
 }
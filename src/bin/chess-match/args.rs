@@ -0,0 +1,231 @@
+use std::path::PathBuf;
+
+use chess_game::difficulty::EngineStrength;
+use chess_game::stockfish::SearchLimits;
+
+pub const USAGE: &str = "\
+Usage: chess-match --engine cmd=PATH|builtin [name=NAME] [skill=0-20 | elo=N] [option.NAME=VALUE]...
+                   --engine ... [more engines]
+                   [--gauntlet] [--games N] [--concurrency N]
+                   [--tc SECONDS+INC | --movetime MS | --depth N | --nodes N]
+                   [--openings FILE.epd|FILE.pgn] [--opening-plies N]
+                   [--resign CP MOVES] [--draw CP MOVES AFTER_MOVE]
+                   [--sprt ELO0 ELO1 [ALPHA BETA]]
+                   [--pgn FILE] [--max-plies N]
+
+Plays a round-robin between all engines (or, with --gauntlet, the first
+engine against each of the others). Every pairing plays --games games with
+colors alternating, each opening once with either color. cmd=builtin
+selects the engine built into this program.";
+
+#[derive(Debug, Clone)]
+pub struct EngineConfig {
+    pub name: String,
+    pub cmd: String,
+    pub strength: Option<EngineStrength>,
+    /// UCI options, checked against what the engine declares
+    pub options: Vec<(String, String)>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    RoundRobin,
+    Gauntlet,
+}
+
+#[derive(Debug, Clone)]
+pub enum TimeControl {
+    /// Base time and increment per move, in milliseconds
+    Clock { base_ms: u64, inc_ms: u64 },
+    /// The same limits for every move, with no clock
+    PerMove(SearchLimits),
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct ResignRule {
+    /// A side resigns once its own score stays at or below -`cp`...
+    pub cp: i32,
+    /// ...for this many of its moves in a row
+    pub moves: u32,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct DrawRule {
+    /// Both sides' scores within ±`cp`...
+    pub cp: i32,
+    /// ...for this many moves each in a row...
+    pub moves: u32,
+    /// ...from this move number on
+    pub after_move: u32,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct SprtConfig {
+    pub elo0: f64,
+    pub elo1: f64,
+    pub alpha: f64,
+    pub beta: f64,
+}
+
+#[derive(Debug, Clone)]
+pub struct Config {
+    pub engines: Vec<EngineConfig>,
+    pub format: Format,
+    pub games: u32,
+    pub concurrency: usize,
+    pub time_control: TimeControl,
+    pub openings: Option<PathBuf>,
+    pub opening_plies: usize,
+    pub resign: Option<ResignRule>,
+    pub draw: Option<DrawRule>,
+    pub sprt: Option<SprtConfig>,
+    pub pgn: Option<PathBuf>,
+    pub max_plies: usize,
+}
+
+pub fn parse_args(args: impl IntoIterator<Item = String>) -> Result<Config, String> {
+    let mut config = Config {
+        engines: Vec::new(),
+        format: Format::RoundRobin,
+        games: 2,
+        concurrency: 1,
+        time_control: TimeControl::Clock { base_ms: 10_000, inc_ms: 100 },
+        openings: None,
+        opening_plies: 8,
+        resign: None,
+        draw: None,
+        sprt: None,
+        pgn: None,
+        max_plies: 600,
+    };
+
+    let mut args = args.into_iter().peekable();
+    while let Some(arg) = args.next() {
+        // Values for the current flag: everything up to the next flag
+        let mut values = Vec::new();
+        while let Some(value) = args.next_if(|next| !next.starts_with("--")) {
+            values.push(value);
+        }
+
+        match arg.as_str() {
+            "--engine" => config.engines.push(parse_engine(&values)?),
+            "--gauntlet" => config.format = Format::Gauntlet,
+            "--games" => config.games = single(&arg, &values)?,
+            "--concurrency" => config.concurrency = single::<usize>(&arg, &values)?.max(1),
+            "--tc" => config.time_control = parse_time_control(&single::<String>(&arg, &values)?)?,
+            "--movetime" => config.time_control = TimeControl::PerMove(SearchLimits::movetime(single(&arg, &values)?)),
+            "--depth" => config.time_control = TimeControl::PerMove(SearchLimits::depth(single(&arg, &values)?)),
+            "--nodes" => config.time_control = TimeControl::PerMove(SearchLimits::nodes(single(&arg, &values)?)),
+            "--openings" => config.openings = Some(PathBuf::from(single::<String>(&arg, &values)?)),
+            "--opening-plies" => config.opening_plies = single(&arg, &values)?,
+            "--resign" => {
+                let [cp, moves] = numbers(&arg, &values)?;
+                config.resign = Some(ResignRule { cp: cp as i32, moves: moves as u32 });
+            }
+            "--draw" => {
+                let [cp, moves, after_move] = numbers(&arg, &values)?;
+                config.draw = Some(DrawRule { cp: cp as i32, moves: moves as u32, after_move: after_move as u32 });
+            }
+            "--sprt" => {
+                let (elo0, elo1, alpha, beta) = match numbers::<4>(&arg, &values) {
+                    Ok([elo0, elo1, alpha, beta]) => (elo0, elo1, alpha, beta),
+                    Err(_) => {
+                        let [elo0, elo1] = numbers(&arg, &values)?;
+                        (elo0, elo1, 0.05, 0.05)
+                    }
+                };
+                config.sprt = Some(SprtConfig { elo0, elo1, alpha, beta });
+            }
+            "--pgn" => config.pgn = Some(PathBuf::from(single::<String>(&arg, &values)?)),
+            "--max-plies" => config.max_plies = single(&arg, &values)?,
+            "--help" | "-h" => return Err(String::new()),
+            _ => return Err(format!("unknown argument '{}'", arg)),
+        }
+    }
+
+    if config.engines.len() < 2 {
+        return Err("at least two --engine configurations are needed".to_string());
+    }
+    if config.sprt.is_some() && config.engines.len() != 2 {
+        return Err("--sprt compares exactly two engines".to_string());
+    }
+
+    // Engines sharing a name would be impossible to tell apart in the results
+    for (index, engine) in config.engines.iter().enumerate() {
+        if config.engines[..index].iter().any(|other| other.name == engine.name) {
+            return Err(format!("two engines are named '{}'; give them distinct name= values", engine.name));
+        }
+    }
+
+    Ok(config)
+}
+
+fn parse_engine(values: &[String]) -> Result<EngineConfig, String> {
+    let mut name = None;
+    let mut cmd = None;
+    let mut strength = None;
+    let mut options = Vec::new();
+
+    for value in values {
+        let (key, value) = value
+            .split_once('=')
+            .ok_or_else(|| format!("--engine expects key=value pairs, got '{}'", value))?;
+        match key {
+            "cmd" => cmd = Some(value.to_string()),
+            "name" => name = Some(value.to_string()),
+            "skill" => {
+                let skill: u8 = value.parse().map_err(|_| format!("bad skill '{}'", value))?;
+                strength = Some(EngineStrength::Skill(skill.min(20)));
+            }
+            "elo" => {
+                let elo: u32 = value.parse().map_err(|_| format!("bad elo '{}'", value))?;
+                strength = Some(EngineStrength::Elo(elo));
+            }
+            _ => match key.strip_prefix("option.") {
+                Some(option) => options.push((option.to_string(), value.to_string())),
+                None => return Err(format!("unknown engine setting '{}'", key)),
+            },
+        }
+    }
+
+    let cmd = cmd.ok_or("--engine needs cmd=PATH")?;
+    let name = name.unwrap_or_else(|| {
+        let mut name = cmd.rsplit(['/', '\\']).next().unwrap_or(&cmd).to_string();
+        match strength {
+            Some(EngineStrength::Skill(skill)) => name.push_str(&format!(" skill {}", skill)),
+            Some(EngineStrength::Elo(elo)) => name.push_str(&format!(" {} Elo", elo)),
+            None => {}
+        }
+        name
+    });
+    Ok(EngineConfig { name, cmd, strength, options })
+}
+
+/// "10+0.1" is 10 seconds plus 0.1 seconds per move; the increment may be
+/// left out.
+fn parse_time_control(text: &str) -> Result<TimeControl, String> {
+    let (base, inc) = text.split_once('+').unwrap_or((text, "0"));
+    let seconds = |value: &str| -> Result<u64, String> {
+        let seconds: f64 = value.parse().map_err(|_| format!("bad time control '{}'", text))?;
+        Ok((seconds * 1000.0).round() as u64)
+    };
+    Ok(TimeControl::Clock { base_ms: seconds(base)?, inc_ms: seconds(inc)? })
+}
+
+fn single<T: std::str::FromStr>(flag: &str, values: &[String]) -> Result<T, String> {
+    match values {
+        [value] => value.parse().map_err(|_| format!("bad value '{}' for {}", value, flag)),
+        _ => Err(format!("{} takes one value", flag)),
+    }
+}
+
+fn numbers<const N: usize>(flag: &str, values: &[String]) -> Result<[f64; N], String> {
+    if values.len() != N {
+        return Err(format!("{} takes {} values", flag, N));
+    }
+    let mut numbers = [0.0; N];
+    for (number, value) in numbers.iter_mut().zip(values) {
+        *number = value.parse().map_err(|_| format!("bad value '{}' for {}", value, flag))?;
+    }
+    Ok(numbers)
+}
@@ -0,0 +1,170 @@
+//! Headless tournaments between UCI engine configurations, for calibrating
+//! engine settings. Run with `--help` for usage.
+
+mod args;
+mod openings;
+mod stats;
+mod tournament;
+
+use std::env;
+use std::fs::File;
+use std::io::Write;
+use std::process::ExitCode;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use chess_game::chess::write_pgn;
+
+use args::{Config, TimeControl, USAGE};
+use stats::{SprtDecision, Tally};
+use tournament::GameRecord;
+
+fn main() -> ExitCode {
+    let config = match args::parse_args(env::args().skip(1)) {
+        Ok(config) => config,
+        Err(err) => {
+            if !err.is_empty() {
+                eprintln!("error: {}\n", err);
+            }
+            eprintln!("{}", USAGE);
+            return ExitCode::from(2);
+        }
+    };
+
+    let openings = match &config.openings {
+        Some(path) => match openings::load_openings(path, config.opening_plies) {
+            Ok(openings) => openings,
+            Err(err) => {
+                eprintln!("error: {}", err);
+                return ExitCode::FAILURE;
+            }
+        },
+        None => Vec::new(),
+    };
+
+    let mut pgn_file = match &config.pgn {
+        Some(path) => match File::create(path) {
+            Ok(file) => Some(file),
+            Err(err) => {
+                eprintln!("error: {}: {}", path.display(), err);
+                return ExitCode::FAILURE;
+            }
+        },
+        None => None,
+    };
+
+    let jobs = tournament::schedule(&config, &openings);
+    println!("{} games, {} at a time", jobs.len(), config.concurrency);
+
+    let stop = Arc::new(AtomicBool::new(false));
+    let results = tournament::run(&config, jobs, Arc::clone(&stop));
+    let mut tallies = vec![Tally::default(); config.engines.len()];
+    let mut decision = SprtDecision::Continue;
+
+    for record in results {
+        let (white_points, black_points) = record.points();
+        tallies[record.job.white].add(white_points);
+        tallies[record.job.black].add(black_points);
+
+        println!(
+            "Game {} ({} vs {}): {} {{{}}}",
+            record.job.number,
+            config.engines[record.job.white].name,
+            config.engines[record.job.black].name,
+            record.result,
+            record.termination
+        );
+
+        if let Some(file) = &mut pgn_file && let Err(err) = file.write_all(game_pgn(&config, &record).as_bytes()) {
+            eprintln!("error: failed to write PGN: {}", err);
+            pgn_file = None;
+        }
+
+        if config.engines.len() == 2 {
+            print_head_to_head(&config, &tallies[0]);
+        }
+        if let Some(sprt) = &config.sprt {
+            let (llr, new_decision) = stats::sprt(&tallies[0], sprt);
+            let (lower, upper) = stats::sprt_bounds(sprt);
+            println!("LLR: {:.2} ({:.2}, {:.2}) [{}, {}]", llr, lower, upper, sprt.elo0, sprt.elo1);
+            if decision == SprtDecision::Continue && new_decision != SprtDecision::Continue {
+                decision = new_decision;
+                // Games already running still finish and count
+                stop.store(true, Ordering::Relaxed);
+            }
+        }
+    }
+
+    print_standings(&config, &tallies);
+    match decision {
+        SprtDecision::AcceptH0 => println!("SPRT: H0 accepted"),
+        SprtDecision::AcceptH1 => println!("SPRT: H1 accepted"),
+        SprtDecision::Continue if config.sprt.is_some() => println!("SPRT: no decision"),
+        SprtDecision::Continue => {}
+    }
+
+    ExitCode::SUCCESS
+}
+
+fn game_pgn(config: &Config, record: &GameRecord) -> String {
+    let time_control = match config.time_control {
+        TimeControl::Clock { base_ms, inc_ms } => format!("{}+{}", base_ms as f64 / 1000.0, inc_ms as f64 / 1000.0),
+        TimeControl::PerMove(_) => "-".to_string(),
+    };
+    let tags = [
+        ("Event", "chess-match".to_string()),
+        ("Round", format!("{}.{}", record.job.round, record.job.number)),
+        ("White", config.engines[record.job.white].name.clone()),
+        ("Black", config.engines[record.job.black].name.clone()),
+        ("Result", record.result.to_string()),
+        ("Termination", record.termination.clone()),
+        ("TimeControl", time_control),
+    ];
+    let mut pgn = write_pgn(&record.game, &tags, &record.annotations);
+    pgn.push('\n');
+    pgn
+}
+
+fn elo_text(tally: &Tally) -> String {
+    match tally.elo() {
+        Some((elo, margin)) => format!("{:+.1} +/- {:.1}", elo, margin),
+        None => "-".to_string(),
+    }
+}
+
+fn print_head_to_head(config: &Config, tally: &Tally) {
+    println!(
+        "Score of {} vs {}: {} - {} - {} [{:.3}] {}, Elo {}",
+        config.engines[0].name,
+        config.engines[1].name,
+        tally.wins,
+        tally.losses,
+        tally.draws,
+        tally.score(),
+        tally.games(),
+        elo_text(tally)
+    );
+}
+
+/// Final table, best first. With more than two engines the Elo is each
+/// engine's performance against the field it played.
+fn print_standings(config: &Config, tallies: &[Tally]) {
+    let mut order: Vec<usize> = (0..tallies.len()).collect();
+    order.sort_by(|&a, &b| tallies[b].score().total_cmp(&tallies[a].score()));
+
+    let width = config.engines.iter().map(|engine| engine.name.len()).fold("Engine".len(), usize::max);
+    println!();
+    println!("{:<width$}  {:>7}  {:>5}  {:>13}  Elo", "Engine", "Points", "Games", "W / D / L", width = width);
+    for index in order {
+        let tally = &tallies[index];
+        println!(
+            "{:<width$}  {:>7.1}  {:>5}  {:>13}  {}",
+            config.engines[index].name,
+            tally.points(),
+            tally.games(),
+            format!("{} / {} / {}", tally.wins, tally.draws, tally.losses),
+            elo_text(tally),
+            width = width
+        );
+    }
+}
@@ -0,0 +1,46 @@
+use std::fs;
+use std::path::Path;
+
+use chess_game::chess::{read_pgn, Board, ChessMove};
+
+/// A start position plus the book moves played from it.
+#[derive(Clone)]
+pub struct Opening {
+    pub start: Board,
+    pub moves: Vec<ChessMove>,
+}
+
+/// Reads an EPD file (one position per line) or a PGN file, keeping the
+/// first `plies` moves of each game.
+pub fn load_openings(path: &Path, plies: usize) -> Result<Vec<Opening>, String> {
+    let text = fs::read_to_string(path).map_err(|err| format!("{}: {}", path.display(), err))?;
+    let is_epd = path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("epd"));
+
+    let openings: Vec<Opening> = if is_epd {
+        text.lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty() && !line.trim_start().starts_with('#'))
+            .map(|(number, line)| {
+                // EPD has four FEN fields followed by operations
+                let fen: Vec<&str> = line.split_whitespace().take(4).collect();
+                let start = Board::from_fen(&fen.join(" "))
+                    .map_err(|err| format!("{}:{}: {}", path.display(), number + 1, err))?;
+                Ok(Opening { start, moves: Vec::new() })
+            })
+            .collect::<Result<_, String>>()?
+    } else {
+        read_pgn(&text)
+            .map_err(|err| format!("{}: {}", path.display(), err))?
+            .into_iter()
+            .map(|game| Opening {
+                start: game.start_board().clone(),
+                moves: game.moves().iter().take(plies).copied().collect(),
+            })
+            .collect()
+    };
+
+    if openings.is_empty() {
+        return Err(format!("{}: no openings found", path.display()));
+    }
+    Ok(openings)
+}
@@ -0,0 +1,166 @@
+use crate::args::SprtConfig;
+
+/// Game outcomes from one engine's point of view.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Tally {
+    pub wins: u32,
+    pub draws: u32,
+    pub losses: u32,
+}
+
+impl Tally {
+    /// Adds a game this engine scored `points` (0, 0.5 or 1) in.
+    pub fn add(&mut self, points: f64) {
+        if points > 0.75 {
+            self.wins += 1;
+        } else if points > 0.25 {
+            self.draws += 1;
+        } else {
+            self.losses += 1;
+        }
+    }
+
+    pub fn games(&self) -> u32 {
+        self.wins + self.draws + self.losses
+    }
+
+    pub fn points(&self) -> f64 {
+        self.wins as f64 + self.draws as f64 / 2.0
+    }
+
+    /// Average points per game.
+    pub fn score(&self) -> f64 {
+        self.points() / self.games().max(1) as f64
+    }
+
+    /// Variance of a single game's result around the mean score.
+    fn variance(&self) -> f64 {
+        let n = self.games().max(1) as f64;
+        let s = self.score();
+        (self.wins as f64 * (1.0 - s).powi(2) + self.draws as f64 * (0.5 - s).powi(2) + self.losses as f64 * s.powi(2)) / n
+    }
+
+    /// Elo difference and the half-width of its 95% confidence interval.
+    /// `None` until both a point has been won and one dropped, since a
+    /// perfect score has no finite Elo.
+    pub fn elo(&self) -> Option<(f64, f64)> {
+        let n = self.games() as f64;
+        let s = self.score();
+        if n == 0.0 || s <= 0.0 || s >= 1.0 {
+            return None;
+        }
+        let margin = 1.959964 * (self.variance() / n).sqrt();
+        let low = elo_from_score((s - margin).max(1e-6));
+        let high = elo_from_score((s + margin).min(1.0 - 1e-6));
+        Some((elo_from_score(s), (high - low) / 2.0))
+    }
+}
+
+pub fn elo_from_score(score: f64) -> f64 {
+    -400.0 * (1.0 / score - 1.0).log10()
+}
+
+fn score_from_elo(elo: f64) -> f64 {
+    1.0 / (1.0 + 10f64.powf(-elo / 400.0))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SprtDecision {
+    Continue,
+    /// The difference is at most `elo0`
+    AcceptH0,
+    /// The difference is at least `elo1`
+    AcceptH1,
+}
+
+/// Log-likelihood ratio of H1 (Elo difference `elo1`) against H0 (`elo0`)
+/// under the usual normal approximation, with the decision it leads to.
+pub fn sprt(tally: &Tally, config: &SprtConfig) -> (f64, SprtDecision) {
+    let variance = tally.variance();
+    let llr = if tally.games() == 0 || variance <= 0.0 {
+        0.0
+    } else {
+        let s0 = score_from_elo(config.elo0);
+        let s1 = score_from_elo(config.elo1);
+        tally.games() as f64 * (s1 - s0) * (2.0 * tally.score() - s0 - s1) / (2.0 * variance)
+    };
+
+    let (lower, upper) = sprt_bounds(config);
+    let decision = if llr >= upper {
+        SprtDecision::AcceptH1
+    } else if llr <= lower {
+        SprtDecision::AcceptH0
+    } else {
+        SprtDecision::Continue
+    };
+    (llr, decision)
+}
+
+pub fn sprt_bounds(config: &SprtConfig) -> (f64, f64) {
+    (
+        (config.beta / (1.0 - config.alpha)).ln(),
+        ((1.0 - config.beta) / config.alpha).ln(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tally(wins: u32, draws: u32, losses: u32) -> Tally {
+        Tally { wins, draws, losses }
+    }
+
+    fn config() -> SprtConfig {
+        SprtConfig { elo0: 0.0, elo1: 5.0, alpha: 0.05, beta: 0.05 }
+    }
+
+    #[test]
+    fn even_score_is_zero_elo() {
+        for even in [tally(10, 0, 10), tally(3, 14, 3), tally(0, 8, 0)] {
+            let (elo, _) = even.elo().unwrap();
+            assert!(elo.abs() < 1e-9, "{even:?}: {elo}");
+        }
+        assert!(elo_from_score(0.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn known_tally_gives_known_elo_and_margin() {
+        // 70% from 60 wins, 20 draws and 20 losses
+        let (elo, margin) = tally(60, 20, 20).elo().unwrap();
+        assert!((elo - 147.19).abs() < 0.01, "{elo}");
+        assert!((margin - 66.01).abs() < 0.01, "{margin}");
+
+        let (elo, _) = tally(20, 20, 60).elo().unwrap();
+        assert!((elo + 147.19).abs() < 0.01, "{elo}");
+    }
+
+    #[test]
+    fn perfect_scores_have_no_elo() {
+        assert!(tally(5, 0, 0).elo().is_none());
+        assert!(tally(0, 0, 5).elo().is_none());
+        assert!(tally(0, 0, 0).elo().is_none());
+    }
+
+    #[test]
+    fn sprt_accepts_h1_for_a_clear_win() {
+        let (llr, decision) = sprt(&tally(600, 200, 200), &config());
+        assert!(llr >= sprt_bounds(&config()).1, "{llr}");
+        assert_eq!(decision, SprtDecision::AcceptH1);
+    }
+
+    #[test]
+    fn sprt_accepts_h0_for_a_clear_loss() {
+        let (llr, decision) = sprt(&tally(200, 200, 600), &config());
+        assert!(llr <= sprt_bounds(&config()).0, "{llr}");
+        assert_eq!(decision, SprtDecision::AcceptH0);
+    }
+
+    #[test]
+    fn sprt_continues_for_an_even_match() {
+        let (lower, upper) = sprt_bounds(&config());
+        let (llr, decision) = sprt(&tally(300, 400, 300), &config());
+        assert!(lower < llr && llr < upper, "{llr}");
+        assert_eq!(decision, SprtDecision::Continue);
+    }
+}
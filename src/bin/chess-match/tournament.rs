@@ -0,0 +1,262 @@
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Instant;
+
+use chess_game::chess::{Board, Game, GameState, MoveAnnotation, PieceColor};
+use chess_game::stockfish::{Score, SearchLimits, Stockfish};
+
+use crate::args::{Config, EngineConfig, Format, TimeControl};
+use crate::openings::Opening;
+
+/// Scores beyond this are mates, for adjudication purposes.
+const MATE_CP: i32 = 100_000;
+
+/// One game to be played.
+#[derive(Clone)]
+pub struct Job {
+    /// 1-based, in scheduling order
+    pub number: usize,
+    pub round: usize,
+    pub white: usize,
+    pub black: usize,
+    pub opening: Option<Opening>,
+}
+
+pub struct GameRecord {
+    pub job: Job,
+    pub game: Game,
+    /// "1-0", "0-1" or "1/2-1/2"
+    pub result: &'static str,
+    pub termination: String,
+    /// Score, depth and time per engine move; empty for book moves
+    pub annotations: Vec<MoveAnnotation>,
+}
+
+impl GameRecord {
+    /// Points for White, then Black.
+    pub fn points(&self) -> (f64, f64) {
+        match self.result {
+            "1-0" => (1.0, 0.0),
+            "0-1" => (0.0, 1.0),
+            _ => (0.5, 0.5),
+        }
+    }
+}
+
+/// Pairings in play order: each pairing plays `games` games with colors
+/// alternating, so every opening is played once with either color.
+/// Pairings are interleaved so partial results stay balanced.
+pub fn schedule(config: &Config, openings: &[Opening]) -> Vec<Job> {
+    let count = config.engines.len();
+    let pairs: Vec<(usize, usize)> = match config.format {
+        Format::RoundRobin => (0..count).flat_map(|a| (a + 1..count).map(move |b| (a, b))).collect(),
+        Format::Gauntlet => (1..count).map(|b| (0, b)).collect(),
+    };
+
+    let mut jobs = Vec::new();
+    for game in 0..config.games as usize {
+        for &(a, b) in &pairs {
+            let (white, black) = if game % 2 == 0 { (a, b) } else { (b, a) };
+            let opening = (!openings.is_empty()).then(|| openings[(game / 2) % openings.len()].clone());
+            jobs.push(Job {
+                number: jobs.len() + 1,
+                round: game / 2 + 1,
+                white,
+                black,
+                opening,
+            });
+        }
+    }
+    jobs
+}
+
+/// Plays `jobs` on `config.concurrency` worker threads. Results arrive in
+/// the order games finish; setting `stop` lets running games finish but
+/// starts no new ones.
+pub fn run(config: &Config, jobs: Vec<Job>, stop: Arc<AtomicBool>) -> Receiver<GameRecord> {
+    let queue = Arc::new(Mutex::new(VecDeque::from(jobs)));
+    let (sender, receiver) = mpsc::channel();
+
+    for _ in 0..config.concurrency {
+        let queue = Arc::clone(&queue);
+        let stop = Arc::clone(&stop);
+        let sender = sender.clone();
+        let config = config.clone();
+        thread::spawn(move || {
+            // Each worker keeps its own engine processes between games
+            let mut engines: HashMap<usize, Stockfish> = HashMap::new();
+            while !stop.load(Ordering::Relaxed) {
+                let Some(job) = queue.lock().unwrap().pop_front() else {
+                    break;
+                };
+                let record = play_game(&config, job, &mut engines);
+                if sender.send(record).is_err() {
+                    break;
+                }
+            }
+        });
+    }
+
+    receiver
+}
+
+fn start_engine(config: &EngineConfig) -> Result<Stockfish, String> {
+    let mut engine = Stockfish::new(&config.cmd).map_err(|err| format!("{}: {}", config.cmd, err))?;
+    for (name, value) in &config.options {
+        engine.set_declared_option(name, value)?;
+    }
+    if let Some(strength) = config.strength {
+        engine.set_strength(strength);
+    }
+    Ok(engine)
+}
+
+/// Mate scores mapped far outside any centipawn range.
+fn score_cp(score: Score) -> i32 {
+    match score {
+        Score::Cp(cp) => cp,
+        Score::Mate(n) if n > 0 => MATE_CP - n,
+        Score::Mate(n) => -MATE_CP - n,
+    }
+}
+
+fn play_game(config: &Config, job: Job, engines: &mut HashMap<usize, Stockfish>) -> GameRecord {
+    let (start, book) = match &job.opening {
+        Some(opening) => (opening.start.clone(), opening.moves.clone()),
+        None => (Board::new(), Vec::new()),
+    };
+    let mut game = Game::from_board(start);
+    let mut annotations = Vec::new();
+    for mv in book {
+        game.make_move(mv);
+        annotations.push(MoveAnnotation::default());
+    }
+
+    let finish = |game: Game, annotations: Vec<MoveAnnotation>, result: &'static str, termination: String| GameRecord {
+        job: job.clone(),
+        game,
+        result,
+        termination,
+        annotations,
+    };
+    let loss = |color: PieceColor| if color == PieceColor::White { "0-1" } else { "1-0" };
+
+    for (index, color) in [(job.white, PieceColor::White), (job.black, PieceColor::Black)] {
+        // An engine that died for good in an earlier game gets a fresh process
+        if engines.get(&index).is_some_and(|engine| !engine.is_alive()) {
+            engines.remove(&index);
+        }
+        if let Entry::Vacant(entry) = engines.entry(index) {
+            match start_engine(&config.engines[index]) {
+                Ok(engine) => {
+                    entry.insert(engine);
+                }
+                Err(err) => return finish(game, annotations, loss(color), format!("{} failed to start: {}", config.engines[index].name, err)),
+            }
+        }
+        engines.get_mut(&index).unwrap().new_game();
+    }
+
+    let mut clocks = match config.time_control {
+        TimeControl::Clock { base_ms, .. } => [base_ms as i64; 2],
+        TimeControl::PerMove(_) => [0; 2],
+    };
+    let mut repetitions: HashMap<String, u32> = HashMap::new();
+    *repetitions.entry(game.board().position_key()).or_default() += 1;
+    let mut resign_counts = [0u32; 2];
+    let mut draw_count = 0u32;
+
+    loop {
+        let board = game.board();
+        let turn = board.current_turn();
+        match board.check_game_state() {
+            GameState::Checkmate => return finish(game, annotations, loss(turn), "checkmate".to_string()),
+            GameState::Stalemate => return finish(game, annotations, "1/2-1/2", "stalemate".to_string()),
+            _ => {}
+        }
+        if board.is_insufficient_material() {
+            return finish(game, annotations, "1/2-1/2", "insufficient material".to_string());
+        }
+        if board.halfmove_clock() >= 100 {
+            return finish(game, annotations, "1/2-1/2", "50-move rule".to_string());
+        }
+        if repetitions.get(&board.position_key()).is_some_and(|&count| count >= 3) {
+            return finish(game, annotations, "1/2-1/2", "threefold repetition".to_string());
+        }
+        if game.moves().len() >= config.max_plies {
+            return finish(game, annotations, "1/2-1/2", format!("adjudicated: {} plies", config.max_plies));
+        }
+
+        let side = (turn == PieceColor::Black) as usize;
+        let index = if turn == PieceColor::White { job.white } else { job.black };
+        let name = &config.engines[index].name;
+        let limits = match &config.time_control {
+            TimeControl::Clock { inc_ms, .. } => SearchLimits::clock(
+                clocks[0].max(1) as u64,
+                clocks[1].max(1) as u64,
+                *inc_ms,
+                *inc_ms,
+                None,
+            ),
+            TimeControl::PerMove(limits) => limits.clone(),
+        };
+
+        let engine = engines.get_mut(&index).unwrap();
+        engine.set_position_with_moves(&game.start_board().to_fen(), game.moves());
+        let started = Instant::now();
+        let best = engine.get_best_move(&limits);
+        let elapsed = started.elapsed();
+        let info = engine.last_info().map(|info| (info.score, info.depth));
+
+        if let TimeControl::Clock { inc_ms, .. } = config.time_control {
+            clocks[side] -= elapsed.as_millis() as i64;
+            if clocks[side] < 0 {
+                // Flagging only loses if the opponent could still mate
+                if !game.board().has_mating_material(turn.opposite()) {
+                    return finish(game, annotations, "1/2-1/2", format!("{} lost on time, opponent cannot mate", name));
+                }
+                return finish(game, annotations, loss(turn), format!("{} lost on time", name));
+            }
+            clocks[side] += inc_ms as i64;
+        }
+
+        let Some(best) = best else {
+            return finish(game, annotations, loss(turn), format!("{} crashed or stopped responding", name));
+        };
+        let Some(mv) = game.board().find_legal_move(&best) else {
+            return finish(game, annotations, loss(turn), format!("{} played illegal move {}", name, best));
+        };
+
+        let comment = info.map(|(score, depth)| {
+            let score = match score {
+                Score::Cp(cp) => format!("{:+.2}", cp as f32 / 100.0),
+                Score::Mate(n) => format!("M{}", n),
+            };
+            format!("{}/{} {:.1}s", score, depth, elapsed.as_secs_f32())
+        });
+        annotations.push(MoveAnnotation { nag: None, comment });
+        game.make_move(mv);
+        *repetitions.entry(game.board().position_key()).or_default() += 1;
+
+        // Adjudication, on the mover's own evaluation
+        let Some((score, _)) = info else { continue };
+        let cp = score_cp(score);
+        if let Some(rule) = config.resign {
+            resign_counts[side] = if cp <= -rule.cp { resign_counts[side] + 1 } else { 0 };
+            if resign_counts[side] >= rule.moves {
+                return finish(game, annotations, loss(turn), format!("adjudicated: {} resigns", name));
+            }
+        }
+        if let Some(rule) = config.draw {
+            let move_number = game.board().fullmove_number() as u32;
+            draw_count = if move_number >= rule.after_move && cp.abs() <= rule.cp { draw_count + 1 } else { 0 };
+            if draw_count >= rule.moves * 2 {
+                return finish(game, annotations, "1/2-1/2", "adjudicated: drawn score".to_string());
+            }
+        }
+    }
+}
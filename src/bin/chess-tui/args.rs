@@ -0,0 +1,112 @@
+use std::path::Path;
+use std::time::Duration;
+
+use chess_game::chess::PieceColor;
+use chess_game::clock::TimeControl;
+use chess_game::engine::BUILTIN_ENGINE;
+use chess_game::game_mode::GameMode;
+
+#[cfg(feature = "bundled-engine")]
+const STOCKFISH_PATH: &str = env!("BUNDLED_ENGINE_PATH");
+#[cfg(not(feature = "bundled-engine"))]
+const STOCKFISH_PATH: &str = "./src/chess/stockfish/stockfish-ubuntu-x86-64-avx2";
+
+pub const USAGE: &str = "\
+Usage: chess-tui [--mode two|computer|watch] [--color white|black]
+                 [--engine PATH|builtin] [--level easy|medium|hard|PROFILE]
+                 [--tc MINUTES[+INC]] [--fen FEN] [--ascii]
+
+Plays chess in the terminal: two players at one keyboard, against the
+engine, or engine against engine. Moves are typed in SAN (Nf3) or UCI
+(g1f3); type 'help' during a game for the other commands. --level also
+takes the name of a profile from difficulty_profiles.cfg. Without
+--engine, Stockfish is used if it is installed and the built-in engine
+otherwise.";
+
+#[derive(Debug, Clone)]
+pub struct Config {
+    pub mode: GameMode,
+    /// The human's side against the computer
+    pub color: PieceColor,
+    pub engine: String,
+    pub level: String,
+    /// The same control for both sides, or no clock
+    pub time_control: Option<TimeControl>,
+    pub fen: Option<String>,
+    /// FEN letters instead of chess figurines, for terminals without them
+    pub ascii: bool,
+}
+
+pub fn parse_args(args: impl IntoIterator<Item = String>) -> Result<Config, String> {
+    let mut config = Config {
+        mode: GameMode::VsStockfish,
+        color: PieceColor::White,
+        engine: if Path::new(STOCKFISH_PATH).exists() { STOCKFISH_PATH } else { BUILTIN_ENGINE }.to_string(),
+        level: "Medium".to_string(),
+        time_control: None,
+        fen: None,
+        ascii: false,
+    };
+
+    let mut args = args.into_iter().peekable();
+    while let Some(arg) = args.next() {
+        // Values for the current flag: everything up to the next flag
+        let mut values = Vec::new();
+        while let Some(value) = args.next_if(|next| !next.starts_with("--")) {
+            values.push(value);
+        }
+
+        match arg.as_str() {
+            "--mode" => {
+                config.mode = match single::<String>(&arg, &values)?.as_str() {
+                    "two" => GameMode::TwoPlayer,
+                    "computer" => GameMode::VsStockfish,
+                    "watch" => GameMode::EngineVsEngine,
+                    other => return Err(format!("unknown mode '{}'", other)),
+                }
+            }
+            "--color" => {
+                config.color = match single::<String>(&arg, &values)?.as_str() {
+                    "white" => PieceColor::White,
+                    "black" => PieceColor::Black,
+                    other => return Err(format!("unknown color '{}'", other)),
+                }
+            }
+            "--engine" => config.engine = single(&arg, &values)?,
+            // Profile names may have spaces
+            "--level" if !values.is_empty() => config.level = values.join(" "),
+            "--tc" => config.time_control = Some(parse_time_control(&single::<String>(&arg, &values)?)?),
+            "--fen" if !values.is_empty() => config.fen = Some(values.join(" ")),
+            "--ascii" => config.ascii = true,
+            "--help" | "-h" => return Err(String::new()),
+            "--level" | "--fen" => return Err(format!("{} needs a value", arg)),
+            _ => return Err(format!("unknown argument '{}'", arg)),
+        }
+    }
+
+    Ok(config)
+}
+
+/// "5+3": five minutes each plus three seconds a move.
+fn parse_time_control(text: &str) -> Result<TimeControl, String> {
+    let bad = || format!("bad time control '{}', expected MINUTES+INC", text);
+    let (minutes, increment) = text.split_once('+').unwrap_or((text, "0"));
+    let minutes: f64 = minutes.parse().map_err(|_| bad())?;
+    let increment: u64 = increment.parse().map_err(|_| bad())?;
+    if minutes <= 0.0 {
+        return Err(bad());
+    }
+
+    let base = Duration::from_secs_f64(minutes * 60.0);
+    Ok(match increment {
+        0 => TimeControl::sudden_death(base),
+        secs => TimeControl::fischer(base, Duration::from_secs(secs)),
+    })
+}
+
+fn single<T: std::str::FromStr>(flag: &str, values: &[String]) -> Result<T, String> {
+    match values {
+        [value] => value.parse().map_err(|_| format!("bad value '{}' for {}", value, flag)),
+        _ => Err(format!("{} takes one value", flag)),
+    }
+}
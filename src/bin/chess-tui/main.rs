@@ -0,0 +1,418 @@
+//! Plays and analyses games in the terminal, with the same game modes as
+//! the GUI. Run with `--help` for usage.
+
+mod args;
+
+use std::env;
+use std::io::{self, BufRead, Write};
+use std::path::Path;
+use std::process::ExitCode;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::thread;
+use std::time::Duration;
+
+use strum::IntoEnumIterator;
+
+use chess_game::chess::{format_numbered_line, write_pgn, Board, Game, PieceColor};
+use chess_game::clock::{self, ChessClock};
+use chess_game::difficulty::{self, DifficultyProfile, PROFILES_PATH};
+use chess_game::game_controller::{GameController, GameEvent};
+use chess_game::game_mode::{Difficulty, GameMode};
+use chess_game::player::{EnginePlayer, HumanPlayer, Player, PlayerAction};
+use chess_game::stockfish::{Score, SearchLimits, Stockfish};
+
+use args::{Config, USAGE};
+
+const HELP: &str = "\
+Type a move in SAN (Nf3, exd5, O-O, e8=Q), UCI (g1f3) or coordinates
+(g1-f3), or one of:
+  board          show the board again
+  flip           turn the board around
+  undo           take back the last move (not in games on the clock)
+  fen            print the position as FEN
+  pgn            print the game so far as PGN
+  analyze [MS]   show the engine's best lines, searching MS milliseconds
+  clock          show both clocks
+  resign         resign the game
+  draw           offer a draw, or accept the one on offer
+  decline        decline a draw offer
+  claim          claim a draw by threefold repetition or the 50-move rule
+  new            start a new game
+  quit           leave";
+
+/// How long `analyze` searches when not given a time.
+const ANALYSIS_MS: u64 = 2000;
+const ANALYSIS_LINES: usize = 3;
+
+fn index(side: PieceColor) -> usize {
+    match side {
+        PieceColor::White => 0,
+        PieceColor::Black => 1,
+    }
+}
+
+fn side_name(side: PieceColor) -> &'static str {
+    match side {
+        PieceColor::White => "White",
+        PieceColor::Black => "Black",
+    }
+}
+
+/// "+0.35", "-1.20", or "#3" / "-#3" for mates, from White's side.
+fn score_text(score: Score) -> String {
+    match score {
+        Score::Cp(cp) => format!("{:+.2}", cp as f32 / 100.0),
+        Score::Mate(n) if n < 0 => format!("-#{}", -n),
+        Score::Mate(n) => format!("#{}", n),
+    }
+}
+
+/// A built-in difficulty, or a custom profile from the profiles file.
+fn find_profile(name: &str) -> Result<DifficultyProfile, String> {
+    if let Some(difficulty) = Difficulty::iter().find(|d| d.to_string().eq_ignore_ascii_case(name)) {
+        return Ok(difficulty.profile());
+    }
+    let profiles = difficulty::load_profiles(Path::new(PROFILES_PATH)).map_err(|err| format!("{}: {}", PROFILES_PATH, err))?;
+    profiles
+        .into_iter()
+        .find(|profile| profile.name.eq_ignore_ascii_case(name))
+        .ok_or_else(|| format!("no difficulty level or profile named '{}'", name))
+}
+
+/// Reads lines from stdin on a thread of its own, so the game keeps
+/// running while nobody types.
+fn spawn_input() -> Receiver<String> {
+    let (sender, lines) = mpsc::channel();
+    thread::spawn(move || {
+        for line in io::stdin().lock().lines() {
+            let Ok(line) = line else { break };
+            if sender.send(line).is_err() {
+                break;
+            }
+        }
+    });
+    lines
+}
+
+fn prompt() {
+    print!("> ");
+    let _ = io::stdout().flush();
+}
+
+/// Where typed actions go, for White and Black when they are played at the
+/// keyboard.
+type HumanInputs = [Option<Sender<PlayerAction>>; 2];
+
+struct Session {
+    config: Config,
+    profile: DifficultyProfile,
+    start: Board,
+    controller: GameController,
+    humans: HumanInputs,
+    flipped: bool,
+    // Started the first time `analyze` is used
+    analysis: Option<Stockfish>,
+}
+
+impl Session {
+    fn new(config: Config, profile: DifficultyProfile, start: Board) -> Result<Self, String> {
+        let flipped = config.mode == GameMode::VsStockfish && config.color == PieceColor::Black;
+        let (controller, humans) = start_game(&config, &profile, &start)?;
+        Ok(Self { config, profile, start, controller, humans, flipped, analysis: None })
+    }
+
+    fn new_game(&mut self) -> Result<(), String> {
+        let (controller, humans) = start_game(&self.config, &self.profile, &self.start)?;
+        self.controller = controller;
+        self.humans = humans;
+        Ok(())
+    }
+
+    /// The side the keyboard acts for right now: the side to move when two
+    /// people share it, nobody when engines play each other.
+    fn keyboard_side(&self) -> Option<PieceColor> {
+        match self.config.mode {
+            GameMode::TwoPlayer => Some(self.controller.side_to_move()),
+            GameMode::VsStockfish => Some(self.config.color),
+            GameMode::EngineVsEngine => None,
+        }
+    }
+
+    fn send(&self, action: PlayerAction) {
+        let sender = self.keyboard_side().and_then(|side| self.humans[index(side)].as_ref());
+        match sender {
+            Some(sender) => {
+                let _ = sender.send(action);
+            }
+            None => println!("The engines are playing this game."),
+        }
+    }
+
+    fn show_board(&self) {
+        println!("\n{}\n", self.controller.board().diagram(self.flipped, !self.config.ascii));
+        if self.controller.clock().is_some() {
+            self.show_clock();
+        }
+        if let Some(outcome) = self.controller.outcome() {
+            println!("{}  {}", outcome, outcome.result());
+        } else {
+            let side = self.controller.side_to_move();
+            let name = self.controller.player(side).map(|player| player.name()).unwrap_or_default();
+            println!("{} to move ({})", side_name(side), name);
+        }
+    }
+
+    fn show_clock(&self) {
+        match self.controller.clock() {
+            Some(clock) => println!(
+                "White {}   Black {}",
+                clock::format_time(clock.remaining(PieceColor::White)),
+                clock::format_time(clock.remaining(PieceColor::Black))
+            ),
+            None => println!("This game has no clock."),
+        }
+    }
+
+    /// Prints what happened since the last tick.
+    fn report(&mut self, events: Vec<GameEvent>) {
+        let moves_made = events.iter().filter(|event| matches!(event, GameEvent::MoveMade { .. })).count();
+        let game = self.controller.game();
+        let start = game.start_board();
+        // Ply of the first of these moves, counted from the start position
+        let mut ply = game.moves().len() - moves_made + usize::from(start.current_turn() == PieceColor::Black);
+
+        for event in &events {
+            match event {
+                GameEvent::MoveMade { side, san, .. } => {
+                    let number = start.fullmove_number() + ply / 2;
+                    println!("{}", format_numbered_line(std::slice::from_ref(san), number, *side == PieceColor::White).trim_end());
+                    ply += 1;
+                }
+                GameEvent::DrawOffered(side) => println!("{} offers a draw.", side_name(*side)),
+                GameEvent::DrawDeclined(side) => println!("{}'s draw offer is declined.", side_name(*side)),
+                GameEvent::Rejected { reason, .. } => println!("Not possible: {}.", reason),
+                GameEvent::ClockTick { .. } | GameEvent::GameOver(_) => {}
+            }
+        }
+
+        let over = events.iter().any(|event| matches!(event, GameEvent::GameOver(_)));
+        if moves_made > 0 || over {
+            self.show_board();
+        }
+        if over {
+            println!("Type 'new' to play again, 'pgn' for the game record or 'quit' to leave.");
+        }
+    }
+
+    /// Carries out one line of input. Returns false on `quit`.
+    fn command(&mut self, line: &str) -> bool {
+        let mut words = line.split_whitespace();
+        let Some(word) = words.next() else {
+            return true;
+        };
+        match word {
+            "quit" | "exit" => return false,
+            "help" | "?" => println!("{}", HELP),
+            "board" => self.show_board(),
+            "flip" => {
+                self.flipped = !self.flipped;
+                self.show_board();
+            }
+            "fen" => println!("{}", self.controller.board().to_fen()),
+            "pgn" => println!("{}", self.pgn()),
+            "clock" => self.show_clock(),
+            "undo" => self.undo(),
+            "analyze" | "analyse" => {
+                let ms = words.next().and_then(|ms| ms.parse().ok()).unwrap_or(ANALYSIS_MS);
+                if let Err(err) = self.analyze(ms) {
+                    println!("Analysis failed: {}", err);
+                }
+            }
+            "new" => match self.new_game() {
+                Ok(()) => self.show_board(),
+                Err(err) => println!("Couldn't start a game: {}", err),
+            },
+            "resign" => self.send(PlayerAction::Resign),
+            "draw" => {
+                let offered_to_us = self.keyboard_side().is_some_and(|side| self.controller.draw_offer() == Some(side.opposite()));
+                self.send(if offered_to_us { PlayerAction::AcceptDraw } else { PlayerAction::OfferDraw });
+            }
+            "decline" => self.send(PlayerAction::DeclineDraw),
+            "claim" => self.send(PlayerAction::ClaimDraw),
+            _ => self.play(line.trim()),
+        }
+        true
+    }
+
+    fn play(&mut self, text: &str) {
+        if self.controller.is_over() {
+            println!("The game is over; type 'new' to start another.");
+            return;
+        }
+        let side = self.controller.side_to_move();
+        if self.humans[index(side)].is_none() {
+            println!("It is not your move.");
+            return;
+        }
+        match self.controller.board().read_move(text) {
+            Ok(mv) => {
+                self.send(PlayerAction::Move(mv));
+                if let Some(opponent) = self.controller.player(side.opposite()).filter(|_| self.humans[index(side.opposite())].is_none()) {
+                    println!("{} is thinking...", opponent.name());
+                }
+            }
+            Err(err) => println!("{}.", err),
+        }
+    }
+
+    /// Takes back the last move; against the computer, also the engine's
+    /// reply, so it is the player's turn again.
+    fn undo(&mut self) {
+        if let Err(err) = self.controller.take_back() {
+            println!("Can't undo: {}.", err);
+            return;
+        }
+        if self.config.mode == GameMode::VsStockfish
+            && self.controller.side_to_move() != self.config.color
+            && !self.controller.game().moves().is_empty()
+        {
+            let _ = self.controller.take_back();
+        }
+        self.show_board();
+    }
+
+    fn analyze(&mut self, ms: u64) -> Result<(), String> {
+        let engine = match &mut self.analysis {
+            Some(engine) => engine,
+            None => {
+                let mut engine = Stockfish::new(&self.config.engine).map_err(|err| format!("{}: {}", self.config.engine, err))?;
+                engine.set_multipv(ANALYSIS_LINES);
+                self.analysis.insert(engine)
+            }
+        };
+        let game = self.controller.game();
+        let board = game.board();
+        engine.set_position_with_moves(&game.start_board().to_fen(), game.moves());
+        engine.get_best_move(&SearchLimits::movetime(ms)).ok_or("no move found")?;
+
+        let white_to_move = board.current_turn() == PieceColor::White;
+        for line in engine.lines() {
+            let sans = board.uci_line_to_san(&line.pv);
+            println!(
+                "{:>7}  depth {:<3} {}",
+                score_text(line.score.for_white(board.current_turn())),
+                line.depth,
+                format_numbered_line(&sans, board.fullmove_number(), white_to_move).trim_end()
+            );
+        }
+        Ok(())
+    }
+
+    fn pgn(&self) -> String {
+        let name = |side| self.controller.player(side).map(|player| player.name()).unwrap_or_default();
+        let mut tags = vec![("White", name(PieceColor::White)), ("Black", name(PieceColor::Black))];
+        if let Some(outcome) = self.controller.outcome() {
+            tags.push(("Result", outcome.result().to_string()));
+            tags.push(("Termination", outcome.termination.pgn_tag().to_string()));
+        }
+        if let Some(clock) = self.controller.clock() {
+            tags.push(("TimeControl", clock.control(PieceColor::White).pgn_tag()));
+        }
+        write_pgn(self.controller.game(), &tags, &[])
+    }
+}
+
+/// Seats a person or an engine on each side, as the mode asks for.
+fn start_game(
+    config: &Config,
+    profile: &DifficultyProfile,
+    start: &Board,
+) -> Result<(GameController, HumanInputs), String> {
+    let mut humans = [None, None];
+    let mut seat = |side: PieceColor| -> Result<Box<dyn Player>, String> {
+        let human = match config.mode {
+            GameMode::TwoPlayer => true,
+            GameMode::VsStockfish => side == config.color,
+            GameMode::EngineVsEngine => false,
+        };
+        if human {
+            let (player, input) = HumanPlayer::new(side_name(side));
+            humans[index(side)] = Some(input);
+            return Ok(Box::new(player));
+        }
+        let engine = Stockfish::new(&config.engine).map_err(|err| format!("{}: {}", config.engine, err))?;
+        Ok(Box::new(EnginePlayer::new(engine, profile.clone())))
+    };
+    let white = seat(PieceColor::White)?;
+    let black = seat(PieceColor::Black)?;
+
+    let clock = config.time_control.clone().map(|control| ChessClock::new(control.clone(), control));
+    let controller = GameController::new(Game::from_board(start.clone()), clock).with_players(white, black);
+    Ok((controller, humans))
+}
+
+fn main() -> ExitCode {
+    let config = match args::parse_args(env::args().skip(1)) {
+        Ok(config) => config,
+        Err(err) => {
+            if !err.is_empty() {
+                eprintln!("error: {}\n", err);
+            }
+            eprintln!("{}", USAGE);
+            return ExitCode::from(2);
+        }
+    };
+
+    let start = match &config.fen {
+        Some(fen) => Board::from_fen(fen),
+        None => Ok(Board::new()),
+    };
+    let setup = start.and_then(|start| Ok((find_profile(&config.level)?, start)));
+    let mut session = match setup.and_then(|(profile, start)| Session::new(config, profile, start)) {
+        Ok(session) => session,
+        Err(err) => {
+            eprintln!("error: {}", err);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    println!("Type 'help' for the commands.");
+    session.show_board();
+
+    let mut input = Some(spawn_input());
+    let mut prompt_due = true;
+    loop {
+        session.controller.tick();
+        let events = session.controller.take_events();
+        if events.iter().any(|event| !matches!(event, GameEvent::ClockTick { .. })) {
+            session.report(events);
+            prompt_due = true;
+        }
+
+        match &input {
+            Some(lines) => match lines.recv_timeout(Duration::from_millis(100)) {
+                Ok(line) => {
+                    if !session.command(&line) {
+                        break;
+                    }
+                    prompt_due = true;
+                }
+                // Prompt once the output has settled, not while an engine
+                // is still about to answer
+                Err(RecvTimeoutError::Timeout) if prompt_due => {
+                    prompt();
+                    prompt_due = false;
+                }
+                Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => input = None,
+            },
+            // With stdin closed, only a game between engines can go on
+            None if session.config.mode == GameMode::EngineVsEngine && !session.controller.is_over() => {
+                thread::sleep(Duration::from_millis(100));
+            }
+            None => break,
+        }
+    }
+    println!();
+    ExitCode::SUCCESS
+}
@@ -0,0 +1,390 @@
+use egui::{Align2, Color32, CursorIcon, FontId, Id, LayerId, Order, Pos2, Rect, Sense, Stroke, Vec2};
+use crate::app::draw_piece;
+use crate::chess::{Board, CastlingRights, Piece, PieceColor, PieceType, Position, PositionError};
+
+const PALETTE_SQUARE: f32 = 40.0;
+const PIECE_TYPES: [PieceType; 6] = [
+    PieceType::King,
+    PieceType::Queen,
+    PieceType::Rook,
+    PieceType::Bishop,
+    PieceType::Knight,
+    PieceType::Pawn,
+];
+
+/// What the editor asks the app to do with the position.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EditorAction {
+    PlayTwoPlayer,
+    PlayComputer,
+    Analyze,
+    Back,
+}
+
+/// Sets up a position to play or analyse from: pieces are dragged on from
+/// the palette, moved around the board and dragged off it to remove them.
+pub struct BoardEditor {
+    board: Board,
+    flipped: bool,
+    // What a click on a square puts there; None erases
+    brush: Option<Piece>,
+    // Piece being dragged, from the palette or lifted off the board
+    dragged: Option<Piece>,
+    fen: String,
+    fen_error: Option<String>,
+}
+
+impl Default for BoardEditor {
+    fn default() -> Self {
+        let board = Board::new();
+        Self {
+            fen: board.to_fen(),
+            board,
+            flipped: false,
+            brush: Some(Piece::new(PieceType::Pawn, PieceColor::White)),
+            dragged: None,
+            fen_error: None,
+        }
+    }
+}
+
+impl BoardEditor {
+    pub fn board(&self) -> &Board {
+        &self.board
+    }
+
+    pub fn flipped(&self) -> bool {
+        self.flipped
+    }
+
+    /// Everything around the board: the palette, the position settings,
+    /// FEN import and export, and the ways out of the editor.
+    pub fn show_controls(&mut self, ui: &mut egui::Ui) -> Option<EditorAction> {
+        let mut action = None;
+
+        ui.heading("Set Up Position");
+        ui.label("Drag pieces onto the board, or pick one and click squares. Drag a piece off the board or right-click it to remove it.");
+        for color in [PieceColor::White, PieceColor::Black] {
+            ui.horizontal(|ui| {
+                for piece_type in PIECE_TYPES {
+                    self.palette_piece(ui, Piece::new(piece_type, color));
+                }
+            });
+        }
+        if ui.selectable_label(self.brush.is_none(), "Eraser").clicked() {
+            self.brush = None;
+        }
+
+        ui.separator();
+        ui.horizontal(|ui| {
+            if ui.button("Clear").clicked() {
+                let turn = self.board.current_turn();
+                self.board = Board::empty();
+                self.board.set_turn(turn);
+                self.edited();
+            }
+            if ui.button("Start position").clicked() {
+                self.board = Board::new();
+                self.edited();
+            }
+            if ui.button("Flip board").clicked() {
+                self.flipped = !self.flipped;
+            }
+        });
+
+        ui.horizontal(|ui| {
+            ui.label("To move:");
+            for color in [PieceColor::White, PieceColor::Black] {
+                if ui.radio(self.board.current_turn() == color, side_name(color)).clicked() {
+                    self.board.set_turn(color);
+                    self.edited();
+                }
+            }
+        });
+
+        ui.label("Castling:");
+        let mut rights = self.board.castling_rights();
+        ui.horizontal(|ui| {
+            for (color, kingside, label) in [
+                (PieceColor::White, true, "White O-O"),
+                (PieceColor::White, false, "White O-O-O"),
+                (PieceColor::Black, true, "Black O-O"),
+                (PieceColor::Black, false, "Black O-O-O"),
+            ] {
+                let right = castling_right(&mut rights, color, kingside);
+                ui.add_enabled(castling_possible(&self.board, color, kingside), egui::Checkbox::new(right, label))
+                    .on_disabled_hover_text("Needs the king and rook on their starting squares");
+            }
+        });
+        if rights != self.board.castling_rights() {
+            self.board.set_castling_rights(rights);
+            self.edited();
+        }
+
+        ui.horizontal(|ui| {
+            ui.label("En passant:");
+            let current = self.board.en_passant_target();
+            let mut target = current;
+            egui::ComboBox::from_id_source("en_passant")
+                .selected_text(current.map_or("-".to_string(), |square| square.to_string()))
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut target, None, "-");
+                    for square in en_passant_squares(&self.board) {
+                        ui.selectable_value(&mut target, Some(square), square.to_string());
+                    }
+                });
+            if target != current {
+                self.board.set_en_passant_target(target);
+                self.edited();
+            }
+        });
+
+        ui.separator();
+        ui.label("FEN:");
+        let response = ui.add(egui::TextEdit::singleline(&mut self.fen).desired_width(f32::INFINITY));
+        if response.changed() {
+            self.fen_error = None;
+        }
+        let entered = response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+        ui.horizontal(|ui| {
+            if ui.button("Load FEN").clicked() || entered {
+                self.load_fen();
+            }
+            if ui.button("Copy FEN").clicked() {
+                ui.output_mut(|o| o.copied_text = self.board.to_fen());
+            }
+        });
+        if let Some(error) = &self.fen_error {
+            ui.colored_label(Color32::from_rgb(220, 80, 60), error);
+        }
+
+        ui.separator();
+        let problems = self.board.validate().err().unwrap_or_default();
+        if problems.is_empty() {
+            ui.colored_label(Color32::from_rgb(60, 160, 80), "The position is legal");
+        }
+        for problem in &problems {
+            ui.colored_label(Color32::from_rgb(220, 80, 60), problem.to_string());
+        }
+        ui.add_enabled_ui(problems.is_empty(), |ui| {
+            if ui.button("Play two players").clicked() {
+                action = Some(EditorAction::PlayTwoPlayer);
+            }
+            if ui.button("Play vs computer").clicked() {
+                action = Some(EditorAction::PlayComputer);
+            }
+            if ui.button("Analyze").clicked() {
+                action = Some(EditorAction::Analyze);
+            }
+        });
+        if ui.button("Back").clicked() {
+            action = Some(EditorAction::Back);
+        }
+
+        action
+    }
+
+    /// The board being edited, plus the piece under the pointer while one
+    /// is dragged.
+    pub fn show_board(&mut self, ui: &mut egui::Ui) {
+        let available = ui.available_size();
+        let board_size = available.x.min(available.y) - 20.0;
+        let square_size = board_size / 8.0;
+        let board_rect = Rect::from_min_size(
+            ui.available_rect_before_wrap().min + Vec2::new((available.x - board_size) / 2.0, 0.0),
+            Vec2::splat(board_size),
+        );
+        let response = ui.allocate_rect(board_rect, Sense::click_and_drag());
+        let painter = ui.painter();
+
+        for rank in 0..8 {
+            for file in 0..8 {
+                let position = Position::new(rank, file);
+                let rect = self.square_rect(board_rect, position);
+                let color = if (rank + file) % 2 == 0 {
+                    Color32::from_rgb(181, 136, 99)
+                } else {
+                    Color32::from_rgb(240, 217, 181)
+                };
+                painter.rect_filled(rect, 0.0, color);
+                if self.board.en_passant_target() == Some(position) {
+                    painter.rect_stroke(rect.shrink(2.0), 0.0, Stroke::new(2.0, Color32::from_rgb(60, 110, 220)));
+                }
+                if let Some(piece) = self.board.get_piece(position) {
+                    draw_piece(painter, rect, piece);
+                }
+            }
+        }
+
+        // Coordinates, since pieces are placed by square
+        let font = FontId::proportional(12.0);
+        let text_color = ui.visuals().text_color();
+        for index in 0..8 {
+            let file_rect = self.square_rect(board_rect, Position::new(0, index));
+            let file_name = ((b'a' + index as u8) as char).to_string();
+            painter.text(Pos2::new(file_rect.center().x, board_rect.bottom() + 2.0), Align2::CENTER_TOP, file_name, font.clone(), text_color);
+            let rank_rect = self.square_rect(board_rect, Position::new(index, 0));
+            painter.text(Pos2::new(board_rect.left() - 4.0, rank_rect.center().y), Align2::RIGHT_CENTER, (index + 1).to_string(), font.clone(), text_color);
+        }
+
+        if response.drag_started() {
+            let origin = ui.input(|i| i.pointer.press_origin()).and_then(|pos| self.square_at(board_rect, pos));
+            if let Some(origin) = origin {
+                self.dragged = self.board.get_piece(origin);
+                self.board.set_piece(origin, None);
+            }
+        } else if response.clicked() {
+            if let Some(square) = response.interact_pointer_pos().and_then(|pos| self.square_at(board_rect, pos)) {
+                // Clicking the piece that is already there takes it away again
+                let piece = if self.board.get_piece(square) == self.brush { None } else { self.brush };
+                self.board.set_piece(square, piece);
+                self.edited();
+            }
+        } else if response.secondary_clicked()
+            && let Some(square) = response.interact_pointer_pos().and_then(|pos| self.square_at(board_rect, pos))
+        {
+            self.board.set_piece(square, None);
+            self.edited();
+        }
+
+        let Some(piece) = self.dragged else {
+            return;
+        };
+        let pointer = ui.input(|i| i.pointer.latest_pos());
+        if ui.input(|i| i.pointer.any_released()) {
+            // Dropped off the board, the piece is gone
+            if let Some(square) = pointer.and_then(|pos| self.square_at(board_rect, pos)) {
+                self.board.set_piece(square, Some(piece));
+            }
+            self.dragged = None;
+            self.edited();
+        } else if let Some(pointer) = pointer {
+            ui.ctx().set_cursor_icon(CursorIcon::Grabbing);
+            let painter = ui.ctx().layer_painter(LayerId::new(Order::Tooltip, Id::new("dragged_piece")));
+            draw_piece(&painter, Rect::from_center_size(pointer, Vec2::splat(square_size)), piece);
+        }
+    }
+
+    /// One piece of the palette: a click picks it for clicking onto
+    /// squares, a drag carries it onto the board.
+    fn palette_piece(&mut self, ui: &mut egui::Ui, piece: Piece) {
+        let (rect, response) = ui.allocate_exact_size(Vec2::splat(PALETTE_SQUARE), Sense::click_and_drag());
+        let background = if self.brush == Some(piece) {
+            ui.visuals().selection.bg_fill
+        } else {
+            Color32::from_rgb(150, 150, 150)
+        };
+        ui.painter().rect_filled(rect, 4.0, background);
+        draw_piece(ui.painter(), rect, piece);
+        if response.clicked() {
+            self.brush = Some(piece);
+        }
+        if response.drag_started() {
+            self.dragged = Some(piece);
+        }
+    }
+
+    fn load_fen(&mut self) {
+        // Problems with the position are listed, so it can be fixed here
+        match Board::parse_fen(self.fen.trim()) {
+            Ok(board) => {
+                self.board = board;
+                self.edited();
+            }
+            Err(err) => self.fen_error = Some(format!("Invalid FEN: {}", err)),
+        }
+    }
+
+    /// Keeps the settings consistent with the pieces after any change:
+    /// castling and en passant rights the board no longer allows are
+    /// dropped, and the FEN field shows the new position.
+    fn edited(&mut self) {
+        let mut rights = self.board.castling_rights();
+        for color in [PieceColor::White, PieceColor::Black] {
+            for kingside in [true, false] {
+                if !castling_possible(&self.board, color, kingside) {
+                    *castling_right(&mut rights, color, kingside) = false;
+                }
+            }
+        }
+        self.board.set_castling_rights(rights);
+
+        if let Some(target) = self.board.en_passant_target() && !en_passant_squares(&self.board).contains(&target) {
+            self.board.set_en_passant_target(None);
+        }
+
+        self.fen = self.board.to_fen();
+        self.fen_error = None;
+    }
+
+    fn square_rect(&self, board_rect: Rect, position: Position) -> Rect {
+        let square_size = board_rect.width() / 8.0;
+        let (column, row) = if self.flipped {
+            (7 - position.file, position.rank)
+        } else {
+            (position.file, 7 - position.rank)
+        };
+        Rect::from_min_size(
+            board_rect.min + Vec2::new(column as f32, row as f32) * square_size,
+            Vec2::splat(square_size),
+        )
+    }
+
+    fn square_at(&self, board_rect: Rect, pos: Pos2) -> Option<Position> {
+        if !board_rect.contains(pos) {
+            return None;
+        }
+        let square_size = board_rect.width() / 8.0;
+        let column = (((pos.x - board_rect.left()) / square_size) as usize).min(7);
+        let row = (((pos.y - board_rect.top()) / square_size) as usize).min(7);
+        Some(if self.flipped {
+            Position::new(row, 7 - column)
+        } else {
+            Position::new(7 - row, column)
+        })
+    }
+}
+
+fn side_name(color: PieceColor) -> &'static str {
+    match color {
+        PieceColor::White => "White",
+        PieceColor::Black => "Black",
+    }
+}
+
+fn castling_right(rights: &mut CastlingRights, color: PieceColor, kingside: bool) -> &mut bool {
+    match (color, kingside) {
+        (PieceColor::White, true) => &mut rights.white_kingside,
+        (PieceColor::White, false) => &mut rights.white_queenside,
+        (PieceColor::Black, true) => &mut rights.black_kingside,
+        (PieceColor::Black, false) => &mut rights.black_queenside,
+    }
+}
+
+/// Whether making `change` to the board leaves it free of `error`.
+fn allows(board: &Board, change: impl FnOnce(&mut Board), error: PositionError) -> bool {
+    let mut board = board.clone();
+    change(&mut board);
+    board.validate().err().is_none_or(|errors| !errors.contains(&error))
+}
+
+/// Whether the king and the rook are where castling on that side needs them.
+fn castling_possible(board: &Board, color: PieceColor, kingside: bool) -> bool {
+    let grant = |board: &mut Board| {
+        let mut rights = board.castling_rights();
+        *castling_right(&mut rights, color, kingside) = true;
+        board.set_castling_rights(rights);
+    };
+    allows(board, grant, PositionError::ImpossibleCastling { color, kingside })
+}
+
+/// Squares a pawn of the side that just moved can have skipped over.
+fn en_passant_squares(board: &Board) -> Vec<Position> {
+    let target_rank = if board.current_turn() == PieceColor::White { 5 } else { 2 };
+    (0..8)
+        .map(|file| Position::new(target_rank, file))
+        .filter(|&square| {
+            allows(board, |board| board.set_en_passant_target(Some(square)), PositionError::ImpossibleEnPassant(square))
+        })
+        .collect()
+}
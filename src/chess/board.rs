@@ -1,5 +1,91 @@
+use std::fmt;
+
 use super::{ChessMove, GameState, Piece, PieceColor, PieceType, Position};
-use crate::app::ChessApp;
+
+pub const START_FEN: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CastlingRights {
+    pub white_kingside: bool,
+    pub white_queenside: bool,
+    pub black_kingside: bool,
+    pub black_queenside: bool,
+}
+
+impl CastlingRights {
+    pub fn all() -> Self {
+        Self {
+            white_kingside: true,
+            white_queenside: true,
+            black_kingside: true,
+            black_queenside: true,
+        }
+    }
+
+    pub fn none() -> Self {
+        Self {
+            white_kingside: false,
+            white_queenside: false,
+            black_kingside: false,
+            black_queenside: false,
+        }
+    }
+
+    pub fn kingside(&self, color: PieceColor) -> bool {
+        match color {
+            PieceColor::White => self.white_kingside,
+            PieceColor::Black => self.black_kingside,
+        }
+    }
+
+    pub fn queenside(&self, color: PieceColor) -> bool {
+        match color {
+            PieceColor::White => self.white_queenside,
+            PieceColor::Black => self.black_queenside,
+        }
+    }
+
+    /// FEN castling field, e.g. "KQkq" or "-".
+    pub fn to_fen(self) -> String {
+        let mut fen = String::new();
+        if self.white_kingside {
+            fen.push('K');
+        }
+        if self.white_queenside {
+            fen.push('Q');
+        }
+        if self.black_kingside {
+            fen.push('k');
+        }
+        if self.black_queenside {
+            fen.push('q');
+        }
+        if fen.is_empty() {
+            fen.push('-');
+        }
+        fen
+    }
+
+    /// Drops the rights that depend on a king or rook standing on `pos`,
+    /// called whenever a piece leaves or is captured on that square.
+    fn clear_for_square(&mut self, pos: Position) {
+        match (pos.rank, pos.file) {
+            (0, 4) => {
+                self.white_kingside = false;
+                self.white_queenside = false;
+            }
+            (7, 4) => {
+                self.black_kingside = false;
+                self.black_queenside = false;
+            }
+            (0, 0) => self.white_queenside = false,
+            (0, 7) => self.white_kingside = false,
+            (7, 0) => self.black_queenside = false,
+            (7, 7) => self.black_kingside = false,
+            _ => {}
+        }
+    }
+}
 
 pub struct Board {
     squares: [[Option<Piece>; 8]; 8],
@@ -7,6 +93,9 @@ pub struct Board {
     move_count: usize,
     captured_pieces: Vec<Piece>,
     en_passant_target: Option<Position>,
+    castling_rights: CastlingRights,
+    // Half-moves since the last capture or pawn move, for the 50-move rule
+    halfmove_clock: usize,
 }
 
 impl Board {
@@ -43,28 +132,146 @@ impl Board {
         });
 
         // Castling availability
-        fen.push_str(" -"); // Simplified for now
+        fen.push(' ');
+        fen.push_str(&self.castling_rights.to_fen());
 
         // En passant target
-        fen.push_str(" -");
+        match self.en_passant_target {
+            Some(target) => fen.push_str(&format!(" {}", target)),
+            None => fen.push_str(" -"),
+        }
 
         // Halfmove clock and fullmove number
-        fen.push_str(" 0 1");
+        fen.push_str(&format!(" {} {}", self.halfmove_clock, self.fullmove_number()));
 
         fen
     }
+    /// Parses a FEN string and checks the position is legal (see
+    /// [`Board::validate`]). The halfmove clock and fullmove number may be
+    /// left out, as in EPD records.
+    pub fn from_fen(fen: &str) -> Result<Self, String> {
+        let board = Self::parse_fen(fen)?;
+        match board.validate() {
+            Ok(()) => Ok(board),
+            Err(errors) => {
+                let errors: Vec<String> = errors.iter().map(ToString::to_string).collect();
+                Err(format!("illegal position: {}", errors.join("; ")))
+            }
+        }
+    }
+
+    /// Reads a FEN string like [`Board::from_fen`] without checking the
+    /// position is legal, for positions still being set up.
+    pub fn parse_fen(fen: &str) -> Result<Self, String> {
+        let fields: Vec<&str> = fen.split_whitespace().collect();
+        if fields.len() < 4 {
+            return Err(format!("expected at least 4 FEN fields, found {}", fields.len()));
+        }
+
+        let mut squares = [[None; 8]; 8];
+        let ranks: Vec<&str> = fields[0].split('/').collect();
+        if ranks.len() != 8 {
+            return Err(format!("expected 8 ranks, found {}", ranks.len()));
+        }
+        for (row, rank_text) in ranks.iter().enumerate() {
+            let rank = 7 - row;
+            let mut file = 0;
+            for c in rank_text.chars() {
+                if let Some(skip) = c.to_digit(10) {
+                    file += skip as usize;
+                } else {
+                    let piece = Piece::from_fen_char(c).ok_or_else(|| format!("unknown piece '{}'", c))?;
+                    if file < 8 {
+                        squares[rank][file] = Some(piece);
+                    }
+                    file += 1;
+                }
+                if file > 8 {
+                    return Err(format!("rank {} has more than 8 squares", rank + 1));
+                }
+            }
+            if file != 8 {
+                return Err(format!("rank {} has {} squares", rank + 1, file));
+            }
+        }
+
+        let turn = match fields[1] {
+            "w" => PieceColor::White,
+            "b" => PieceColor::Black,
+            other => return Err(format!("unknown side to move '{}'", other)),
+        };
+
+        let mut castling_rights = CastlingRights::none();
+        if fields[2] != "-" {
+            for c in fields[2].chars() {
+                match c {
+                    'K' => castling_rights.white_kingside = true,
+                    'Q' => castling_rights.white_queenside = true,
+                    'k' => castling_rights.black_kingside = true,
+                    'q' => castling_rights.black_queenside = true,
+                    _ => return Err(format!("unknown castling right '{}'", c)),
+                }
+            }
+        }
+
+        let en_passant_target = match fields[3] {
+            "-" => None,
+            square => Some(Position::from_uci(square).ok_or_else(|| format!("bad en passant square '{}'", square))?),
+        };
+
+        let number = |index: usize, default: usize| -> Result<usize, String> {
+            fields.get(index).map_or(Ok(default), |text| text.parse().map_err(|_| format!("bad move number '{}'", text)))
+        };
+        let halfmove_clock = number(4, 0)?;
+        let fullmove = number(5, 1)?.max(1);
+
+        Ok(Self {
+            squares,
+            turn,
+            move_count: (fullmove - 1) * 2 + (turn == PieceColor::Black) as usize,
+            captured_pieces: Vec::new(),
+            en_passant_target,
+            castling_rights,
+            halfmove_clock,
+        })
+    }
+
+    /// The FEN without the move counters: equal for positions that count as
+    /// the same for threefold repetition. An en passant square only counts
+    /// when the capture can actually be played.
+    pub fn position_key(&self) -> String {
+        let mut board = self.clone();
+        if let Some(target) = self.en_passant_target {
+            let capturable = self.legal_moves().iter().any(|mv| {
+                mv.to == target && self.get_piece(mv.from).is_some_and(|piece| piece.piece_type == PieceType::Pawn)
+            });
+            if !capturable {
+                board.en_passant_target = None;
+            }
+        }
+        board.to_fen().rsplitn(3, ' ').nth(2).unwrap_or_default().to_string()
+    }
+
     pub fn new() -> Self {
-        let mut board = Self {
+        let mut board = Self::empty();
+        board.castling_rights = CastlingRights::all();
+
+        // Initialize the board with the standard chess setup
+        board.setup_standard_position();
+        board
+    }
+
+    /// A board with no pieces, White to move and no castling rights.
+    pub fn empty() -> Self {
+        Self {
             en_passant_target: None,
             squares: [[None; 8]; 8],
             turn: PieceColor::White,
             move_count: 0,
             captured_pieces: Vec::new(),
-        };
-
-        // Initialize the board with the standard chess setup
-        board.setup_standard_position();
-        board
+            castling_rights: CastlingRights::none(),
+            halfmove_clock: 0,
+        }
     }
 
     pub fn setup_standard_position(&mut self) {
@@ -119,28 +326,143 @@ impl Board {
         self.turn
     }
 
+    /// Hands the move to `color`, keeping the fullmove number.
+    pub fn set_turn(&mut self, color: PieceColor) {
+        let fullmove = self.fullmove_number();
+        self.turn = color;
+        self.move_count = (fullmove - 1) * 2 + (color == PieceColor::Black) as usize;
+    }
+
+    /// The FEN fullmove number: starts at 1 and goes up after Black moves.
+    pub fn fullmove_number(&self) -> usize {
+        self.move_count / 2 + 1
+    }
+
+    /// Half-moves since the last capture or pawn move.
+    pub fn halfmove_clock(&self) -> usize {
+        self.halfmove_clock
+    }
+
+    pub fn castling_rights(&self) -> CastlingRights {
+        self.castling_rights
+    }
+
+    /// The square a pawn skipped over on the last move, if any.
+    pub fn en_passant_target(&self) -> Option<Position> {
+        self.en_passant_target
+    }
+
+    pub fn set_castling_rights(&mut self, rights: CastlingRights) {
+        self.castling_rights = rights;
+    }
+
+    pub fn set_en_passant_target(&mut self, target: Option<Position>) {
+        self.en_passant_target = target;
+    }
+
+    /// Neither side has enough material left to ever checkmate: bare kings,
+    /// or a lone minor piece, or bishops all on one color.
+    pub fn is_insufficient_material(&self) -> bool {
+        let mut minors = Vec::new();
+        for rank in 0..8 {
+            for file in 0..8 {
+                let Some(piece) = self.squares[rank][file] else { continue };
+                match piece.piece_type {
+                    PieceType::King => {}
+                    PieceType::Knight | PieceType::Bishop => minors.push((piece.piece_type, (rank + file) % 2)),
+                    _ => return false,
+                }
+            }
+        }
+        match minors.as_slice() {
+            [] | [_] => true,
+            _ => minors.iter().all(|&(piece_type, shade)| piece_type == PieceType::Bishop && shade == minors[0].1),
+        }
+    }
+
+    /// Whether `color` could still checkmate by some series of legal moves,
+    /// however unlikely. Decides whether running out of time loses or
+    /// draws. A lone knight, or bishops all on one color, can only mate
+    /// with the opponent's own pieces hemming its king in.
+    pub fn has_mating_material(&self, color: PieceColor) -> bool {
+        let mut knights = 0;
+        // Whether there are bishops on dark and on light squares
+        let mut bishops = [false; 2];
+        // The opponent's pieces besides the king, with their square color
+        let mut blockers = Vec::new();
+        for rank in 0..8 {
+            for file in 0..8 {
+                let Some(piece) = self.squares[rank][file] else { continue };
+                let shade = (rank + file) % 2;
+                if piece.color != color {
+                    if piece.piece_type != PieceType::King {
+                        blockers.push((piece.piece_type, shade));
+                    }
+                    continue;
+                }
+                match piece.piece_type {
+                    PieceType::King => {}
+                    PieceType::Knight => knights += 1,
+                    PieceType::Bishop => bishops[shade] = true,
+                    _ => return true,
+                }
+            }
+        }
+
+        let bishop_shades: Vec<usize> = (0..2).filter(|&shade| bishops[shade]).collect();
+        match (knights, bishop_shades.as_slice()) {
+            (0, []) => false,
+            (1, []) => !blockers.is_empty(),
+            // Bishops of one color can't be helped by an opposing bishop of that color either
+            (0, &[shade]) => blockers
+                .iter()
+                .any(|&(piece_type, blocker_shade)| piece_type != PieceType::Bishop || blocker_shade != shade),
+            _ => true,
+        }
+    }
+
     pub fn make_move(&mut self, chess_move: ChessMove) {
         let from = chess_move.from;
         let to = chess_move.to;
 
         if let Some(mut piece) = self.get_piece(from) {
+            let is_capture = self.get_piece(to).is_some();
+
             // Check if there's a piece to capture
             if let Some(captured_piece) = self.get_piece(to) {
                 self.captured_pieces.push(captured_piece);
             }
 
+            // Pawn moves and captures reset the 50-move count
+            if piece.piece_type == PieceType::Pawn || is_capture {
+                self.halfmove_clock = 0;
+            } else {
+                self.halfmove_clock += 1;
+            }
+
+            // Castling: the king moves two squares and the rook jumps over it
+            if piece.piece_type == PieceType::King && from.file.abs_diff(to.file) == 2 {
+                let (rook_from, rook_to) = if to.file > from.file { (7, 5) } else { (0, 3) };
+                let rook = self.get_piece(Position::new(from.rank, rook_from));
+                self.set_piece(Position::new(from.rank, rook_from), None);
+                self.set_piece(Position::new(from.rank, rook_to), rook.map(|mut r| {
+                    r.has_moved = true;
+                    r
+                }));
+            }
+            self.castling_rights.clear_for_square(from);
+            self.castling_rights.clear_for_square(to);
+
             // Handle pawn promotion (only if moving to promotion rank)
             if piece.piece_type == PieceType::Pawn {
                 let promotion_rank = match piece.color {
-                    PieceColor::White => 0,
-                    PieceColor::Black => 7,
+                    PieceColor::White => 7,
+                    PieceColor::Black => 0,
                 };
                 
-                if to.rank == promotion_rank {
-                    if let Some(promotion_type) = chess_move.promotion {
-                        piece.piece_type = promotion_type;
-                    }
-                } 
+                if to.rank == promotion_rank && let Some(promotion_type) = chess_move.promotion {
+                    piece.piece_type = promotion_type;
+                }
             }
 
             // Handle en passant capture
@@ -157,8 +479,8 @@ impl Board {
             // Set en passant target if pawn moved two squares
             self.en_passant_target = None;
             if piece.piece_type == PieceType::Pawn && (from.rank as i32 - to.rank as i32).abs() == 2 {
-                // White pawns move up (negative direction), Black pawns move down (positive direction)
-                let direction = if piece.color == PieceColor::White { -1 } else { 1 };
+                // The target is the square the pawn skipped over
+                let direction = if piece.color == PieceColor::White { 1 } else { -1 };
                 self.en_passant_target = Some(Position::new((from.rank as i32 + direction) as usize, from.file));
             }
 
@@ -169,6 +491,7 @@ impl Board {
 
             // Switch turns
             self.turn = self.turn.opposite();
+            self.move_count += 1;
                 }
     }
 
@@ -190,7 +513,10 @@ impl Board {
                     self.get_bishop_moves(position, piece, &mut moves);
                     self.get_rook_moves(position, piece, &mut moves);
                 }
-                PieceType::King => self.get_king_moves(position, piece, &mut moves),
+                PieceType::King => {
+                    self.get_king_moves(position, piece, &mut moves);
+                    self.get_castling_moves(position, piece, &mut moves);
+                }
             }
 
             // Filter out moves that would leave the king in check
@@ -200,49 +526,105 @@ impl Board {
         moves
     }
 
-    fn get_pawn_moves(&self, position: Position, piece: Piece, moves: &mut Vec<ChessMove>) {
-                // White pawns move up (negative direction), Black pawns move down (positive direction)
-                // White pawns move up (negative direction), Black pawns move down (positive direction)
+    /// Moves the piece on `position` could be queued to make before its
+    /// side is on move: wherever it could go now, plus captures of its own
+    /// side's pieces and pawn captures onto empty squares, since the
+    /// opponent may have put something there by then. Whether a premove is
+    /// legal is only known once it is played.
+    pub fn premoves(&self, position: Position) -> Vec<ChessMove> {
+        let Some(piece) = self.get_piece(position) else {
+            return Vec::new();
+        };
+
+        // Own pieces become capturable, but still block the way
+        let mut board = self.clone();
+        board.en_passant_target = None;
+        for rank in 0..8 {
+            for file in 0..8 {
+                let square = Position::new(rank, file);
+                if let Some(mut other) = board.get_piece(square).filter(|p| p.color == piece.color && square != position) {
+                    other.color = piece.color.opposite();
+                    board.set_piece(square, Some(other));
+                }
+            }
+        }
+
+        let mut moves = Vec::new();
+        match piece.piece_type {
+            PieceType::Pawn => {
+                board.get_pawn_moves(position, piece, &mut moves);
                 let direction = if piece.color == PieceColor::White { 1 } else { -1 };
-                
-                // Forward move (same file, different rank)
-                if let Some(forward) = position.offset(direction, 0) {
-            if self.get_piece(forward).is_none() {
-                // Regular move
-                moves.push(ChessMove::new(position, forward));
-                
-                // Double move from starting position
-                // White pawns start at rank 6 (second row from bottom), Black at rank 1 (second row from top)
-                let starting_rank = if piece.color == PieceColor::White { 1 } else { 6 };
-                if position.rank == starting_rank {
-                    if let Some(double_forward) = position.offset(direction * 2, 0) {
-                        if self.get_piece(double_forward).is_none() {
-                            moves.push(ChessMove::new(position, double_forward));
-                        }
+                let promotion_rank = if piece.color == PieceColor::White { 7 } else { 0 };
+                for file_offset in [-1, 1] {
+                    let Some(target) = position.offset(direction, file_offset) else {
+                        continue;
+                    };
+                    if board.get_piece(target).is_none() {
+                        moves.push(match target.rank == promotion_rank {
+                            true => ChessMove::with_promotion(position, target, PieceType::Queen),
+                            false => ChessMove::new(position, target),
+                        });
                     }
                 }
             }
+            PieceType::Knight => board.get_knight_moves(position, piece, &mut moves),
+            PieceType::Bishop => board.get_bishop_moves(position, piece, &mut moves),
+            PieceType::Rook => board.get_rook_moves(position, piece, &mut moves),
+            PieceType::Queen => {
+                board.get_bishop_moves(position, piece, &mut moves);
+                board.get_rook_moves(position, piece, &mut moves);
+            }
+            PieceType::King => {
+                board.get_king_moves(position, piece, &mut moves);
+                self.get_castling_moves(position, piece, &mut moves);
+            }
         }
-        
+        moves
+    }
+
+    fn get_pawn_moves(&self, position: Position, piece: Piece, moves: &mut Vec<ChessMove>) {
+        // White pawns move up the board (towards rank 8), Black pawns down
+        let direction = if piece.color == PieceColor::White { 1 } else { -1 };
+
+        // Forward move (same file, different rank)
+        if let Some(forward) = position.offset(direction, 0) && self.get_piece(forward).is_none() {
+            // Regular move, or one of the four promotions on the last rank
+            let promotion_rank = if piece.color == PieceColor::White { 7 } else { 0 };
+            if forward.rank == promotion_rank {
+                for promotion in [PieceType::Queen, PieceType::Rook, PieceType::Bishop, PieceType::Knight] {
+                    moves.push(ChessMove::with_promotion(position, forward, promotion));
+                }
+            } else {
+                moves.push(ChessMove::new(position, forward));
+            }
+
+            // Double move from the starting rank: the second rank for White, the seventh for Black
+            let starting_rank = if piece.color == PieceColor::White { 1 } else { 6 };
+            if position.rank == starting_rank
+                && let Some(double_forward) = position.offset(direction * 2, 0)
+                && self.get_piece(double_forward).is_none()
+            {
+                moves.push(ChessMove::new(position, double_forward));
+            }
+        }
+
         // Capture moves
         for file_offset in [-1, 1].iter() {
             if let Some(capture_pos) = position.offset(direction, *file_offset) {
-                if let Some(target) = self.get_piece(capture_pos) {
-                    if target.color != piece.color {
-                        // Regular capture
-                        let capture_move = ChessMove::new(position, capture_pos);
-                        
-                        // Check for promotion on capture
-                        let promotion_rank = if piece.color == PieceColor::White { 0 } else { 7 };
-                        if capture_pos.rank == promotion_rank {
-                            // Add promotion options for capture
-                            moves.push(ChessMove::with_promotion(position, capture_pos, PieceType::Queen));
-                            moves.push(ChessMove::with_promotion(position, capture_pos, PieceType::Rook));
-                            moves.push(ChessMove::with_promotion(position, capture_pos, PieceType::Bishop));
-                            moves.push(ChessMove::with_promotion(position, capture_pos, PieceType::Knight));
-                        } else {
-                            moves.push(capture_move);
-                        }
+                if let Some(target) = self.get_piece(capture_pos) && target.color != piece.color {
+                    // Regular capture
+                    let capture_move = ChessMove::new(position, capture_pos);
+
+                    // Check for promotion on capture
+                    let promotion_rank = if piece.color == PieceColor::White { 7 } else { 0 };
+                    if capture_pos.rank == promotion_rank {
+                        // Add promotion options for capture
+                        moves.push(ChessMove::with_promotion(position, capture_pos, PieceType::Queen));
+                        moves.push(ChessMove::with_promotion(position, capture_pos, PieceType::Rook));
+                        moves.push(ChessMove::with_promotion(position, capture_pos, PieceType::Bishop));
+                        moves.push(ChessMove::with_promotion(position, capture_pos, PieceType::Knight));
+                    } else {
+                        moves.push(capture_move);
                     }
                 }
                 
@@ -283,28 +665,23 @@ impl Board {
         for (rank_dir, file_dir) in directions.iter() {
             let mut current_pos = position;
             
-            loop {
-                if let Some(next_pos) = current_pos.offset(*rank_dir, *file_dir) {
-                    current_pos = next_pos;
-                    
-                    match self.get_piece(current_pos) {
-                        None => {
-                            // Empty square, can move there
-                            moves.push(ChessMove::new(position, current_pos));
-                        }
-                        Some(target) if target.color != piece.color => {
-                            // Opponent's piece, can capture and then stop
-                            moves.push(ChessMove::new(position, current_pos));
-                            break;
-                        }
-                        _ => {
-                            // Own piece, can't move there and stop looking in this direction
-                            break;
-                        }
+            while let Some(next_pos) = current_pos.offset(*rank_dir, *file_dir) {
+                current_pos = next_pos;
+
+                match self.get_piece(current_pos) {
+                    None => {
+                        // Empty square, can move there
+                        moves.push(ChessMove::new(position, current_pos));
+                    }
+                    Some(target) if target.color != piece.color => {
+                        // Opponent's piece, can capture and then stop
+                        moves.push(ChessMove::new(position, current_pos));
+                        break;
+                    }
+                    _ => {
+                        // Own piece, can't move there and stop looking in this direction
+                        break;
                     }
-                } else {
-                    // Off the board
-                    break;
                 }
             }
         }
@@ -316,28 +693,23 @@ impl Board {
         for (rank_dir, file_dir) in directions.iter() {
             let mut current_pos = position;
             
-            loop {
-                if let Some(next_pos) = current_pos.offset(*rank_dir, *file_dir) {
-                    current_pos = next_pos;
-                    
-                    match self.get_piece(current_pos) {
-                        None => {
-                            // Empty square, can move there
-                            moves.push(ChessMove::new(position, current_pos));
-                        }
-                        Some(target) if target.color != piece.color => {
-                            // Opponent's piece, can capture and then stop
-                            moves.push(ChessMove::new(position, current_pos));
-                            break;
-                        }
-                        _ => {
-                            // Own piece, can't move there and stop looking in this direction
-                            break;
-                        }
+            while let Some(next_pos) = current_pos.offset(*rank_dir, *file_dir) {
+                current_pos = next_pos;
+
+                match self.get_piece(current_pos) {
+                    None => {
+                        // Empty square, can move there
+                        moves.push(ChessMove::new(position, current_pos));
+                    }
+                    Some(target) if target.color != piece.color => {
+                        // Opponent's piece, can capture and then stop
+                        moves.push(ChessMove::new(position, current_pos));
+                        break;
+                    }
+                    _ => {
+                        // Own piece, can't move there and stop looking in this direction
+                        break;
                     }
-                } else {
-                    // Off the board
-                    break;
                 }
             }
         }
@@ -366,63 +738,81 @@ impl Board {
             }
         }
         
-        // TODO: Castling (not implemented for simplicity)
     }
 
-    pub fn is_in_check(&self, color: PieceColor) -> bool {
-        // Find the king's position
-        let mut king_pos = None;
-        for rank in 0..8 {
-            for file in 0..8 {
-                let pos = Position::new(rank, file);
-                if let Some(piece) = self.get_piece(pos) {
-                    if piece.piece_type == PieceType::King && piece.color == color {
-                        king_pos = Some(pos);
-                        break;
-                    }
-                }
-            }
-            if king_pos.is_some() {
-                break;
-            }
+    fn get_castling_moves(&self, position: Position, piece: Piece, moves: &mut Vec<ChessMove>) {
+        let home_rank = if piece.color == PieceColor::White { 0 } else { 7 };
+        if position != Position::new(home_rank, 4) || self.is_in_check(piece.color) {
+            return;
         }
-        
-        if let Some(king_position) = king_pos {
-            // Check if any opponent's piece can attack the king
-            for rank in 0..8 {
-                for file in 0..8 {
-                    let pos = Position::new(rank, file);
-                    if let Some(piece) = self.get_piece(pos) {
-                        if piece.color != color {
-                            // Get all moves for this opponent piece
-                            let mut opponent_moves = Vec::new();
-                            match piece.piece_type {
-                                PieceType::Pawn => self.get_pawn_moves(pos, piece, &mut opponent_moves),
-                                PieceType::Knight => self.get_knight_moves(pos, piece, &mut opponent_moves),
-                                PieceType::Bishop => self.get_bishop_moves(pos, piece, &mut opponent_moves),
-                                PieceType::Rook => self.get_rook_moves(pos, piece, &mut opponent_moves),
-                                PieceType::Queen => {
-                                    self.get_bishop_moves(pos, piece, &mut opponent_moves);
-                                    self.get_rook_moves(pos, piece, &mut opponent_moves);
-                                }
-                                PieceType::King => self.get_king_moves(pos, piece, &mut opponent_moves),
-                            }
-                            
-                            // Check if any move can capture the king
-                            for m in opponent_moves {
-                                if m.to == king_position {
-                                    return true;
-                                }
-                            }
-                        }
+
+        let opponent = piece.color.opposite();
+        let rook_in_place = |file: usize| {
+            self.get_piece(Position::new(home_rank, file))
+                .is_some_and(|p| p.piece_type == PieceType::Rook && p.color == piece.color)
+        };
+        let empty = |files: &[usize]| files.iter().all(|&f| self.get_piece(Position::new(home_rank, f)).is_none());
+        let safe = |files: &[usize]| files.iter().all(|&f| !self.is_square_attacked(Position::new(home_rank, f), opponent));
+
+        if self.castling_rights.kingside(piece.color) && rook_in_place(7) && empty(&[5, 6]) && safe(&[5, 6]) {
+            moves.push(ChessMove::new(position, Position::new(home_rank, 6)));
+        }
+        if self.castling_rights.queenside(piece.color) && rook_in_place(0) && empty(&[1, 2, 3]) && safe(&[2, 3]) {
+            moves.push(ChessMove::new(position, Position::new(home_rank, 2)));
+        }
+    }
+
+    /// Whether any piece of `attacker` attacks `target`. Unlike move
+    /// generation this counts pawn captures only, never pawn pushes.
+    pub fn is_square_attacked(&self, target: Position, attacker: PieceColor) -> bool {
+        let is_attacker = |pos: Option<Position>, types: &[PieceType]| {
+            pos.and_then(|p| self.get_piece(p))
+                .is_some_and(|p| p.color == attacker && types.contains(&p.piece_type))
+        };
+
+        // Pawns attack diagonally forward, so look one rank back towards them
+        let pawn_direction = if attacker == PieceColor::White { -1 } else { 1 };
+        if [-1, 1].iter().any(|&df| is_attacker(target.offset(pawn_direction, df), &[PieceType::Pawn])) {
+            return true;
+        }
+
+        let knight_offsets = [(-2, -1), (-2, 1), (-1, -2), (-1, 2), (1, -2), (1, 2), (2, -1), (2, 1)];
+        if knight_offsets.iter().any(|&(dr, df)| is_attacker(target.offset(dr, df), &[PieceType::Knight])) {
+            return true;
+        }
+
+        let king_offsets = [(-1, -1), (-1, 0), (-1, 1), (0, -1), (0, 1), (1, -1), (1, 0), (1, 1)];
+        if king_offsets.iter().any(|&(dr, df)| is_attacker(target.offset(dr, df), &[PieceType::King])) {
+            return true;
+        }
+
+        let slides = [
+            ((-1, 0), PieceType::Rook), ((1, 0), PieceType::Rook), ((0, -1), PieceType::Rook), ((0, 1), PieceType::Rook),
+            ((-1, -1), PieceType::Bishop), ((-1, 1), PieceType::Bishop), ((1, -1), PieceType::Bishop), ((1, 1), PieceType::Bishop),
+        ];
+        for ((dr, df), slider) in slides {
+            let mut current = target;
+            while let Some(next) = current.offset(dr, df) {
+                current = next;
+                if let Some(piece) = self.get_piece(current) {
+                    if piece.color == attacker && (piece.piece_type == slider || piece.piece_type == PieceType::Queen) {
+                        return true;
                     }
+                    break;
                 }
             }
         }
-        
+
         false
     }
 
+    pub fn is_in_check(&self, color: PieceColor) -> bool {
+        match self.get_king_position(color) {
+            Some(king_position) => self.is_square_attacked(king_position, color.opposite()),
+            None => false,
+        }
+    }
+
     fn would_be_in_check_after_move(&self, chess_move: ChessMove, color: PieceColor) -> bool {
         // Create a temporary board to simulate the move
         let mut temp_board = self.clone();
@@ -436,10 +826,11 @@ impl Board {
         for rank in 0..8 {
             for file in 0..8 {
                 let pos = Position::new(rank, file);
-                if let Some(piece) = self.get_piece(pos) {
-                    if piece.piece_type == PieceType::King && piece.color == color {
-                        return Some(pos);
-                    }
+                if let Some(piece) = self.get_piece(pos)
+                    && piece.piece_type == PieceType::King
+                    && piece.color == color
+                {
+                    return Some(pos);
                 }
             }
         }
@@ -457,13 +848,11 @@ impl Board {
         for rank in 0..8 {
             for file in 0..8 {
                 let pos = Position::new(rank, file);
-                if let Some(piece) = self.get_piece(pos) {
-                    if piece.color == current_color {
-                        let moves = self.get_valid_moves(pos);
-                        if !moves.is_empty() {
-                            has_valid_moves = true;
-                            break;
-                        }
+                if let Some(piece) = self.get_piece(pos) && piece.color == current_color {
+                    let moves = self.get_valid_moves(pos);
+                    if !moves.is_empty() {
+                        has_valid_moves = true;
+                        break;
                     }
                 }
             }
@@ -489,11 +878,107 @@ impl Board {
 impl Clone for Board {
     fn clone(&self) -> Self {
         Self {
-            squares: self.squares.clone(),
+            squares: self.squares,
             turn: self.turn,
             move_count: self.move_count,
             captured_pieces: self.captured_pieces.clone(),
             en_passant_target: self.en_passant_target,
+            castling_rights: self.castling_rights,
+            halfmove_clock: self.halfmove_clock,
         }
     }
 }
+
+impl Default for Board {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Board {
+    /// The board as text, one rank per line with coordinates along the
+    /// edges. `from_black` puts Black at the bottom; `unicode` draws
+    /// figurines instead of FEN letters.
+    pub fn diagram(&self, from_black: bool, unicode: bool) -> String {
+        let ranks: Vec<usize> = if from_black { (0..8).collect() } else { (0..8).rev().collect() };
+        let files: Vec<usize> = if from_black { (0..8).rev().collect() } else { (0..8).collect() };
+
+        let mut out = String::new();
+        for &rank in &ranks {
+            out.push_str(&format!("{} ", rank + 1));
+            for &file in &files {
+                let square = match self.squares[rank][file] {
+                    Some(piece) if unicode => piece.figurine(),
+                    Some(piece) => piece.fen_char(),
+                    None if unicode => '·',
+                    None => '.',
+                };
+                out.push(' ');
+                out.push(square);
+            }
+            out.push('\n');
+        }
+        out.push_str("  ");
+        for &file in &files {
+            out.push(' ');
+            out.push((b'a' + file as u8) as char);
+        }
+        out
+    }
+}
+
+/// White at the bottom, in FEN letters; the alternate form (`{:#}`) uses
+/// Unicode figurines.
+impl fmt::Display for Board {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.diagram(false, f.alternate()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn board(fen: &str) -> Board {
+        Board::from_fen(fen).unwrap()
+    }
+
+    #[test]
+    fn knight_can_mate_with_opposing_pawn() {
+        let board = board("8/8/4k3/8/8/4p3/8/4K1N1 w - - 0 1");
+        assert!(board.has_mating_material(PieceColor::White));
+    }
+
+    #[test]
+    fn knight_cannot_mate_bare_king() {
+        let board = board("8/8/4k3/8/8/8/8/4K1N1 w - - 0 1");
+        assert!(!board.has_mating_material(PieceColor::White));
+        assert!(!board.has_mating_material(PieceColor::Black));
+    }
+
+    #[test]
+    fn same_colored_bishops_cannot_mate_bare_king() {
+        let board = board("8/8/4k3/8/8/4B3/8/2B1K3 w - - 0 1");
+        assert!(!board.has_mating_material(PieceColor::White));
+    }
+
+    #[test]
+    fn same_colored_bishops_need_a_blocker_of_another_kind() {
+        // An opposing bishop on the same color can never block a flight square that matters
+        assert!(!board("8/8/4k3/8/3b4/4B3/8/2B1K3 w - - 0 1").has_mating_material(PieceColor::White));
+        assert!(board("8/8/4k3/3b4/8/4B3/8/2B1K3 w - - 0 1").has_mating_material(PieceColor::White));
+        assert!(board("8/8/4k3/3n4/8/4B3/8/2B1K3 w - - 0 1").has_mating_material(PieceColor::White));
+    }
+
+    #[test]
+    fn bishops_of_both_colors_or_bishop_and_knight_can_mate() {
+        assert!(board("8/8/4k3/8/8/8/8/2B1KB2 w - - 0 1").has_mating_material(PieceColor::White));
+        assert!(board("8/8/4k3/8/8/8/8/2B1K1N1 w - - 0 1").has_mating_material(PieceColor::White));
+    }
+
+    #[test]
+    fn pawns_and_major_pieces_can_mate() {
+        assert!(board("8/8/4k3/8/8/8/4P3/4K3 w - - 0 1").has_mating_material(PieceColor::White));
+        assert!(board("8/8/4k3/8/8/8/8/R3K3 w - - 0 1").has_mating_material(PieceColor::White));
+    }
+}
@@ -0,0 +1,107 @@
+use super::{Board, ChessMove, GameState, PieceColor, Termination};
+
+/// A game record: the starting position plus every move played from it.
+#[derive(Clone)]
+pub struct Game {
+    start: Board,
+    board: Board,
+    moves: Vec<ChessMove>,
+    san_moves: Vec<String>,
+    // Board::position_key of every position so far, the current one last
+    position_keys: Vec<String>,
+}
+
+impl Game {
+    pub fn new() -> Self {
+        Self::from_board(Board::new())
+    }
+
+    pub fn from_board(board: Board) -> Self {
+        Self {
+            start: board.clone(),
+            position_keys: vec![board.position_key()],
+            board,
+            moves: Vec::new(),
+            san_moves: Vec::new(),
+        }
+    }
+
+    /// The current position.
+    pub fn board(&self) -> &Board {
+        &self.board
+    }
+
+    pub fn start_board(&self) -> &Board {
+        &self.start
+    }
+
+    pub fn moves(&self) -> &[ChessMove] {
+        &self.moves
+    }
+
+    pub fn san_moves(&self) -> &[String] {
+        &self.san_moves
+    }
+
+    pub fn make_move(&mut self, mv: ChessMove) {
+        self.san_moves.push(self.board.move_to_san(mv));
+        self.moves.push(mv);
+        self.board.make_move(mv);
+        self.position_keys.push(self.board.position_key());
+    }
+
+    /// Takes back the last move, if there is one.
+    pub fn undo(&mut self) -> Option<ChessMove> {
+        let mv = self.moves.pop()?;
+        self.san_moves.pop();
+        self.position_keys.pop();
+        self.board = self.board_at(self.moves.len());
+        Some(mv)
+    }
+
+    /// How often the current position has occurred, this time included.
+    pub fn repetitions(&self) -> usize {
+        let current = self.position_keys.last();
+        self.position_keys.iter().filter(|key| Some(*key) == current).count()
+    }
+
+    /// The draw either player may claim in the current position:
+    /// threefold repetition, or 50 moves by each side without a capture or
+    /// pawn move.
+    pub fn claimable_draw(&self) -> Option<Termination> {
+        if self.repetitions() >= 3 {
+            Some(Termination::ThreefoldRepetition)
+        } else if self.board.halfmove_clock() >= 100 {
+            Some(Termination::FiftyMoveRule)
+        } else {
+            None
+        }
+    }
+
+    /// The position after `ply` half-moves (0 is the starting position).
+    pub fn board_at(&self, ply: usize) -> Board {
+        let mut board = self.start.clone();
+        for mv in self.moves.iter().take(ply) {
+            board.make_move(*mv);
+        }
+        board
+    }
+
+    /// PGN result string for the current position.
+    pub fn result(&self) -> &'static str {
+        match self.board.check_game_state() {
+            GameState::Checkmate => match self.board.current_turn() {
+                PieceColor::White => "0-1",
+                PieceColor::Black => "1-0",
+            },
+            GameState::Stalemate => "1/2-1/2",
+            _ => "*",
+        }
+    }
+}
+
+impl Default for Game {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -1,12 +1,22 @@
 mod board;
 mod piece;
 mod position;
+mod game;
 mod game_state;
+mod outcome;
+mod pgn;
+mod san;
+mod validation;
 
-pub use board::Board;
+pub use board::{Board, CastlingRights, START_FEN};
 pub use piece::{Piece, PieceColor, PieceType};
 pub use position::Position;
+pub use game::Game;
 pub use game_state::GameState;
+pub use outcome::{Outcome, Termination};
+pub use pgn::{read_pgn, write_pgn, MoveAnnotation};
+pub use san::format_numbered_line;
+pub use validation::PositionError;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct ChessMove {
@@ -52,4 +62,17 @@ impl ChessMove {
             None => Self::new(from, to),
         })
     }
+
+    /// UCI long algebraic form, e.g. "e2e4", "e7e8q", or "e1g1" for
+    /// White castling kingside.
+    pub fn to_uci(self) -> String {
+        let promotion = match self.promotion {
+            Some(PieceType::Queen) => "q",
+            Some(PieceType::Rook) => "r",
+            Some(PieceType::Bishop) => "b",
+            Some(PieceType::Knight) => "n",
+            _ => "",
+        };
+        format!("{}{}{}", self.from, self.to, promotion)
+    }
 }
@@ -0,0 +1,74 @@
+use std::fmt;
+
+use super::PieceColor;
+
+/// Why a game ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Termination {
+    Checkmate,
+    Stalemate,
+    /// A flag fell while the opponent could still mate
+    Timeout,
+    /// A flag fell but the opponent had no way to mate, so it is a draw
+    TimeoutVsInsufficientMaterial,
+    /// The loser gave up
+    Resignation,
+    DrawAgreement,
+    /// Drawn on a claim
+    ThreefoldRepetition,
+    FiftyMoveRule,
+}
+
+impl Termination {
+    /// Value for the PGN `Termination` tag.
+    pub fn pgn_tag(self) -> &'static str {
+        match self {
+            Termination::Timeout | Termination::TimeoutVsInsufficientMaterial => "time forfeit",
+            _ => "normal",
+        }
+    }
+}
+
+/// The result of a finished game and how it came about.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Outcome {
+    /// `None` for a draw
+    pub winner: Option<PieceColor>,
+    pub termination: Termination,
+}
+
+impl Outcome {
+    /// PGN result string.
+    pub fn result(&self) -> &'static str {
+        match self.winner {
+            Some(PieceColor::White) => "1-0",
+            Some(PieceColor::Black) => "0-1",
+            None => "1/2-1/2",
+        }
+    }
+}
+
+impl fmt::Display for Outcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = |color: Option<PieceColor>| match color {
+            Some(PieceColor::White) => "White",
+            Some(PieceColor::Black) => "Black",
+            None => "",
+        };
+        let winner = name(self.winner);
+        match self.termination {
+            Termination::Checkmate => write!(f, "Checkmate, {} wins", winner),
+            Termination::Stalemate => write!(f, "Stalemate, draw"),
+            Termination::Timeout => write!(f, "{} wins on time", winner),
+            Termination::TimeoutVsInsufficientMaterial => {
+                write!(f, "Draw: time ran out, but the opponent cannot mate")
+            }
+            Termination::Resignation => {
+                write!(f, "{} resigns, {} wins", name(self.winner.map(|w| w.opposite())), winner)
+            }
+            Termination::DrawAgreement => write!(f, "Draw by agreement"),
+            Termination::ThreefoldRepetition => write!(f, "Draw by threefold repetition"),
+            Termination::FiftyMoveRule => write!(f, "Draw by the 50-move rule"),
+        }
+    }
+}
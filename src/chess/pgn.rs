@@ -0,0 +1,233 @@
+use super::{Board, Game, PieceColor};
+
+/// Extra text attached to a single move when exporting PGN.
+#[derive(Debug, Clone, Default)]
+pub struct MoveAnnotation {
+    /// Numeric annotation glyph, e.g. 2 for "?" or 4 for "??"
+    pub nag: Option<u8>,
+    pub comment: Option<String>,
+}
+
+const MAX_LINE_LENGTH: usize = 79;
+
+/// Writes `game` as PGN. `tags` are emitted after the Seven Tag Roster
+/// defaults they don't override; `annotations` is indexed by ply. A
+/// `Result` tag overrides the result read off the board, e.g. for games
+/// decided on time or by resignation.
+pub fn write_pgn(game: &Game, tags: &[(&str, String)], annotations: &[MoveAnnotation]) -> String {
+    let mut roster: Vec<(&str, String)> = vec![
+        ("Event", "Casual game".to_string()),
+        ("Site", "Chess.rs".to_string()),
+        ("Date", "????.??.??".to_string()),
+        ("Round", "-".to_string()),
+        ("White", "?".to_string()),
+        ("Black", "?".to_string()),
+        ("Result", game.result().to_string()),
+    ];
+    for (name, value) in tags {
+        match roster.iter_mut().find(|(n, _)| n == name) {
+            Some(existing) => existing.1 = value.clone(),
+            None => roster.push((name, value.clone())),
+        }
+    }
+
+    let result = roster.iter().find(|(name, _)| *name == "Result").map(|(_, value)| value.clone()).unwrap_or_default();
+
+    let start_fen = game.start_board().to_fen();
+    if start_fen != Board::new().to_fen() {
+        roster.push(("SetUp", "1".to_string()));
+        roster.push(("FEN", start_fen));
+    }
+
+    let mut pgn = String::new();
+    for (name, value) in &roster {
+        pgn.push_str(&format!("[{} \"{}\"]\n", name, value.replace('\\', "\\\\").replace('"', "\\\"")));
+    }
+    pgn.push('\n');
+
+    let start = game.start_board();
+    let mut number = start.fullmove_number();
+    let mut white_to_move = start.current_turn() == PieceColor::White;
+    let mut tokens = Vec::new();
+    // Black's move needs its own number at the start or after a comment
+    let mut needs_number = true;
+
+    for (ply, san) in game.san_moves().iter().enumerate() {
+        if white_to_move {
+            tokens.push(format!("{}.", number));
+        } else if needs_number {
+            tokens.push(format!("{}...", number));
+        }
+        tokens.push(san.clone());
+        needs_number = false;
+
+        if let Some(annotation) = annotations.get(ply) {
+            if let Some(nag) = annotation.nag {
+                tokens.push(format!("${}", nag));
+            }
+            if let Some(comment) = &annotation.comment {
+                tokens.push(format!("{{ {} }}", comment.replace('}', ")")));
+                needs_number = true;
+            }
+        }
+
+        if !white_to_move {
+            number += 1;
+        }
+        white_to_move = !white_to_move;
+    }
+    tokens.push(result);
+
+    let mut line_length = 0;
+    for token in tokens {
+        if line_length > 0 && line_length + 1 + token.len() > MAX_LINE_LENGTH {
+            pgn.push('\n');
+            line_length = 0;
+        } else if line_length > 0 {
+            pgn.push(' ');
+            line_length += 1;
+        }
+        line_length += token.len();
+        pgn.push_str(&token);
+    }
+    pgn.push('\n');
+
+    pgn
+}
+
+/// Reads every game in a PGN file: tag pairs plus the main line.
+/// Comments, variations, NAGs and move numbers are skipped.
+pub fn read_pgn(text: &str) -> Result<Vec<Game>, String> {
+    let mut games = Vec::new();
+    let mut fen: Option<String> = None;
+    let mut movetext = String::new();
+
+    let mut finish = |fen: &mut Option<String>, movetext: &mut String| -> Result<(), String> {
+        if movetext.trim().is_empty() && fen.is_none() {
+            return Ok(());
+        }
+        let board = match fen.take() {
+            Some(fen) => Board::from_fen(&fen)?,
+            None => Board::new(),
+        };
+        games.push(read_movetext(board, movetext)?);
+        movetext.clear();
+        Ok(())
+    };
+
+    for line in text.lines() {
+        let line = line.trim();
+        if let Some(tag) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            // Tags after movetext start the next game
+            if !movetext.trim().is_empty() {
+                finish(&mut fen, &mut movetext)?;
+            }
+            if let Some((name, value)) = tag.split_once(' ') && name == "FEN" {
+                fen = Some(value.trim().trim_matches('"').to_string());
+            }
+        } else {
+            movetext.push_str(line);
+            movetext.push('\n');
+        }
+    }
+    finish(&mut fen, &mut movetext)?;
+
+    Ok(games)
+}
+
+fn read_movetext(board: Board, movetext: &str) -> Result<Game, String> {
+    let mut game = Game::from_board(board);
+    let mut chars = movetext.chars().peekable();
+    let mut depth = 0;
+
+    while let Some(c) = chars.next() {
+        match c {
+            '{' => {
+                for c in chars.by_ref() {
+                    if c == '}' {
+                        break;
+                    }
+                }
+            }
+            ';' => {
+                for c in chars.by_ref() {
+                    if c == '\n' {
+                        break;
+                    }
+                }
+            }
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            c if c.is_whitespace() => {}
+            c => {
+                let mut token = String::from(c);
+                while let Some(&next) = chars.peek() {
+                    if next.is_whitespace() || "{}();".contains(next) {
+                        break;
+                    }
+                    token.push(next);
+                    chars.next();
+                }
+                if depth > 0 || token.starts_with('$') || matches!(token.as_str(), "1-0" | "0-1" | "1/2-1/2" | "*") {
+                    continue;
+                }
+                // "12." or "12..." possibly glued to the move: "12.e4". Castling
+                // written with zeros also starts with a digit.
+                let san = if token.starts_with("0-0") {
+                    token.as_str()
+                } else {
+                    token.trim_start_matches(|c: char| c.is_ascii_digit() || c == '.')
+                };
+                if san.is_empty() {
+                    continue;
+                }
+                let mv = game
+                    .board()
+                    .find_san_move(san)
+                    .ok_or_else(|| format!("illegal move '{}' after {} plies", san, game.moves().len()))?;
+                game.make_move(mv);
+            }
+        }
+    }
+
+    Ok(game)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sans(game: &Game) -> Vec<String> {
+        game.start_board().uci_line_to_san(&game.moves().iter().map(|mv| mv.to_uci()).collect::<Vec<_>>())
+    }
+
+    #[test]
+    fn move_numbers_may_be_glued_to_moves() {
+        let games = read_pgn("1.e4 e5 2.Nf3 Nc6 12.Bb5 12...a6 *").unwrap();
+        assert_eq!(sans(&games[0]), ["e4", "e5", "Nf3", "Nc6", "Bb5", "a6"]);
+    }
+
+    #[test]
+    fn comments_variations_and_nags_are_skipped() {
+        let text = "[Event \"Test\"]\n\n1. e4 {best by test} e5 (1... c5 2. Nf3) 2. Nf3 $1 ; a comment\nNc6 1-0\n";
+        let games = read_pgn(text).unwrap();
+        assert_eq!(sans(&games[0]), ["e4", "e5", "Nf3", "Nc6"]);
+    }
+
+    #[test]
+    fn castling_with_zeros_and_several_games() {
+        let text = "[FEN \"4k3/8/8/8/8/8/8/4K2R w K - 0 1\"]\n\n1. 0-0 Kd7 *\n\n[Event \"Second\"]\n\n1. d4 *\n";
+        let games = read_pgn(text).unwrap();
+        assert_eq!(games.len(), 2);
+        assert_eq!(sans(&games[0]), ["O-O", "Kd7"]);
+        assert_eq!(sans(&games[1]), ["d4"]);
+    }
+
+    #[test]
+    fn illegal_moves_are_reported() {
+        let Err(err) = read_pgn("1. e4 e5 2. Ke3 *") else {
+            panic!("read an illegal move");
+        };
+        assert_eq!(err, "illegal move 'Ke3' after 2 plies");
+    }
+}
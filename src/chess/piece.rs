@@ -43,7 +43,7 @@ impl Piece {
         }
     }
 
-    pub fn fen_char(&self) -> char {
+    pub fn fen_char(self) -> char {
         match (self.piece_type, self.color) {
             (PieceType::King, PieceColor::White) => 'K',
             (PieceType::Queen, PieceColor::White) => 'Q',
@@ -60,7 +60,40 @@ impl Piece {
         }
     }
 
-    pub fn to_char(&self) -> char {
+    /// Inverse of [`Piece::fen_char`].
+    pub fn from_fen_char(c: char) -> Option<Self> {
+        let piece_type = match c.to_ascii_lowercase() {
+            'p' => PieceType::Pawn,
+            'n' => PieceType::Knight,
+            'b' => PieceType::Bishop,
+            'r' => PieceType::Rook,
+            'q' => PieceType::Queen,
+            'k' => PieceType::King,
+            _ => return None,
+        };
+        let color = if c.is_ascii_uppercase() { PieceColor::White } else { PieceColor::Black };
+        Some(Self::new(piece_type, color))
+    }
+
+    /// Unicode chess symbol, outlined for White and filled for Black.
+    pub fn figurine(self) -> char {
+        match (self.piece_type, self.color) {
+            (PieceType::King, PieceColor::White) => '♔',
+            (PieceType::Queen, PieceColor::White) => '♕',
+            (PieceType::Rook, PieceColor::White) => '♖',
+            (PieceType::Bishop, PieceColor::White) => '♗',
+            (PieceType::Knight, PieceColor::White) => '♘',
+            (PieceType::Pawn, PieceColor::White) => '♙',
+            (PieceType::King, PieceColor::Black) => '♚',
+            (PieceType::Queen, PieceColor::Black) => '♛',
+            (PieceType::Rook, PieceColor::Black) => '♜',
+            (PieceType::Bishop, PieceColor::Black) => '♝',
+            (PieceType::Knight, PieceColor::Black) => '♞',
+            (PieceType::Pawn, PieceColor::Black) => '♟',
+        }
+    }
+
+    pub fn to_char(self) -> char {
         let c = match self.piece_type {
             PieceType::Pawn => 'p',
             PieceType::Knight => 'n',
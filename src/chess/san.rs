@@ -0,0 +1,344 @@
+use super::{Board, ChessMove, GameState, PieceType, Position};
+
+fn piece_letter(piece_type: PieceType) -> &'static str {
+    match piece_type {
+        PieceType::Pawn => "",
+        PieceType::Knight => "N",
+        PieceType::Bishop => "B",
+        PieceType::Rook => "R",
+        PieceType::Queen => "Q",
+        PieceType::King => "K",
+    }
+}
+
+impl Board {
+    /// Every legal move for the side to move.
+    pub fn legal_moves(&self) -> Vec<ChessMove> {
+        let mut moves = Vec::new();
+        for rank in 0..8 {
+            for file in 0..8 {
+                moves.extend(self.get_valid_moves(Position::new(rank, file)));
+            }
+        }
+        moves
+    }
+
+    /// Standard algebraic notation for a legal move in this position.
+    pub fn move_to_san(&self, mv: ChessMove) -> String {
+        let Some(piece) = self.get_piece(mv.from) else {
+            return String::new();
+        };
+
+        let mut san = String::new();
+        let file_distance = mv.from.file.abs_diff(mv.to.file);
+
+        if piece.piece_type == PieceType::King && file_distance == 2 {
+            san.push_str(if mv.to.file > mv.from.file { "O-O" } else { "O-O-O" });
+        } else {
+            let is_capture = self.get_piece(mv.to).is_some()
+                || (piece.piece_type == PieceType::Pawn && file_distance == 1);
+
+            san.push_str(piece_letter(piece.piece_type));
+
+            if piece.piece_type == PieceType::Pawn {
+                if is_capture {
+                    san.push((b'a' + mv.from.file as u8) as char);
+                }
+            } else {
+                // Disambiguate between identical pieces that can reach the same square
+                let rivals: Vec<Position> = self
+                    .legal_moves()
+                    .into_iter()
+                    .filter(|m| m.to == mv.to && m.from != mv.from)
+                    .filter(|m| self.get_piece(m.from).map(|p| p.piece_type) == Some(piece.piece_type))
+                    .map(|m| m.from)
+                    .collect();
+
+                if !rivals.is_empty() {
+                    let square = mv.from.to_algebraic();
+                    if rivals.iter().all(|r| r.file != mv.from.file) {
+                        san.push_str(&square[0..1]);
+                    } else if rivals.iter().all(|r| r.rank != mv.from.rank) {
+                        san.push_str(&square[1..2]);
+                    } else {
+                        san.push_str(&square);
+                    }
+                }
+            }
+
+            if is_capture {
+                san.push('x');
+            }
+            san.push_str(&mv.to.to_algebraic());
+
+            if let Some(promotion) = mv.promotion {
+                san.push('=');
+                san.push_str(piece_letter(promotion));
+            }
+        }
+
+        let mut after = self.clone();
+        after.make_move(mv);
+        match after.check_game_state() {
+            GameState::Checkmate => san.push('#'),
+            GameState::Check => san.push('+'),
+            _ => {}
+        }
+
+        san
+    }
+
+    /// Converts a line of UCI moves (e.g. an engine PV) to SAN, stopping at
+    /// the first move that isn't legal in the resulting position.
+    pub fn uci_line_to_san(&self, line: &[String]) -> Vec<String> {
+        let mut board = self.clone();
+        let mut sans = Vec::new();
+        for uci in line {
+            let Some(mv) = board.find_legal_move(uci) else {
+                break;
+            };
+            sans.push(board.move_to_san(mv));
+            board.make_move(mv);
+        }
+        sans
+    }
+
+    /// Looks up the legal move written in SAN, e.g. "Nf3", "exd5", "e8=Q+"
+    /// or "O-O". Check marks and annotations like "!?" are optional.
+    pub fn find_san_move(&self, san: &str) -> Option<ChessMove> {
+        let normalize = |text: &str| {
+            text.trim_end_matches(['+', '#', '!', '?'])
+                .replace('0', "O")
+                .to_string()
+        };
+        let wanted = normalize(san.trim());
+        if wanted.is_empty() {
+            return None;
+        }
+        self.legal_moves()
+            .into_iter()
+            .find(|&mv| normalize(&self.move_to_san(mv)) == wanted)
+    }
+
+    /// Reads a move the way a person might type it: SAN ("Nf3", "exd5",
+    /// "e8=Q"), UCI ("g1f3") or coordinates ("g1-f3", "e7-e8=Q"). SAN is
+    /// read leniently, so "Nxf3" finds Nf3 and "ed5" finds exd5. The error
+    /// says what is wrong, e.g. which moves an ambiguous entry could be.
+    pub fn read_move(&self, text: &str) -> Result<ChessMove, String> {
+        let text = text.trim();
+        if text.is_empty() {
+            return Err("no move entered".to_string());
+        }
+        if let Some(mv) = self.find_san_move(text) {
+            return Ok(mv);
+        }
+
+        let coordinates: String = text
+            .chars()
+            .filter(|c| !matches!(c, '-' | 'x' | ' ' | '=' | '+' | '#'))
+            .map(|c| c.to_ascii_lowercase())
+            .collect();
+        if coordinates.is_ascii() && let Some(parsed) = ChessMove::from_uci(&coordinates) {
+            if let Some(mv) = self.find_legal_move(&coordinates) {
+                return Ok(mv);
+            }
+            let promotions = self.get_valid_moves(parsed.from).into_iter().any(|m| m.to == parsed.to && m.promotion.is_some());
+            return Err(if promotions && parsed.promotion.is_none() {
+                format!("{} needs a promotion piece, e.g. {}q", text, coordinates)
+            } else {
+                format!("{} is not a legal move here", text)
+            });
+        }
+
+        if matches!(text.trim_end_matches(['+', '#']).replace('0', "O").as_str(), "O-O" | "O-O-O") {
+            return Err(format!("{} is not a legal move here", text));
+        }
+        let candidates = self.san_candidates(text).ok_or_else(|| format!("'{}' is not a move", text))?;
+        match candidates.as_slice() {
+            [mv] => Ok(*mv),
+            [] => Err(format!("{} is not a legal move here", text)),
+            moves if moves.iter().all(|m| m.from == moves[0].from && m.to == moves[0].to) => {
+                Err(format!("{} needs a promotion piece, e.g. {}=Q", text, moves[0].to))
+            }
+            moves => {
+                let sans: Vec<String> = moves.iter().map(|&m| self.move_to_san(m)).collect();
+                Err(format!("{} is ambiguous: {}", text, sans.join(" or ")))
+            }
+        }
+    }
+
+    /// Legal moves fitting a loosely written SAN move: the piece, the
+    /// destination, any promotion, and whatever file or rank is given for
+    /// the origin. `None` if the text doesn't look like SAN at all.
+    fn san_candidates(&self, san: &str) -> Option<Vec<ChessMove>> {
+        let san = san.trim_end_matches(['+', '#', '!', '?']);
+        let (piece_type, rest) = match san.chars().next()? {
+            'N' => (PieceType::Knight, &san[1..]),
+            'B' => (PieceType::Bishop, &san[1..]),
+            'R' => (PieceType::Rook, &san[1..]),
+            'Q' => (PieceType::Queen, &san[1..]),
+            'K' => (PieceType::King, &san[1..]),
+            _ => (PieceType::Pawn, san),
+        };
+        let (rest, promotion) = match rest.char_indices().last()? {
+            (i, letter @ ('N' | 'B' | 'R' | 'Q' | 'n' | 'b' | 'r' | 'q')) if i >= 2 => {
+                let promotion = match letter.to_ascii_uppercase() {
+                    'N' => PieceType::Knight,
+                    'B' => PieceType::Bishop,
+                    'R' => PieceType::Rook,
+                    _ => PieceType::Queen,
+                };
+                (rest[..i].trim_end_matches('='), Some(promotion))
+            }
+            _ => (rest, None),
+        };
+        if !rest.is_ascii() || rest.len() < 2 {
+            return None;
+        }
+        let to = Position::from_uci(&rest[rest.len() - 2..])?;
+        let origin: Vec<char> = rest[..rest.len() - 2].chars().filter(|&c| c != 'x').collect();
+        if !origin.iter().all(|c| matches!(c, 'a'..='h' | '1'..='8')) {
+            return None;
+        }
+
+        Some(
+            self.legal_moves()
+                .into_iter()
+                .filter(|m| m.to == to && self.get_piece(m.from).map(|p| p.piece_type) == Some(piece_type))
+                .filter(|m| promotion.is_none() || m.promotion == promotion)
+                .filter(|m| {
+                    let from = m.from.to_algebraic();
+                    origin.iter().all(|&c| from.contains(c))
+                })
+                .collect(),
+        )
+    }
+
+    /// Legal moves whose SAN or UCI starts with `prefix`, written the same
+    /// way as the prefix, for suggestions while a move is being typed.
+    pub fn complete_move(&self, prefix: &str) -> Vec<String> {
+        let prefix = prefix.trim();
+        let mut completions: Vec<String> = self
+            .legal_moves()
+            .into_iter()
+            .filter_map(|mv| {
+                let san = self.move_to_san(mv);
+                let uci = mv.to_uci();
+                if san.starts_with(prefix) {
+                    Some(san)
+                } else if uci.starts_with(prefix) {
+                    Some(uci)
+                } else {
+                    None
+                }
+            })
+            .collect();
+        completions.sort();
+        completions.dedup();
+        completions
+    }
+
+    /// Looks up the legal move matching a UCI string.
+    pub fn find_legal_move(&self, uci: &str) -> Option<ChessMove> {
+        let parsed = ChessMove::from_uci(uci)?;
+        self.get_valid_moves(parsed.from)
+            .into_iter()
+            .find(|m| m.to == parsed.to && m.promotion == parsed.promotion)
+    }
+}
+
+/// Joins SAN moves with move numbers, e.g. "12. Nf3 Nc6 13. Bb5" or
+/// "12... Nc6 13. Bb5" when the line starts with Black.
+pub fn format_numbered_line(sans: &[String], fullmove: usize, white_to_move: bool) -> String {
+    let mut out = String::new();
+    let mut number = fullmove;
+    let mut white = white_to_move;
+
+    for (i, san) in sans.iter().enumerate() {
+        if white {
+            out.push_str(&format!("{}. ", number));
+        } else if i == 0 {
+            out.push_str(&format!("{}... ", number));
+        }
+        out.push_str(san);
+        out.push(' ');
+        if !white {
+            number += 1;
+        }
+        white = !white;
+    }
+
+    out.trim_end().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn board(fen: &str) -> Board {
+        Board::from_fen(fen).unwrap()
+    }
+
+    fn mv(uci: &str) -> ChessMove {
+        ChessMove::from_uci(uci).unwrap()
+    }
+
+    // Knights on b1 and f3 can both go to d2
+    const TWO_KNIGHTS: &str = "4k3/8/8/8/8/5N2/8/1N2K3 w - - 0 1";
+    // The a-pawn is about to promote
+    const PROMOTION: &str = "4k3/P7/8/8/8/8/8/4K3 w - - 0 1";
+
+    #[test]
+    fn read_move_accepts_san_uci_and_coordinates() {
+        let board = Board::new();
+        for text in ["Nf3", "g1f3", "g1-f3", "Ng1f3", "Nxf3"] {
+            assert_eq!(board.read_move(text), Ok(mv("g1f3")), "{}", text);
+        }
+        assert!(board.read_move("Nf4").unwrap_err().contains("not a legal move"));
+        assert!(board.read_move("hello").unwrap_err().contains("not a move"));
+        assert_eq!(board.read_move("  "), Err("no move entered".to_string()));
+    }
+
+    #[test]
+    fn ambiguous_entries_list_the_moves_they_could_be() {
+        let board = board(TWO_KNIGHTS);
+        assert_eq!(board.san_candidates("Nd2").map(|moves| moves.len()), Some(2));
+        let err = board.read_move("Nd2").unwrap_err();
+        assert!(err.contains("ambiguous") && err.contains("Nbd2") && err.contains("Nfd2"), "{}", err);
+        assert_eq!(board.read_move("Nbd2"), Ok(mv("b1d2")));
+        assert_eq!(board.read_move("Nfd2"), Ok(mv("f3d2")));
+        assert_eq!(board.find_san_move("Nd2"), None);
+    }
+
+    #[test]
+    fn promotions_need_a_piece() {
+        let board = board(PROMOTION);
+        assert_eq!(board.san_candidates("a8").map(|moves| moves.len()), Some(4));
+        assert!(board.read_move("a8").unwrap_err().contains("needs a promotion piece, e.g. a8=Q"));
+        assert!(board.read_move("a7a8").unwrap_err().contains("needs a promotion piece, e.g. a7a8q"));
+        assert_eq!(board.read_move("a8=Q"), Ok(mv("a7a8q")));
+        assert_eq!(board.read_move("a8N"), Ok(mv("a7a8n")));
+        assert_eq!(board.read_move("a7-a8=R"), Ok(mv("a7a8r")));
+        // SAN gives check here, but the mark is optional
+        assert_eq!(board.find_san_move("a8=Q"), Some(mv("a7a8q")));
+    }
+
+    #[test]
+    fn castling_may_be_written_with_zeros() {
+        let board = board("4k3/8/8/8/8/8/8/4K2R w K - 0 1");
+        assert_eq!(board.find_san_move("O-O"), Some(mv("e1g1")));
+        assert_eq!(board.read_move("0-0"), Ok(mv("e1g1")));
+        assert_eq!(board.read_move("e1g1"), Ok(mv("e1g1")));
+        assert!(board.read_move("0-0-0").unwrap_err().contains("not a legal move"));
+    }
+
+    #[test]
+    fn complete_move_matches_san_or_uci_prefixes() {
+        let board = board(TWO_KNIGHTS);
+        let completions = board.complete_move("Nb");
+        assert!(completions.contains(&"Nbd2".to_string()));
+        assert!(!completions.contains(&"Nfd2".to_string()));
+        assert_eq!(board.complete_move("b1"), ["b1a3", "b1c3", "b1d2"]);
+        assert!(board.complete_move("Qa").is_empty());
+    }
+}
@@ -0,0 +1,273 @@
+use std::fmt;
+
+use super::{Board, PieceColor, PieceType, Position};
+
+/// Something that makes a position impossible to reach in a game.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PositionError {
+    MissingKing(PieceColor),
+    /// More than one king, with how many there are
+    TooManyKings(PieceColor, usize),
+    PawnOnBackRank(Position),
+    TooManyPawns(PieceColor, usize),
+    TooManyPieces(PieceColor, usize),
+    /// More queens, rooks, bishops of one square color or knights than the
+    /// starting set plus promotions of the missing pawns could give
+    TooManyPromotions(PieceColor),
+    /// A castling right without the king and that rook on their starting squares
+    ImpossibleCastling { color: PieceColor, kingside: bool },
+    /// An en passant square no pawn can just have skipped over
+    ImpossibleEnPassant(Position),
+    /// The side that just moved is in check
+    OpponentInCheck(PieceColor),
+}
+
+fn side_name(color: PieceColor) -> &'static str {
+    match color {
+        PieceColor::White => "White",
+        PieceColor::Black => "Black",
+    }
+}
+
+impl fmt::Display for PositionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            PositionError::MissingKing(color) => write!(f, "{} has no king", side_name(color)),
+            PositionError::TooManyKings(color, count) => write!(f, "{} has {} kings", side_name(color), count),
+            PositionError::PawnOnBackRank(square) => write!(f, "Pawn on {} can't stand on the first or last rank", square),
+            PositionError::TooManyPawns(color, count) => {
+                write!(f, "{} has {} pawns, at most 8 are possible", side_name(color), count)
+            }
+            PositionError::TooManyPieces(color, count) => {
+                write!(f, "{} has {} pieces, at most 16 are possible", side_name(color), count)
+            }
+            PositionError::TooManyPromotions(color) => {
+                write!(f, "{} has more promoted pieces than missing pawns", side_name(color))
+            }
+            PositionError::ImpossibleCastling { color, kingside } => write!(
+                f,
+                "{} can't castle {} without the king and rook on their starting squares",
+                side_name(color),
+                if kingside { "kingside" } else { "queenside" }
+            ),
+            PositionError::ImpossibleEnPassant(square) => {
+                write!(f, "En passant square {} doesn't follow a double pawn step", square)
+            }
+            PositionError::OpponentInCheck(color) => {
+                write!(f, "{} is in check with {} to move", side_name(color), side_name(color.opposite()))
+            }
+        }
+    }
+}
+
+impl Board {
+    /// Checks that the position could come up in a game: one king each,
+    /// no pawns on the back ranks, no more pieces than promotions allow,
+    /// castling and en passant rights the pieces agree with, and the side
+    /// that just moved not left in check. Lists every problem found.
+    pub fn validate(&self) -> Result<(), Vec<PositionError>> {
+        let mut errors = Vec::new();
+
+        for color in [PieceColor::White, PieceColor::Black] {
+            self.validate_material(color, &mut errors);
+        }
+        for rank in [0, 7] {
+            for file in 0..8 {
+                let square = Position::new(rank, file);
+                if self.get_piece(square).is_some_and(|piece| piece.piece_type == PieceType::Pawn) {
+                    errors.push(PositionError::PawnOnBackRank(square));
+                }
+            }
+        }
+
+        let rights = self.castling_rights();
+        for color in [PieceColor::White, PieceColor::Black] {
+            let home_rank = if color == PieceColor::White { 0 } else { 7 };
+            let on_square = |file: usize, piece_type: PieceType| {
+                self.get_piece(Position::new(home_rank, file))
+                    .is_some_and(|piece| piece.piece_type == piece_type && piece.color == color)
+            };
+            for (kingside, rook_file) in [(true, 7), (false, 0)] {
+                let right = if kingside { rights.kingside(color) } else { rights.queenside(color) };
+                if right && !(on_square(4, PieceType::King) && on_square(rook_file, PieceType::Rook)) {
+                    errors.push(PositionError::ImpossibleCastling { color, kingside });
+                }
+            }
+        }
+
+        if let Some(target) = self.en_passant_target() && !self.en_passant_plausible(target) {
+            errors.push(PositionError::ImpossibleEnPassant(target));
+        }
+
+        let waiting = self.current_turn().opposite();
+        if self.get_king_position(waiting).is_some() && self.is_in_check(waiting) {
+            errors.push(PositionError::OpponentInCheck(waiting));
+        }
+
+        if errors.is_empty() { Ok(()) } else { Err(errors) }
+    }
+
+    fn validate_material(&self, color: PieceColor, errors: &mut Vec<PositionError>) {
+        let mut counts = [0usize; 6];
+        // Bishops on light and on dark squares, which can't change color
+        let mut bishops = [0usize; 2];
+        for rank in 0..8 {
+            for file in 0..8 {
+                let Some(piece) = self.get_piece(Position::new(rank, file)).filter(|piece| piece.color == color) else {
+                    continue;
+                };
+                counts[piece.piece_type as usize] += 1;
+                if piece.piece_type == PieceType::Bishop {
+                    bishops[(rank + file) % 2] += 1;
+                }
+            }
+        }
+        let count = |piece_type: PieceType| counts[piece_type as usize];
+
+        match count(PieceType::King) {
+            0 => errors.push(PositionError::MissingKing(color)),
+            1 => {}
+            kings => errors.push(PositionError::TooManyKings(color, kings)),
+        }
+
+        // Each piece beyond the starting set is a promoted pawn
+        let promoted = count(PieceType::Queen).saturating_sub(1)
+            + count(PieceType::Rook).saturating_sub(2)
+            + count(PieceType::Knight).saturating_sub(2)
+            + bishops[0].saturating_sub(1)
+            + bishops[1].saturating_sub(1);
+        let pawns = count(PieceType::Pawn);
+        let total: usize = counts.iter().sum();
+        if pawns > 8 {
+            errors.push(PositionError::TooManyPawns(color, pawns));
+        } else if total > 16 {
+            errors.push(PositionError::TooManyPieces(color, total));
+        } else if pawns + promoted > 8 {
+            errors.push(PositionError::TooManyPromotions(color));
+        }
+    }
+
+    /// Whether a pawn of the side that just moved can have double-stepped
+    /// over `target`: it stands in front of the square, and the square
+    /// and the one it started from are empty.
+    fn en_passant_plausible(&self, target: Position) -> bool {
+        let mover = self.current_turn().opposite();
+        let (target_rank, forward) = match mover {
+            PieceColor::White => (2, 1),
+            PieceColor::Black => (5, -1),
+        };
+        let pawn = target.offset(forward, 0).and_then(|square| self.get_piece(square));
+        target.rank == target_rank
+            && self.get_piece(target).is_none()
+            && target.offset(-forward, 0).is_some_and(|start| self.get_piece(start).is_none())
+            && pawn.is_some_and(|pawn| pawn.piece_type == PieceType::Pawn && pawn.color == mover)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chess::START_FEN;
+
+    fn errors(fen: &str) -> Vec<PositionError> {
+        Board::parse_fen(fen).unwrap().validate().err().unwrap_or_default()
+    }
+
+    #[test]
+    fn legal_positions_validate() {
+        for fen in [
+            START_FEN,
+            "r1bqkbnr/1ppp1ppp/p1n5/1B2p3/4P3/5N2/PPPP1PPP/RNBQK2R w KQkq - 0 4",
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3",
+            "6k1/5ppp/8/8/8/8/1Q3PPP/QQ4K1 b - - 0 50",
+        ] {
+            assert_eq!(errors(fen), [], "{}", fen);
+            assert!(Board::from_fen(fen).is_ok());
+        }
+    }
+
+    #[test]
+    fn kings() {
+        assert_eq!(errors("8/8/8/8/8/8/8/4K3 w - - 0 1"), [PositionError::MissingKing(PieceColor::Black)]);
+        assert_eq!(errors("4k3/8/8/8/8/8/8/K3K3 w - - 0 1"), [PositionError::TooManyKings(PieceColor::White, 2)]);
+        assert_eq!(
+            errors("8/8/8/8/8/8/8/8 w - - 0 1"),
+            [PositionError::MissingKing(PieceColor::White), PositionError::MissingKing(PieceColor::Black)]
+        );
+    }
+
+    #[test]
+    fn pawns() {
+        assert_eq!(errors("P3k3/8/8/8/8/8/8/4K3 w - - 0 1"), [PositionError::PawnOnBackRank(Position::new(7, 0))]);
+        assert_eq!(
+            errors("4k3/8/8/8/8/P7/PPPPPPPP/4K3 w - - 0 1"),
+            [PositionError::TooManyPawns(PieceColor::White, 9)]
+        );
+    }
+
+    #[test]
+    fn piece_counts() {
+        assert_eq!(
+            errors("4k3/8/8/8/NNNN4/NNNN4/PPPPPPPP/4K3 w - - 0 1"),
+            [PositionError::TooManyPieces(PieceColor::White, 17)]
+        );
+        // Two extra queens need two promotions, but only one pawn is missing
+        assert_eq!(
+            errors("4k3/8/8/8/8/QQQ5/PPPPPPP1/4K3 w - - 0 1"),
+            [PositionError::TooManyPromotions(PieceColor::White)]
+        );
+        assert_eq!(errors("4k3/8/8/8/8/QQ6/PPPPPPP1/4K3 w - - 0 1"), []);
+    }
+
+    #[test]
+    fn bishops_count_per_square_color() {
+        // c1 and f1: the starting pair, no promotion needed
+        assert_eq!(errors("4k3/8/8/8/8/8/PPPPPPPP/2B1KB2 w - - 0 1"), []);
+        // c1 and e3 are both dark, so one of them was promoted
+        assert_eq!(
+            errors("4k3/8/8/8/8/4B3/PPPPPPPP/2B1K3 w - - 0 1"),
+            [PositionError::TooManyPromotions(PieceColor::White)]
+        );
+        assert_eq!(errors("4k3/8/8/8/8/4B3/PPPPPPP1/2B1K3 w - - 0 1"), []);
+        // Three bishops, two of them on light squares, with all pawns missing but one
+        assert_eq!(errors("2b1kb2/3b3p/8/8/8/8/8/4K3 w - - 0 1"), []);
+    }
+
+    #[test]
+    fn castling_rights_need_king_and_rook_at_home() {
+        assert_eq!(
+            errors("4k3/8/8/8/8/8/8/4K3 w K - 0 1"),
+            [PositionError::ImpossibleCastling { color: PieceColor::White, kingside: true }]
+        );
+        assert_eq!(errors("r3k3/8/8/8/8/8/8/4K3 w q - 0 1"), []);
+        assert_eq!(
+            errors("4k2r/8/8/8/8/8/8/4K3 w q - 0 1"),
+            [PositionError::ImpossibleCastling { color: PieceColor::Black, kingside: false }]
+        );
+    }
+
+    #[test]
+    fn en_passant_needs_a_pawn_that_just_double_stepped() {
+        assert_eq!(
+            errors("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR b KQkq e3 0 1"),
+            [PositionError::ImpossibleEnPassant(Position::new(2, 4))]
+        );
+        assert_eq!(errors("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1"), []);
+        // Right square, but it is White's pawn and White to move
+        assert_eq!(
+            errors("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR w KQkq e3 0 1"),
+            [PositionError::ImpossibleEnPassant(Position::new(2, 4))]
+        );
+    }
+
+    #[test]
+    fn side_not_to_move_must_not_be_in_check() {
+        assert_eq!(errors("4k3/8/8/8/8/8/8/4R2K w - - 0 1"), [PositionError::OpponentInCheck(PieceColor::Black)]);
+        assert_eq!(errors("4k3/8/8/8/8/8/8/4R2K b - - 0 1"), []);
+        let Err(err) = Board::from_fen("4k3/8/8/8/8/8/8/4R2K w - - 0 1") else {
+            panic!("accepted an illegal position");
+        };
+        assert_eq!(err, "illegal position: Black is in check with White to move");
+    }
+}
@@ -0,0 +1,406 @@
+use std::time::{Duration, Instant};
+
+use crate::chess::PieceColor;
+
+/// What a player gets back for each move.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Increment {
+    None,
+    /// Added after every move, so time can build up
+    Fischer(Duration),
+    /// The time a move took is refunded, up to this much
+    Bronstein(Duration),
+    /// The clock only starts running once this much of the turn has passed
+    Delay(Duration),
+}
+
+/// A period of the game: `time` for `moves` moves, or for the rest of the
+/// game when `moves` is `None`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Stage {
+    pub moves: Option<u32>,
+    pub time: Duration,
+}
+
+/// One side's time control.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TimeControl {
+    /// Never empty; after the last stage with a move count, that stage
+    /// repeats
+    pub stages: Vec<Stage>,
+    pub increment: Increment,
+}
+
+impl TimeControl {
+    pub fn sudden_death(base: Duration) -> Self {
+        Self { stages: vec![Stage { moves: None, time: base }], increment: Increment::None }
+    }
+
+    pub fn fischer(base: Duration, increment: Duration) -> Self {
+        Self { increment: Increment::Fischer(increment), ..Self::sudden_death(base) }
+    }
+
+    pub fn bronstein(base: Duration, delay: Duration) -> Self {
+        Self { increment: Increment::Bronstein(delay), ..Self::sudden_death(base) }
+    }
+
+    pub fn delay(base: Duration, delay: Duration) -> Self {
+        Self { increment: Increment::Delay(delay), ..Self::sudden_death(base) }
+    }
+
+    /// The same control with every stage's time scaled to `percent`, for
+    /// time odds. Increments are left alone.
+    pub fn scaled(&self, percent: u32) -> Self {
+        let stages = self
+            .stages
+            .iter()
+            .map(|stage| Stage { moves: stage.moves, time: stage.time * percent / 100 })
+            .collect();
+        Self { stages, increment: self.increment }
+    }
+
+    /// PGN `TimeControl` tag value, e.g. "300+3" or "40/5400:1800+30".
+    /// Delays have no PGN notation and are left out.
+    pub fn pgn_tag(&self) -> String {
+        let stages: Vec<String> = self
+            .stages
+            .iter()
+            .map(|stage| match stage.moves {
+                Some(moves) => format!("{}/{}", moves, stage.time.as_secs()),
+                None => stage.time.as_secs().to_string(),
+            })
+            .collect();
+        let mut tag = stages.join(":");
+        if let Increment::Fischer(increment) = self.increment {
+            tag.push_str(&format!("+{}", increment.as_secs()));
+        }
+        tag
+    }
+}
+
+/// Named time controls offered on the setup screens.
+pub fn presets() -> Vec<(&'static str, TimeControl)> {
+    let minutes = |m: u64| Duration::from_secs(m * 60);
+    let seconds = Duration::from_secs;
+    vec![
+        ("Bullet 1+0", TimeControl::sudden_death(minutes(1))),
+        ("Blitz 3+2", TimeControl::fischer(minutes(3), seconds(2))),
+        ("Blitz 5+0", TimeControl::sudden_death(minutes(5))),
+        ("Blitz 5 min, 3 s Bronstein", TimeControl::bronstein(minutes(5), seconds(3))),
+        ("Rapid 10+5", TimeControl::fischer(minutes(10), seconds(5))),
+        ("Rapid 15+10", TimeControl::fischer(minutes(15), seconds(10))),
+        ("Rapid 25 min, 5 s delay", TimeControl::delay(minutes(25), seconds(5))),
+        ("Classical 90/40 + 30, 30 s increment", TimeControl {
+            stages: vec![Stage { moves: Some(40), time: minutes(90) }, Stage { moves: None, time: minutes(30) }],
+            increment: Increment::Fischer(seconds(30)),
+        }),
+    ]
+}
+
+/// "1:05:00", "4:59", or tenths once under ten seconds: "7.3".
+pub fn format_time(time: Duration) -> String {
+    let secs = time.as_secs();
+    if secs >= 3600 {
+        format!("{}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60)
+    } else if secs >= 10 {
+        format!("{}:{:02}", secs / 60, secs % 60)
+    } else {
+        format!("{}.{}", secs, time.subsec_millis() / 100)
+    }
+}
+
+fn index(color: PieceColor) -> usize {
+    match color {
+        PieceColor::White => 0,
+        PieceColor::Black => 1,
+    }
+}
+
+/// A two-sided game clock. Only the side to move's clock runs; pressing it
+/// after a move applies that side's increment and starts the other.
+#[derive(Debug, Clone)]
+pub struct ChessClock {
+    controls: [TimeControl; 2],
+    // Time left when the current turn started, for the side to move;
+    // simply the time left for the other
+    remaining: [Duration; 2],
+    stage: [usize; 2],
+    stage_moves: [u32; 2],
+    // The running side and when its turn started
+    turn: Option<(PieceColor, Instant)>,
+    flagged: Option<PieceColor>,
+}
+
+impl ChessClock {
+    pub fn new(white: TimeControl, black: TimeControl) -> Self {
+        let remaining = [white.stages[0].time, black.stages[0].time];
+        Self {
+            controls: [white, black],
+            remaining,
+            stage: [0; 2],
+            stage_moves: [0; 2],
+            turn: None,
+            flagged: None,
+        }
+    }
+
+    pub fn control(&self, side: PieceColor) -> &TimeControl {
+        &self.controls[index(side)]
+    }
+
+    /// Starts `side`'s clock.
+    pub fn start(&mut self, side: PieceColor) {
+        self.start_at(side, Instant::now());
+    }
+
+    fn start_at(&mut self, side: PieceColor, now: Instant) {
+        if self.flagged.is_none() {
+            self.turn = Some((side, now));
+        }
+    }
+
+    /// Stops the clock for good, e.g. when the game ends on the board.
+    pub fn stop(&mut self) {
+        self.stop_at(Instant::now());
+    }
+
+    fn stop_at(&mut self, now: Instant) {
+        if let Some((side, _)) = self.turn {
+            self.remaining[index(side)] = self.remaining_at(side, now);
+            self.turn = None;
+        }
+    }
+
+    pub fn running_side(&self) -> Option<PieceColor> {
+        self.turn.map(|(side, _)| side)
+    }
+
+    /// Part of the current turn's time that comes off the clock.
+    fn charged(&self, side: PieceColor, elapsed: Duration) -> Duration {
+        match self.control(side).increment {
+            Increment::Delay(delay) => elapsed.saturating_sub(delay),
+            _ => elapsed,
+        }
+    }
+
+    /// Time showing on `side`'s clock right now.
+    pub fn remaining(&self, side: PieceColor) -> Duration {
+        self.remaining_at(side, Instant::now())
+    }
+
+    fn remaining_at(&self, side: PieceColor, now: Instant) -> Duration {
+        let left = self.remaining[index(side)];
+        match self.turn {
+            Some((running, started)) if running == side => {
+                left.saturating_sub(self.charged(side, now.saturating_duration_since(started)))
+            }
+            _ => left,
+        }
+    }
+
+    /// What is left of the current turn's delay, while one is running.
+    pub fn delay_left(&self) -> Option<Duration> {
+        self.delay_left_at(Instant::now())
+    }
+
+    fn delay_left_at(&self, now: Instant) -> Option<Duration> {
+        let (side, started) = self.turn?;
+        match self.control(side).increment {
+            Increment::Delay(delay) => delay.checked_sub(now.saturating_duration_since(started)).filter(|left| !left.is_zero()),
+            _ => None,
+        }
+    }
+
+    /// The side whose time has run out, if any. Checking stops the clock
+    /// once a flag has fallen.
+    pub fn flag(&mut self) -> Option<PieceColor> {
+        self.flag_at(Instant::now())
+    }
+
+    fn flag_at(&mut self, now: Instant) -> Option<PieceColor> {
+        if let Some((side, _)) = self.turn && self.remaining_at(side, now).is_zero() {
+            self.remaining[index(side)] = Duration::ZERO;
+            self.turn = None;
+            self.flagged = Some(side);
+        }
+        self.flagged
+    }
+
+    /// The side to move has made its move: its turn's time comes off, its
+    /// increment and any new stage's time go on, and the opponent's clock
+    /// starts. Does nothing if the flag fell first.
+    pub fn press(&mut self) {
+        self.press_at(Instant::now());
+    }
+
+    fn press_at(&mut self, now: Instant) {
+        if self.flag_at(now).is_some() {
+            return;
+        }
+        let Some((side, started)) = self.turn else {
+            return;
+        };
+        let elapsed = now.saturating_duration_since(started);
+        let i = index(side);
+
+        self.remaining[i] = self.remaining_at(side, now);
+        match self.controls[i].increment {
+            Increment::Fischer(increment) => self.remaining[i] += increment,
+            Increment::Bronstein(delay) => self.remaining[i] += elapsed.min(delay),
+            Increment::None | Increment::Delay(_) => {}
+        }
+
+        self.stage_moves[i] += 1;
+        let stages = &self.controls[i].stages;
+        if stages[self.stage[i]].moves == Some(self.stage_moves[i]) {
+            self.stage[i] = (self.stage[i] + 1).min(stages.len() - 1);
+            self.stage_moves[i] = 0;
+            self.remaining[i] += stages[self.stage[i]].time;
+        }
+
+        self.turn = Some((side.opposite(), now));
+    }
+
+    /// Moves `side` has left to play before its next time control, as UCI
+    /// `movestogo`.
+    pub fn moves_to_go(&self, side: PieceColor) -> Option<u32> {
+        let i = index(side);
+        let moves = self.controls[i].stages[self.stage[i]].moves?;
+        Some(moves - self.stage_moves[i])
+    }
+
+    /// Per-move time `side` gets back, as an engine's increment. Delays
+    /// are passed on as increments, which they amount to for a move that
+    /// takes at least as long as the delay.
+    pub fn increment(&self, side: PieceColor) -> Duration {
+        match self.control(side).increment {
+            Increment::None => Duration::ZERO,
+            Increment::Fischer(time) | Increment::Bronstein(time) | Increment::Delay(time) => time,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use PieceColor::{Black, White};
+
+    fn secs(s: u64) -> Duration {
+        Duration::from_secs(s)
+    }
+
+    /// A clock for `control` on both sides with White's clock started at
+    /// the returned instant.
+    fn started(control: TimeControl) -> (ChessClock, Instant) {
+        let mut clock = ChessClock::new(control.clone(), control);
+        let start = Instant::now();
+        clock.start_at(White, start);
+        (clock, start)
+    }
+
+    #[test]
+    fn sudden_death_only_runs_the_side_to_move() {
+        let (mut clock, start) = started(TimeControl::sudden_death(secs(300)));
+        assert_eq!(clock.remaining_at(White, start + secs(4)), secs(296));
+        assert_eq!(clock.remaining_at(Black, start + secs(4)), secs(300));
+
+        clock.press_at(start + secs(10));
+        assert_eq!(clock.running_side(), Some(Black));
+        assert_eq!(clock.remaining_at(White, start + secs(15)), secs(290));
+        assert_eq!(clock.remaining_at(Black, start + secs(15)), secs(295));
+    }
+
+    #[test]
+    fn fischer_adds_the_increment_after_each_move() {
+        let (mut clock, start) = started(TimeControl::fischer(secs(180), secs(2)));
+        clock.press_at(start + secs(10));
+        assert_eq!(clock.remaining_at(White, start + secs(10)), secs(172));
+        // Quick moves build time up
+        clock.press_at(start + secs(11));
+        assert_eq!(clock.remaining_at(Black, start + secs(11)), secs(181));
+    }
+
+    #[test]
+    fn bronstein_refund_is_capped_at_the_time_used() {
+        let (mut clock, start) = started(TimeControl::bronstein(secs(300), secs(3)));
+        clock.press_at(start + secs(10));
+        assert_eq!(clock.remaining_at(White, start + secs(10)), secs(293));
+        // A one second move gets only that second back
+        clock.press_at(start + secs(11));
+        assert_eq!(clock.remaining_at(Black, start + secs(11)), secs(300));
+    }
+
+    #[test]
+    fn delay_does_not_eat_into_the_main_time() {
+        let (mut clock, start) = started(TimeControl::delay(secs(1500), secs(5)));
+        assert_eq!(clock.delay_left_at(start + secs(2)), Some(secs(3)));
+        assert_eq!(clock.remaining_at(White, start + secs(4)), secs(1500));
+        clock.press_at(start + secs(4));
+        assert_eq!(clock.remaining_at(White, start + secs(4)), secs(1500));
+
+        // Only the part of the turn after the delay comes off
+        assert_eq!(clock.delay_left_at(start + secs(10)), None);
+        clock.press_at(start + secs(12));
+        assert_eq!(clock.remaining_at(Black, start + secs(12)), secs(1497));
+    }
+
+    #[test]
+    fn next_stage_time_is_added_on_move_forty() {
+        let control = TimeControl {
+            stages: vec![Stage { moves: Some(40), time: secs(90 * 60) }, Stage { moves: None, time: secs(30 * 60) }],
+            increment: Increment::Fischer(secs(30)),
+        };
+        let (mut clock, start) = started(control);
+        let mut now = start;
+        for _ in 0..39 {
+            // A minute per move for each side
+            now += secs(60);
+            clock.press_at(now);
+            now += secs(60);
+            clock.press_at(now);
+        }
+        assert_eq!(clock.moves_to_go(White), Some(1));
+        assert_eq!(clock.remaining_at(White, now), secs(5400 - 39 * 30));
+
+        now += secs(60);
+        clock.press_at(now);
+        assert_eq!(clock.moves_to_go(White), None);
+        assert_eq!(clock.moves_to_go(Black), Some(1));
+        assert_eq!(clock.remaining_at(White, now), secs(5400 - 40 * 30 + 1800));
+    }
+
+    #[test]
+    fn time_odds_scale_one_side_only() {
+        let base = TimeControl::fischer(secs(300), secs(2));
+        let mut clock = ChessClock::new(base.scaled(50), base);
+        let start = Instant::now();
+        clock.start_at(White, start);
+        assert_eq!(clock.remaining_at(White, start), secs(150));
+        assert_eq!(clock.remaining_at(Black, start), secs(300));
+        clock.press_at(start + secs(10));
+        assert_eq!(clock.remaining_at(White, start + secs(10)), secs(142));
+    }
+
+    #[test]
+    fn flag_falls_when_the_time_runs_out() {
+        let (mut clock, start) = started(TimeControl::sudden_death(secs(60)));
+        assert_eq!(clock.flag_at(start + secs(59)), None);
+        assert_eq!(clock.flag_at(start + secs(61)), Some(White));
+        assert_eq!(clock.remaining_at(White, start + secs(70)), Duration::ZERO);
+        assert_eq!(clock.running_side(), None);
+
+        // Nothing restarts a clock once a flag has fallen
+        clock.press_at(start + secs(62));
+        clock.start_at(Black, start + secs(62));
+        assert_eq!(clock.running_side(), None);
+        assert_eq!(clock.remaining_at(Black, start + secs(70)), secs(60));
+    }
+
+    #[test]
+    fn flag_waits_for_the_delay() {
+        let (mut clock, start) = started(TimeControl::delay(secs(60), secs(5)));
+        assert_eq!(clock.flag_at(start + secs(64)), None);
+        assert_eq!(clock.flag_at(start + secs(65)), Some(White));
+    }
+}
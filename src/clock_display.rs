@@ -0,0 +1,125 @@
+use std::time::Duration;
+
+use egui::{Align2, Color32, FontId, Rect, Stroke};
+use crate::chess::PieceColor;
+use crate::clock::{self, TimeControl};
+
+pub const CLOCK_WIDTH: f32 = 110.0;
+pub const CLOCK_HEIGHT: f32 = 40.0;
+
+/// Choices on the setup screens. Kept between games so "New Game" replays
+/// the same control.
+pub struct ClockSetup {
+    /// Index into [`clock::presets`], the custom control at the end of the
+    /// list, or no clock at all
+    pub preset: Option<usize>,
+    pub custom_minutes: u64,
+    pub custom_increment_secs: u64,
+    /// Side that gets less time, and what share of the full time it gets
+    pub odds: Option<PieceColor>,
+    pub odds_percent: u32,
+}
+
+impl Default for ClockSetup {
+    fn default() -> Self {
+        Self {
+            preset: None,
+            custom_minutes: 10,
+            custom_increment_secs: 0,
+            odds: None,
+            odds_percent: 50,
+        }
+    }
+}
+
+impl ClockSetup {
+    /// White's and Black's time controls, or `None` for an untimed game.
+    pub fn time_controls(&self) -> Option<(TimeControl, TimeControl)> {
+        let presets = clock::presets();
+        let control = match self.preset? {
+            index if index < presets.len() => presets[index].1.clone(),
+            _ => TimeControl::fischer(
+                Duration::from_secs(self.custom_minutes * 60),
+                Duration::from_secs(self.custom_increment_secs),
+            ),
+        };
+        let odds = control.scaled(self.odds_percent);
+        Some(match self.odds {
+            Some(PieceColor::White) => (odds, control),
+            Some(PieceColor::Black) => (control, odds),
+            None => (control.clone(), control),
+        })
+    }
+}
+
+pub fn show_clock_setup(ui: &mut egui::Ui, setup: &mut ClockSetup) {
+    let presets = clock::presets();
+    let custom = presets.len();
+    let selected = match setup.preset {
+        None => "No clock",
+        Some(index) if index < custom => presets[index].0,
+        Some(_) => "Custom",
+    };
+
+    ui.horizontal(|ui| {
+        ui.label("Time control:");
+        egui::ComboBox::from_id_source("time_control")
+            .selected_text(selected)
+            .show_ui(ui, |ui| {
+                ui.selectable_value(&mut setup.preset, None, "No clock");
+                for (index, (name, _)) in presets.iter().enumerate() {
+                    ui.selectable_value(&mut setup.preset, Some(index), *name);
+                }
+                ui.selectable_value(&mut setup.preset, Some(custom), "Custom");
+            });
+    });
+    if setup.preset.is_none() {
+        return;
+    }
+
+    if setup.preset == Some(custom) {
+        ui.horizontal(|ui| {
+            ui.add(egui::DragValue::new(&mut setup.custom_minutes).clamp_range(1..=300).suffix(" min"));
+            ui.label("+");
+            ui.add(egui::DragValue::new(&mut setup.custom_increment_secs).clamp_range(0..=180).suffix(" s"));
+        });
+    }
+
+    ui.horizontal(|ui| {
+        ui.label("Time odds:");
+        ui.radio_value(&mut setup.odds, None, "None");
+        ui.radio_value(&mut setup.odds, Some(PieceColor::White), "White");
+        ui.radio_value(&mut setup.odds, Some(PieceColor::Black), "Black");
+        if setup.odds.is_some() {
+            ui.add(egui::DragValue::new(&mut setup.odds_percent).clamp_range(10..=90).suffix("%"))
+                .on_hover_text("Share of the normal time the handicapped side gets");
+        }
+    });
+}
+
+/// Draws one side's clock. The running clock is lit up, a fallen flag is
+/// red, and a running delay shows as a countdown under the time.
+pub fn draw_clock(ui: &egui::Ui, rect: Rect, remaining: Duration, running: bool, flagged: bool, delay_left: Option<Duration>) {
+    let (fill, text) = if flagged {
+        (Color32::from_rgb(190, 50, 40), Color32::WHITE)
+    } else if running {
+        (Color32::from_gray(235), Color32::BLACK)
+    } else {
+        (Color32::from_gray(60), Color32::from_gray(170))
+    };
+
+    let painter = ui.painter();
+    painter.rect_filled(rect, 4.0, fill);
+    painter.rect_stroke(rect, 4.0, Stroke::new(1.0, Color32::DARK_GRAY));
+    painter.text(rect.center(), Align2::CENTER_CENTER, clock::format_time(remaining), FontId::monospace(22.0), text);
+
+    if let Some(delay) = delay_left.filter(|_| running) {
+        painter.text(
+            rect.center_bottom(),
+            Align2::CENTER_BOTTOM,
+            format!("delay {:.1}", delay.as_secs_f32()),
+            FontId::proportional(9.0),
+            Color32::from_gray(90),
+        );
+    }
+}
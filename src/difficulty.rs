@@ -0,0 +1,173 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use rand::Rng;
+use crate::game_mode::Difficulty;
+use crate::stockfish::SearchLimits;
+
+pub const PROFILES_PATH: &str = "difficulty_profiles.cfg";
+
+/// Stockfish's `UCI_Elo` range, assumed until it has started and
+/// advertised its own.
+pub const STOCKFISH_MIN_ELO: u32 = 1320;
+pub const STOCKFISH_MAX_ELO: u32 = 3190;
+
+/// How the engine's playing strength is limited.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EngineStrength {
+    /// `Skill Level` 0-20, 20 being full strength
+    Skill(u8),
+    /// Target rating through `UCI_LimitStrength` and `UCI_Elo`
+    Elo(u32),
+}
+
+/// A named engine configuration: strength plus how long it may search.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DifficultyProfile {
+    pub name: String,
+    pub strength: EngineStrength,
+    pub movetime_ms: u64,
+    pub depth: Option<u32>,
+    pub nodes: Option<u64>,
+    /// When set, each move's think time is drawn from this range instead of
+    /// using `movetime_ms`, so the engine doesn't answer like clockwork.
+    pub think_time_ms: Option<(u64, u64)>,
+}
+
+impl DifficultyProfile {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            strength: EngineStrength::Elo(1500),
+            movetime_ms: 1000,
+            depth: None,
+            nodes: None,
+            think_time_ms: None,
+        }
+    }
+
+    /// Think time for the next move.
+    pub fn next_movetime(&self) -> u64 {
+        match self.think_time_ms {
+            Some((min, max)) if min < max => rand::thread_rng().gen_range(min..=max),
+            Some((min, _)) => min,
+            None => self.movetime_ms,
+        }
+    }
+
+    /// Fixed-time limits for the next move, capped by depth and nodes.
+    pub fn search_limits(&self) -> SearchLimits {
+        SearchLimits::movetime(self.next_movetime())
+            .with_depth(self.depth)
+            .with_nodes(self.nodes)
+    }
+}
+
+impl Difficulty {
+    pub fn profile(self) -> DifficultyProfile {
+        let (strength, depth) = match self {
+            Difficulty::Easy => (EngineStrength::Skill(5), Some(8)),
+            Difficulty::Medium => (EngineStrength::Skill(15), None),
+            Difficulty::Hard => (EngineStrength::Skill(20), None),
+        };
+        DifficultyProfile {
+            name: self.to_string(),
+            strength,
+            movetime_ms: 1000,
+            depth,
+            nodes: None,
+            think_time_ms: None,
+        }
+    }
+}
+
+/// Reads custom profiles from an INI-style file:
+///
+/// ```text
+/// [Club player]
+/// elo = 1800
+/// movetime = 1000
+/// depth = 12
+/// think_time = 500-2000
+/// ```
+///
+/// A missing file is not an error; it just means no custom profiles yet.
+pub fn load_profiles(path: &Path) -> io::Result<Vec<DifficultyProfile>> {
+    let text = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => return Err(err),
+    };
+    Ok(parse_profiles(&text))
+}
+
+pub fn parse_profiles(text: &str) -> Vec<DifficultyProfile> {
+    let mut profiles: Vec<DifficultyProfile> = Vec::new();
+
+    for (number, line) in text.lines().enumerate() {
+        // Everything after '#' is a comment
+        let line = line.split('#').next().unwrap_or_default().trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            profiles.push(DifficultyProfile::new(name.trim()));
+            continue;
+        }
+
+        let (Some(profile), Some((key, value))) = (profiles.last_mut(), line.split_once('=')) else {
+            eprintln!("{}:{}: ignoring line outside a [profile] section", PROFILES_PATH, number + 1);
+            continue;
+        };
+
+        let value = value.trim();
+        let parsed = match key.trim() {
+            // Each engine brings the rating into its own range when it is applied
+            "elo" => value.parse().ok().map(|elo| profile.strength = EngineStrength::Elo(elo)),
+            "skill" => value.parse().ok().map(|skill: u8| {
+                profile.strength = EngineStrength::Skill(skill.min(20));
+            }),
+            "movetime" => value.parse().ok().map(|ms| profile.movetime_ms = ms),
+            "depth" => value.parse().ok().map(|depth| profile.depth = Some(depth)),
+            "nodes" => value.parse().ok().map(|nodes| profile.nodes = Some(nodes)),
+            "think_time" => value.split_once('-').and_then(|(min, max)| {
+                let range = (min.trim().parse().ok()?, max.trim().parse().ok()?);
+                profile.think_time_ms = Some(range);
+                Some(())
+            }),
+            _ => None,
+        };
+
+        if parsed.is_none() {
+            eprintln!("{}:{}: ignoring invalid setting '{}'", PROFILES_PATH, number + 1, line);
+        }
+    }
+
+    profiles
+}
+
+pub fn save_profiles(path: &Path, profiles: &[DifficultyProfile]) -> io::Result<()> {
+    let mut text = String::from("# Custom engine difficulty profiles\n");
+
+    for profile in profiles {
+        text.push_str(&format!("\n[{}]\n", profile.name));
+        match profile.strength {
+            EngineStrength::Elo(elo) => text.push_str(&format!("elo = {}\n", elo)),
+            EngineStrength::Skill(skill) => text.push_str(&format!("skill = {}\n", skill)),
+        }
+        text.push_str(&format!("movetime = {}\n", profile.movetime_ms));
+        if let Some(depth) = profile.depth {
+            text.push_str(&format!("depth = {}\n", depth));
+        }
+        if let Some(nodes) = profile.nodes {
+            text.push_str(&format!("nodes = {}\n", nodes));
+        }
+        if let Some((min, max)) = profile.think_time_ms {
+            text.push_str(&format!("think_time = {}-{}\n", min, max));
+        }
+    }
+
+    fs::write(path, text)
+}
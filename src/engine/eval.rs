@@ -0,0 +1,178 @@
+use crate::chess::{Board, PieceColor, PieceType, Position};
+
+/// Material values in the middlegame and the endgame, by [`piece_index`].
+const MG_VALUE: [i32; 6] = [82, 337, 365, 477, 1025, 0];
+const EG_VALUE: [i32; 6] = [94, 281, 297, 512, 936, 0];
+
+/// How much each piece counts towards the game phase; 24 is the full
+/// starting set, 0 bare kings and pawns.
+const PHASE_WEIGHT: [i32; 6] = [0, 1, 1, 2, 4, 0];
+const MAX_PHASE: i32 = 24;
+
+const BISHOP_PAIR: i32 = 30;
+
+// Piece-square tables from White's point of view, laid out as the board is
+// printed: a8 first, h1 last. Black reads them mirrored.
+
+const PAWN_MG: [i32; 64] = [
+      0,   0,   0,   0,   0,   0,   0,   0,
+     50,  50,  50,  50,  50,  50,  50,  50,
+     10,  10,  20,  30,  30,  20,  10,  10,
+      5,   5,  10,  25,  25,  10,   5,   5,
+      0,   0,   0,  20,  20,   0,   0,   0,
+      5,  -5, -10,   0,   0, -10,  -5,   5,
+      5,  10,  10, -20, -20,  10,  10,   5,
+      0,   0,   0,   0,   0,   0,   0,   0,
+];
+
+const PAWN_EG: [i32; 64] = [
+      0,   0,   0,   0,   0,   0,   0,   0,
+     90,  90,  90,  90,  90,  90,  90,  90,
+     55,  55,  55,  55,  55,  55,  55,  55,
+     30,  30,  30,  30,  30,  30,  30,  30,
+     15,  15,  15,  15,  15,  15,  15,  15,
+      5,   5,   5,   5,   5,   5,   5,   5,
+      0,   0,   0,   0,   0,   0,   0,   0,
+      0,   0,   0,   0,   0,   0,   0,   0,
+];
+
+const KNIGHT: [i32; 64] = [
+    -50, -40, -30, -30, -30, -30, -40, -50,
+    -40, -20,   0,   0,   0,   0, -20, -40,
+    -30,   0,  10,  15,  15,  10,   0, -30,
+    -30,   5,  15,  20,  20,  15,   5, -30,
+    -30,   0,  15,  20,  20,  15,   0, -30,
+    -30,   5,  10,  15,  15,  10,   5, -30,
+    -40, -20,   0,   5,   5,   0, -20, -40,
+    -50, -40, -30, -30, -30, -30, -40, -50,
+];
+
+const BISHOP: [i32; 64] = [
+    -20, -10, -10, -10, -10, -10, -10, -20,
+    -10,   0,   0,   0,   0,   0,   0, -10,
+    -10,   0,   5,  10,  10,   5,   0, -10,
+    -10,   5,   5,  10,  10,   5,   5, -10,
+    -10,   0,  10,  10,  10,  10,   0, -10,
+    -10,  10,  10,  10,  10,  10,  10, -10,
+    -10,   5,   0,   0,   0,   0,   5, -10,
+    -20, -10, -10, -10, -10, -10, -10, -20,
+];
+
+const ROOK: [i32; 64] = [
+      0,   0,   0,   0,   0,   0,   0,   0,
+      5,  10,  10,  10,  10,  10,  10,   5,
+     -5,   0,   0,   0,   0,   0,   0,  -5,
+     -5,   0,   0,   0,   0,   0,   0,  -5,
+     -5,   0,   0,   0,   0,   0,   0,  -5,
+     -5,   0,   0,   0,   0,   0,   0,  -5,
+     -5,   0,   0,   0,   0,   0,   0,  -5,
+      0,   0,   0,   5,   5,   0,   0,   0,
+];
+
+const QUEEN: [i32; 64] = [
+    -20, -10, -10,  -5,  -5, -10, -10, -20,
+    -10,   0,   0,   0,   0,   0,   0, -10,
+    -10,   0,   5,   5,   5,   5,   0, -10,
+     -5,   0,   5,   5,   5,   5,   0,  -5,
+      0,   0,   5,   5,   5,   5,   0,  -5,
+    -10,   5,   5,   5,   5,   5,   0, -10,
+    -10,   0,   5,   0,   0,   0,   0, -10,
+    -20, -10, -10,  -5,  -5, -10, -10, -20,
+];
+
+const KING_MG: [i32; 64] = [
+    -30, -40, -40, -50, -50, -40, -40, -30,
+    -30, -40, -40, -50, -50, -40, -40, -30,
+    -30, -40, -40, -50, -50, -40, -40, -30,
+    -30, -40, -40, -50, -50, -40, -40, -30,
+    -20, -30, -30, -40, -40, -30, -30, -20,
+    -10, -20, -20, -20, -20, -20, -20, -10,
+     20,  20,   0,   0,   0,   0,  20,  20,
+     20,  30,  10,   0,   0,  10,  30,  20,
+];
+
+const KING_EG: [i32; 64] = [
+    -50, -40, -30, -20, -20, -30, -40, -50,
+    -30, -20, -10,   0,   0, -10, -20, -30,
+    -30, -10,  20,  30,  30,  20, -10, -30,
+    -30, -10,  30,  40,  40,  30, -10, -30,
+    -30, -10,  30,  40,  40,  30, -10, -30,
+    -30, -10,  20,  30,  30,  20, -10, -30,
+    -30, -30,   0,   0,   0,   0, -30, -30,
+    -50, -30, -30, -30, -30, -30, -30, -50,
+];
+
+/// Pawn, knight, bishop, rook, queen, king: the order of the value tables.
+pub fn piece_index(piece_type: PieceType) -> usize {
+    match piece_type {
+        PieceType::Pawn => 0,
+        PieceType::Knight => 1,
+        PieceType::Bishop => 2,
+        PieceType::Rook => 3,
+        PieceType::Queen => 4,
+        PieceType::King => 5,
+    }
+}
+
+/// Rough material value, for ordering captures.
+pub fn piece_value(piece_type: PieceType) -> i32 {
+    match piece_type {
+        PieceType::King => 20_000,
+        _ => MG_VALUE[piece_index(piece_type)],
+    }
+}
+
+fn square_bonus(piece_type: PieceType, color: PieceColor, pos: Position) -> (i32, i32) {
+    let index = match color {
+        PieceColor::White => (7 - pos.rank) * 8 + pos.file,
+        PieceColor::Black => pos.rank * 8 + pos.file,
+    };
+    match piece_type {
+        PieceType::Pawn => (PAWN_MG[index], PAWN_EG[index]),
+        PieceType::Knight => (KNIGHT[index], KNIGHT[index]),
+        PieceType::Bishop => (BISHOP[index], BISHOP[index]),
+        PieceType::Rook => (ROOK[index], ROOK[index]),
+        PieceType::Queen => (QUEEN[index], QUEEN[index]),
+        PieceType::King => (KING_MG[index], KING_EG[index]),
+    }
+}
+
+/// Static evaluation in centipawns from the side to move's point of view:
+/// material and piece-square values, blended between middlegame and
+/// endgame tables by how much material is left.
+pub fn evaluate(board: &Board) -> i32 {
+    let mut mg = [0; 2];
+    let mut eg = [0; 2];
+    let mut bishops = [0; 2];
+    let mut phase = 0;
+
+    for rank in 0..8 {
+        for file in 0..8 {
+            let pos = Position::new(rank, file);
+            let Some(piece) = board.get_piece(pos) else { continue };
+            let side = (piece.color == PieceColor::Black) as usize;
+            let index = piece_index(piece.piece_type);
+            let (mg_bonus, eg_bonus) = square_bonus(piece.piece_type, piece.color, pos);
+            mg[side] += MG_VALUE[index] + mg_bonus;
+            eg[side] += EG_VALUE[index] + eg_bonus;
+            phase += PHASE_WEIGHT[index];
+            if piece.piece_type == PieceType::Bishop {
+                bishops[side] += 1;
+            }
+        }
+    }
+    for side in 0..2 {
+        if bishops[side] >= 2 {
+            mg[side] += BISHOP_PAIR;
+            eg[side] += BISHOP_PAIR;
+        }
+    }
+
+    // Promotions can push the phase past the starting total
+    let phase = phase.min(MAX_PHASE);
+    let white = ((mg[0] - mg[1]) * phase + (eg[0] - eg[1]) * (MAX_PHASE - phase)) / MAX_PHASE;
+    match board.current_turn() {
+        PieceColor::White => white,
+        PieceColor::Black => -white,
+    }
+}
@@ -0,0 +1,331 @@
+//! A native engine for playing without Stockfish. It runs on its own
+//! thread and speaks UCI over channels, so [`crate::stockfish::Stockfish`]
+//! drives it exactly like an external engine: pass [`BUILTIN_ENGINE`] as
+//! the engine path.
+
+mod eval;
+mod search;
+
+use std::str::SplitWhitespace;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use crate::chess::{Board, PieceColor};
+use crate::stockfish::SearchLimits;
+use search::{position_hash, SearchControl, SearchParams, Searcher, MAX_PLY};
+
+/// Engine "path" that selects the built-in engine instead of an executable.
+pub const BUILTIN_ENGINE: &str = "builtin";
+pub const ENGINE_NAME: &str = "Chess.rs built-in";
+
+const DEFAULT_HASH_MB: usize = 16;
+/// Ratings mapped onto skill levels 0-20 when `UCI_LimitStrength` is on,
+/// advertised as the `UCI_Elo` range.
+pub const MIN_ELO: u32 = 600;
+pub const MAX_ELO: u32 = 2000;
+
+/// Starts the engine. Commands go in through the returned sender, one per
+/// message; everything the engine says comes out through `output`. The
+/// engine quits on `quit` or when the command sender is dropped.
+pub fn spawn(output: Sender<String>) -> (Sender<String>, JoinHandle<()>) {
+    let (commands, input) = mpsc::channel();
+    let handle = thread::spawn(move || Uci::new(output).run(input));
+    (commands, handle)
+}
+
+struct EngineOptions {
+    hash_mb: usize,
+    multipv: usize,
+    skill: u8,
+    limit_strength: bool,
+    elo: u32,
+}
+
+impl EngineOptions {
+    fn effective_skill(&self) -> u8 {
+        if self.limit_strength {
+            let elo = self.elo.clamp(MIN_ELO, MAX_ELO);
+            ((elo - MIN_ELO) * 20 / (MAX_ELO - MIN_ELO)) as u8
+        } else {
+            self.skill
+        }
+    }
+}
+
+struct RunningSearch {
+    control: Arc<SearchControl>,
+    // Keeps the answer back until `stop`, or `ponderhit` for a ponder search
+    hold: Arc<AtomicBool>,
+    // Time for a ponder search once it becomes a real one
+    ponder_budget: Option<Duration>,
+    handle: JoinHandle<Searcher>,
+}
+
+struct Uci {
+    output: Sender<String>,
+    options: EngineOptions,
+    board: Board,
+    // Hashes of the positions before `board`, for repetition draws
+    history: Vec<u64>,
+    // Lent to the search thread while a search runs
+    searcher: Option<Searcher>,
+    running: Option<RunningSearch>,
+}
+
+impl Uci {
+    fn new(output: Sender<String>) -> Self {
+        Self {
+            output,
+            options: EngineOptions {
+                hash_mb: DEFAULT_HASH_MB,
+                multipv: 1,
+                skill: 20,
+                limit_strength: false,
+                elo: 1500,
+            },
+            board: Board::new(),
+            history: Vec::new(),
+            searcher: Some(Searcher::new(DEFAULT_HASH_MB)),
+            running: None,
+        }
+    }
+
+    fn run(mut self, input: Receiver<String>) {
+        for line in input {
+            if !self.handle(line.trim()) {
+                break;
+            }
+        }
+        self.finish_search();
+    }
+
+    fn send(&self, line: String) {
+        let _ = self.output.send(line);
+    }
+
+    /// Returns false on `quit`.
+    fn handle(&mut self, line: &str) -> bool {
+        let (command, args) = line.split_once(' ').unwrap_or((line, ""));
+        match command {
+            "uci" => {
+                for line in [
+                    format!("id name {}", ENGINE_NAME),
+                    "id author the Chess.rs authors".to_string(),
+                    format!("option name Hash type spin default {} min 1 max 1024", DEFAULT_HASH_MB),
+                    "option name Clear Hash type button".to_string(),
+                    "option name Ponder type check default false".to_string(),
+                    "option name MultiPV type spin default 1 min 1 max 5".to_string(),
+                    "option name Skill Level type spin default 20 min 0 max 20".to_string(),
+                    "option name UCI_LimitStrength type check default false".to_string(),
+                    format!("option name UCI_Elo type spin default 1500 min {} max {}", MIN_ELO, MAX_ELO),
+                    "uciok".to_string(),
+                ] {
+                    self.send(line);
+                }
+            }
+            "isready" => self.send("readyok".to_string()),
+            "setoption" => {
+                self.finish_search();
+                self.set_option(args);
+            }
+            "ucinewgame" => {
+                self.finish_search();
+                if let Some(searcher) = &mut self.searcher {
+                    searcher.clear();
+                }
+            }
+            "position" => {
+                self.finish_search();
+                self.set_position(args);
+            }
+            "go" => {
+                self.finish_search();
+                self.go(args);
+            }
+            "stop" => self.finish_search(),
+            "ponderhit" => self.ponder_hit(),
+            "quit" => return false,
+            "" => {}
+            _ => self.send(format!("info string unknown command: {}", line)),
+        }
+        true
+    }
+
+    fn set_option(&mut self, args: &str) {
+        let Some(rest) = args.strip_prefix("name ") else {
+            return;
+        };
+        let (name, value) = rest.split_once(" value ").unwrap_or((rest, ""));
+        let (name, value) = (name.trim().to_ascii_lowercase(), value.trim());
+        let number = value.parse::<u32>().ok();
+
+        match name.as_str() {
+            "hash" => {
+                if let Some(mb) = number {
+                    self.options.hash_mb = (mb as usize).clamp(1, 1024);
+                    self.searcher = Some(Searcher::new(self.options.hash_mb));
+                }
+            }
+            "clear hash" => {
+                if let Some(searcher) = &mut self.searcher {
+                    searcher.clear();
+                }
+            }
+            "multipv" => self.options.multipv = number.map_or(1, |n| n.clamp(1, 5) as usize),
+            "skill level" => self.options.skill = number.map_or(20, |n| n.min(20) as u8),
+            "uci_limitstrength" => self.options.limit_strength = value == "true",
+            "uci_elo" => self.options.elo = number.unwrap_or(self.options.elo),
+            // Pondering is up to whoever sends `go ponder`
+            "ponder" => {}
+            _ => self.send(format!("info string no such option: {}", name)),
+        }
+    }
+
+    fn set_position(&mut self, args: &str) {
+        let (setup, moves) = match args.split_once("moves") {
+            Some((setup, moves)) => (setup.trim(), moves),
+            None => (args.trim(), ""),
+        };
+        let board = if setup == "startpos" {
+            Ok(Board::new())
+        } else if let Some(fen) = setup.strip_prefix("fen ") {
+            Board::from_fen(fen)
+        } else {
+            Err(format!("bad position command: {}", args))
+        };
+        let mut board = match board {
+            Ok(board) => board,
+            Err(err) => {
+                self.send(format!("info string {}", err));
+                return;
+            }
+        };
+
+        let mut history = Vec::new();
+        for uci in moves.split_whitespace() {
+            let Some(mv) = board.find_legal_move(uci) else {
+                self.send(format!("info string illegal move {}", uci));
+                break;
+            };
+            history.push(position_hash(&board));
+            board.make_move(mv);
+        }
+        self.board = board;
+        self.history = history;
+    }
+
+    fn go(&mut self, args: &str) {
+        let mut limits = SearchLimits::default();
+        let mut ponder = false;
+        let mut infinite = false;
+        let mut tokens = args.split_whitespace();
+        while let Some(token) = tokens.next() {
+            match token {
+                "ponder" => ponder = true,
+                "infinite" => infinite = true,
+                "wtime" => limits.wtime_ms = next_number(&mut tokens),
+                "btime" => limits.btime_ms = next_number(&mut tokens),
+                "winc" => limits.winc_ms = next_number(&mut tokens),
+                "binc" => limits.binc_ms = next_number(&mut tokens),
+                "movestogo" => limits.movestogo = next_number(&mut tokens).map(|n| n as u32),
+                "depth" => limits.depth = next_number(&mut tokens).map(|n| n as u32),
+                "nodes" => limits.nodes = next_number(&mut tokens),
+                "mate" => limits.mate = next_number(&mut tokens).map(|n| n as u32),
+                "movetime" => limits.movetime_ms = next_number(&mut tokens),
+                _ => {}
+            }
+        }
+
+        let (time, increment) = match self.board.current_turn() {
+            PieceColor::White => (limits.wtime_ms, limits.winc_ms),
+            PieceColor::Black => (limits.btime_ms, limits.binc_ms),
+        };
+        let clock_budget = time.map(|time| {
+            search::allocate_time(
+                Duration::from_millis(time),
+                Duration::from_millis(increment.unwrap_or(0)),
+                limits.movestogo,
+            )
+        });
+        let budget = match (limits.movetime_ms.map(Duration::from_millis), clock_budget) {
+            (Some(movetime), Some(clock)) => Some(movetime.min(clock)),
+            (movetime, clock) => movetime.or(clock),
+        };
+        let budget = if infinite { None } else { budget };
+
+        let unlimited = MAX_PLY as u32 - 1;
+        let params = SearchParams {
+            max_depth: limits.depth.unwrap_or(unlimited).min(limits.mate.map_or(unlimited, |moves| moves * 2)),
+            nodes: limits.nodes,
+            mate: limits.mate,
+            multipv: self.options.multipv,
+            skill: self.options.effective_skill(),
+        };
+
+        let control = Arc::new(SearchControl::default());
+        if !ponder {
+            *control.deadline.lock().unwrap() = budget.map(|budget| Instant::now() + budget);
+        }
+        let hold = Arc::new(AtomicBool::new(infinite || ponder));
+
+        let mut searcher = self.searcher.take().unwrap_or_else(|| Searcher::new(self.options.hash_mb));
+        let board = self.board.clone();
+        let history = self.history.clone();
+        let output = self.output.clone();
+        let (thread_control, thread_hold) = (Arc::clone(&control), Arc::clone(&hold));
+        let handle = thread::spawn(move || {
+            let mut report = |line: String| {
+                let _ = output.send(line);
+            };
+            let (best, ponder_move) = searcher.think(&board, &history, &params, &thread_control, &mut report);
+
+            // UCI forbids answering a ponder or infinite search before it is released
+            while thread_hold.load(Ordering::Relaxed) && !thread_control.stop.load(Ordering::Relaxed) {
+                thread::sleep(Duration::from_millis(5));
+            }
+            let answer = match (best, ponder_move) {
+                (Some(best), Some(ponder_move)) => format!("bestmove {} ponder {}", best.to_uci(), ponder_move.to_uci()),
+                (Some(best), None) => format!("bestmove {}", best.to_uci()),
+                (None, _) => "bestmove (none)".to_string(),
+            };
+            let _ = output.send(answer);
+            searcher
+        });
+
+        self.running = Some(RunningSearch {
+            control,
+            hold,
+            ponder_budget: if ponder { budget } else { None },
+            handle,
+        });
+    }
+
+    /// The expected move was played: the ponder search keeps going, now
+    /// against the clock.
+    fn ponder_hit(&mut self) {
+        let Some(running) = &self.running else {
+            return;
+        };
+        *running.control.deadline.lock().unwrap() = running.ponder_budget.map(|budget| Instant::now() + budget);
+        running.hold.store(false, Ordering::Relaxed);
+    }
+
+    /// Stops the running search, if any, and waits for its `bestmove`.
+    fn finish_search(&mut self) {
+        let Some(running) = self.running.take() else {
+            return;
+        };
+        running.control.stop.store(true, Ordering::Relaxed);
+        match running.handle.join() {
+            Ok(searcher) => self.searcher = Some(searcher),
+            Err(_) => self.send("info string search thread panicked".to_string()),
+        }
+    }
+}
+
+fn next_number(tokens: &mut SplitWhitespace) -> Option<u64> {
+    tokens.next()?.parse().ok()
+}
@@ -0,0 +1,706 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+use rand::rngs::StdRng;
+use rand::{Rng, RngCore, SeedableRng};
+
+use super::eval::{evaluate, piece_index, piece_value};
+use crate::chess::{Board, ChessMove, PieceColor, PieceType, Position};
+
+/// Score for delivering mate right now; mate in n plies scores `MATE - n`.
+pub const MATE: i32 = 30_000;
+const INFINITY: i32 = 32_000;
+pub const MAX_PLY: usize = 64;
+/// Scores this close to `MATE` are mates rather than material.
+const MATE_BOUND: i32 = MATE - MAX_PLY as i32;
+
+/// Nodes searched between looks at the clock and the stop flag.
+const CHECK_INTERVAL: u64 = 256;
+
+/// Random keys identifying positions in the transposition table.
+struct ZobristKeys {
+    pieces: [[u64; 64]; 12],
+    black_to_move: u64,
+    castling: [u64; 4],
+    en_passant: [u64; 8],
+}
+
+fn zobrist() -> &'static ZobristKeys {
+    static KEYS: OnceLock<ZobristKeys> = OnceLock::new();
+    KEYS.get_or_init(|| {
+        // Fixed seed, so hashes are the same from run to run
+        let mut rng = StdRng::seed_from_u64(0x2545_F491_4F6C_DD1D);
+        let mut next = || rng.next_u64();
+        ZobristKeys {
+            pieces: std::array::from_fn(|_| std::array::from_fn(|_| next())),
+            black_to_move: next(),
+            castling: std::array::from_fn(|_| next()),
+            en_passant: std::array::from_fn(|_| next()),
+        }
+    })
+}
+
+/// Zobrist hash of everything that makes two positions the same for
+/// repetition purposes.
+pub fn position_hash(board: &Board) -> u64 {
+    let keys = zobrist();
+    let mut hash = 0;
+    for rank in 0..8 {
+        for file in 0..8 {
+            if let Some(piece) = board.get_piece(Position::new(rank, file)) {
+                let index = piece_index(piece.piece_type) + 6 * (piece.color == PieceColor::Black) as usize;
+                hash ^= keys.pieces[index][rank * 8 + file];
+            }
+        }
+    }
+    if board.current_turn() == PieceColor::Black {
+        hash ^= keys.black_to_move;
+    }
+    let castling = board.castling_rights();
+    let rights = [castling.white_kingside, castling.white_queenside, castling.black_kingside, castling.black_queenside];
+    for (key, _) in keys.castling.iter().zip(rights).filter(|(_, allowed)| *allowed) {
+        hash ^= key;
+    }
+    if let Some(target) = board.en_passant_target() {
+        hash ^= keys.en_passant[target.file];
+    }
+    hash
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Bound {
+    Exact,
+    /// The score is at least this much (the search failed high)
+    Lower,
+    /// The score is at most this much (no move raised alpha)
+    Upper,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct TableEntry {
+    key: u64,
+    best: Option<ChessMove>,
+    score: i32,
+    depth: i32,
+    bound: Bound,
+}
+
+/// Results of earlier searches by position, so transpositions and later
+/// iterations don't repeat work. Newer entries always replace older ones.
+pub struct TranspositionTable {
+    entries: Vec<Option<TableEntry>>,
+}
+
+impl TranspositionTable {
+    pub fn new(megabytes: usize) -> Self {
+        let count = (megabytes.max(1) << 20) / std::mem::size_of::<Option<TableEntry>>();
+        Self { entries: vec![None; count] }
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.fill(None);
+    }
+
+    fn slot(&self, key: u64) -> usize {
+        (key % self.entries.len() as u64) as usize
+    }
+
+    fn probe(&self, key: u64) -> Option<TableEntry> {
+        self.entries[self.slot(key)].filter(|entry| entry.key == key)
+    }
+
+    fn store(&mut self, entry: TableEntry) {
+        let slot = self.slot(entry.key);
+        self.entries[slot] = Some(entry);
+    }
+}
+
+/// Mate scores are stored relative to the node, not the root, so they stay
+/// right when the position turns up at another distance from the root.
+fn score_to_table(score: i32, ply: usize) -> i32 {
+    if score > MATE_BOUND {
+        score + ply as i32
+    } else if score < -MATE_BOUND {
+        score - ply as i32
+    } else {
+        score
+    }
+}
+
+fn score_from_table(score: i32, ply: usize) -> i32 {
+    if score > MATE_BOUND {
+        score - ply as i32
+    } else if score < -MATE_BOUND {
+        score + ply as i32
+    } else {
+        score
+    }
+}
+
+/// A score in UCI terms: `cp <n>` or `mate <moves>`.
+pub fn uci_score(score: i32) -> String {
+    if score > MATE_BOUND {
+        format!("mate {}", (MATE - score + 1) / 2)
+    } else if score < -MATE_BOUND {
+        format!("mate -{}", (MATE + score) / 2)
+    } else {
+        format!("cp {}", score)
+    }
+}
+
+/// Shared between a running search and the thread that started it.
+#[derive(Default)]
+pub struct SearchControl {
+    pub stop: AtomicBool,
+    /// When the search must end; `None` while pondering or searching
+    /// without a time limit
+    pub deadline: Mutex<Option<Instant>>,
+}
+
+pub struct SearchParams {
+    pub max_depth: u32,
+    pub nodes: Option<u64>,
+    /// Stop once a mate in this many moves is found
+    pub mate: Option<u32>,
+    /// Lines to report
+    pub multipv: usize,
+    /// 0 plays random moves, 20 is full strength
+    pub skill: u8,
+}
+
+/// A root move with its score and the line the search expects after it.
+#[derive(Debug, Clone)]
+pub struct Line {
+    pub score: i32,
+    pub moves: Vec<ChessMove>,
+}
+
+pub struct Searcher {
+    table: TranspositionTable,
+    // Quiet moves that caused a cutoff, two per ply
+    killers: [[Option<ChessMove>; 2]; MAX_PLY],
+    // Hashes of the game so far and the current search path, for repetitions
+    path: Vec<u64>,
+    nodes: u64,
+    node_limit: Option<u64>,
+    stopped: bool,
+    deadline: Option<Instant>,
+}
+
+impl Searcher {
+    pub fn new(hash_megabytes: usize) -> Self {
+        Self {
+            table: TranspositionTable::new(hash_megabytes),
+            killers: [[None; 2]; MAX_PLY],
+            path: Vec::new(),
+            nodes: 0,
+            node_limit: None,
+            stopped: false,
+            deadline: None,
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.table.clear();
+    }
+
+    /// Iterative deepening from `board`, reporting each finished iteration
+    /// as UCI `info` lines through `report`. `history` holds the hashes of
+    /// the positions played before `board`. Returns the move to play and
+    /// the reply the engine expects.
+    pub fn think(
+        &mut self,
+        board: &Board,
+        history: &[u64],
+        params: &SearchParams,
+        control: &SearchControl,
+        report: &mut dyn FnMut(String),
+    ) -> (Option<ChessMove>, Option<ChessMove>) {
+        let root_moves = board.legal_moves();
+        if root_moves.is_empty() {
+            return (None, None);
+        }
+        if params.skill == 0 {
+            let mv = root_moves[rand::thread_rng().gen_range(0..root_moves.len())];
+            return (Some(mv), None);
+        }
+
+        self.path = history.to_vec();
+        self.killers = [[None; 2]; MAX_PLY];
+        self.nodes = 0;
+        self.node_limit = params.nodes;
+        self.stopped = false;
+        let started = Instant::now();
+
+        // Weaker levels look at a few candidates and pick one with noise
+        let weakened = params.skill < 20;
+        let line_count = if weakened { params.multipv.max(4) } else { params.multipv }.min(root_moves.len());
+        let max_depth = if weakened { params.max_depth.min(1 + params.skill as u32 / 3) } else { params.max_depth };
+
+        let mut lines: Vec<Line> = Vec::new();
+        for depth in 1..=max_depth.min(MAX_PLY as u32 - 1) {
+            let iteration_started = Instant::now();
+            // Last iteration's best lines first
+            let mut order: Vec<ChessMove> = lines.iter().map(|line| line.moves[0]).collect();
+            let rest: Vec<ChessMove> = root_moves.iter().filter(|mv| !order.contains(mv)).copied().collect();
+            order.extend(rest);
+
+            let mut found: Vec<Line> = Vec::new();
+            while found.len() < line_count {
+                let candidates: Vec<ChessMove> = order
+                    .iter()
+                    .filter(|mv| !found.iter().any(|line| line.moves[0] == **mv))
+                    .copied()
+                    .collect();
+                match self.search_root(board, depth as i32, &candidates, control) {
+                    Some(line) => found.push(line),
+                    None => break,
+                }
+            }
+
+            // A partly searched iteration is only used if there is nothing better
+            if (found.len() == line_count || lines.is_empty()) && !found.is_empty() {
+                lines = found;
+                lines.sort_by_key(|line| std::cmp::Reverse(line.score));
+            }
+            if self.stopped {
+                break;
+            }
+
+            let elapsed = started.elapsed();
+            let nps = (self.nodes as f64 / elapsed.as_secs_f64().max(0.001)) as u64;
+            for (index, line) in lines.iter().take(params.multipv).enumerate() {
+                let pv: Vec<String> = line.moves.iter().map(|mv| mv.to_uci()).collect();
+                report(format!(
+                    "info depth {} multipv {} score {} nodes {} nps {} time {} pv {}",
+                    depth,
+                    index + 1,
+                    uci_score(line.score),
+                    self.nodes,
+                    nps,
+                    elapsed.as_millis(),
+                    pv.join(" ")
+                ));
+            }
+
+            let best = lines[0].score;
+            if let Some(moves) = params.mate && best > MATE_BOUND && (MATE - best + 1) / 2 <= moves as i32 {
+                break;
+            }
+            // The next iteration would most likely not finish in time
+            let deadline = *control.deadline.lock().unwrap();
+            if deadline.is_some_and(|deadline| Instant::now() + iteration_started.elapsed() * 2 > deadline) {
+                break;
+            }
+        }
+
+        let Some(chosen) = self.choose(&lines, params.skill) else {
+            return (Some(root_moves[0]), None);
+        };
+        (chosen.moves.first().copied(), chosen.moves.get(1).copied())
+    }
+
+    /// The best line, or at reduced skill one of the top lines picked with
+    /// random noise added to their scores.
+    fn choose<'a>(&self, lines: &'a [Line], skill: u8) -> Option<&'a Line> {
+        if skill >= 20 {
+            return lines.first();
+        }
+        let noise = (20 - skill as i32) * 15;
+        let mut rng = rand::thread_rng();
+        lines.iter().max_by_key(|line| line.score + rng.gen_range(0..=noise))
+    }
+
+    /// Full-window search over `candidates`, returning the best of them with
+    /// its principal variation. `None` if the search was stopped first.
+    fn search_root(&mut self, board: &Board, depth: i32, candidates: &[ChessMove], control: &SearchControl) -> Option<Line> {
+        if candidates.is_empty() {
+            return None;
+        }
+        self.deadline = *control.deadline.lock().unwrap();
+        let key = position_hash(board);
+        let mut alpha = -INFINITY;
+        let mut best: Option<(i32, ChessMove)> = None;
+
+        self.path.push(key);
+        for (index, &mv) in candidates.iter().enumerate() {
+            let mut child = board.clone();
+            child.make_move(mv);
+            let mut score = if index == 0 {
+                -self.negamax(&child, depth - 1, -INFINITY, -alpha, 1, control)
+            } else {
+                -self.negamax(&child, depth - 1, -alpha - 1, -alpha, 1, control)
+            };
+            if index > 0 && score > alpha && !self.stopped {
+                score = -self.negamax(&child, depth - 1, -INFINITY, -alpha, 1, control);
+            }
+            if self.stopped {
+                break;
+            }
+            if score > alpha {
+                alpha = score;
+                best = Some((score, mv));
+            }
+        }
+        self.path.pop();
+
+        if self.stopped {
+            return None;
+        }
+        let (score, mv) = best?;
+        let mut moves = vec![mv];
+        let mut position = board.clone();
+        position.make_move(mv);
+        self.extend_pv(&mut position, &mut moves, depth as usize);
+        Some(Line { score, moves })
+    }
+
+    /// Follows best moves in the transposition table for the rest of a line.
+    fn extend_pv(&self, board: &mut Board, moves: &mut Vec<ChessMove>, max_len: usize) {
+        let mut seen = Vec::new();
+        while moves.len() < max_len.max(1) {
+            let key = position_hash(board);
+            if seen.contains(&key) {
+                break;
+            }
+            seen.push(key);
+            let Some(mv) = self.table.probe(key).and_then(|entry| entry.best) else {
+                break;
+            };
+            if !board.legal_moves().contains(&mv) {
+                break;
+            }
+            board.make_move(mv);
+            moves.push(mv);
+        }
+    }
+
+    fn check_limits(&mut self, control: &SearchControl) {
+        if !self.nodes.is_multiple_of(CHECK_INTERVAL) {
+            return;
+        }
+        if control.stop.load(Ordering::Relaxed)
+            || self.node_limit.is_some_and(|limit| self.nodes >= limit)
+            || self.deadline.is_some_and(|deadline| Instant::now() >= deadline)
+        {
+            self.stopped = true;
+        }
+        // A ponder search gets its deadline only once the move is played
+        if self.deadline.is_none() {
+            self.deadline = *control.deadline.lock().unwrap();
+        }
+    }
+
+    fn is_draw(&self, board: &Board, key: u64) -> bool {
+        if board.halfmove_clock() >= 100 || board.is_insufficient_material() {
+            return true;
+        }
+        // Only positions since the last capture or pawn move can repeat,
+        // and only every other one has the same side to move
+        self.path
+            .iter()
+            .rev()
+            .take(board.halfmove_clock())
+            .skip(1)
+            .step_by(2)
+            .any(|&earlier| earlier == key)
+    }
+
+    fn negamax(&mut self, board: &Board, mut depth: i32, mut alpha: i32, mut beta: i32, ply: usize, control: &SearchControl) -> i32 {
+        self.nodes += 1;
+        self.check_limits(control);
+        if self.stopped {
+            return 0;
+        }
+
+        let key = position_hash(board);
+        if self.is_draw(board, key) {
+            return 0;
+        }
+        // No line from here can beat a mate already found closer to the root
+        alpha = alpha.max(-MATE + ply as i32);
+        beta = beta.min(MATE - ply as i32 - 1);
+        if alpha >= beta {
+            return alpha;
+        }
+
+        let in_check = board.is_in_check(board.current_turn());
+        if in_check {
+            depth += 1;
+        }
+        if depth <= 0 {
+            return self.quiesce(board, alpha, beta, ply, control);
+        }
+        if ply >= MAX_PLY - 1 {
+            return evaluate(board);
+        }
+
+        let entry = self.table.probe(key);
+        if let Some(entry) = entry && entry.depth >= depth {
+            let score = score_from_table(entry.score, ply);
+            let usable = match entry.bound {
+                Bound::Exact => true,
+                Bound::Lower => score >= beta,
+                Bound::Upper => score <= alpha,
+            };
+            if usable {
+                return score;
+            }
+        }
+
+        let mut moves = board.legal_moves();
+        if moves.is_empty() {
+            return if in_check { -MATE + ply as i32 } else { 0 };
+        }
+        self.order_moves(board, &mut moves, entry.and_then(|entry| entry.best), ply);
+
+        let original_alpha = alpha;
+        let mut best_score = -INFINITY;
+        let mut best_move = None;
+        self.path.push(key);
+        for (index, &mv) in moves.iter().enumerate() {
+            let quiet = !is_capture(board, mv) && mv.promotion.is_none();
+            let mut child = board.clone();
+            child.make_move(mv);
+
+            let score = if index == 0 {
+                -self.negamax(&child, depth - 1, -beta, -alpha, ply + 1, control)
+            } else {
+                // Late quiet moves are searched shallower first, and all but
+                // the first move with a null window, re-searching only if
+                // they turn out better than expected
+                let gives_check = child.is_in_check(child.current_turn());
+                let reduction = if depth >= 3 && index >= 4 && quiet && !in_check && !gives_check { 1 } else { 0 };
+                let mut score = -self.negamax(&child, depth - 1 - reduction, -alpha - 1, -alpha, ply + 1, control);
+                if score > alpha && (reduction > 0 || score < beta) {
+                    score = -self.negamax(&child, depth - 1, -beta, -alpha, ply + 1, control);
+                }
+                score
+            };
+            if self.stopped {
+                self.path.pop();
+                return 0;
+            }
+
+            if score > best_score {
+                best_score = score;
+                best_move = Some(mv);
+            }
+            alpha = alpha.max(score);
+            if alpha >= beta {
+                if quiet && self.killers[ply][0] != Some(mv) {
+                    self.killers[ply] = [Some(mv), self.killers[ply][0]];
+                }
+                break;
+            }
+        }
+        self.path.pop();
+
+        let bound = if best_score <= original_alpha {
+            Bound::Upper
+        } else if best_score >= beta {
+            Bound::Lower
+        } else {
+            Bound::Exact
+        };
+        self.table.store(TableEntry {
+            key,
+            best: best_move,
+            score: score_to_table(best_score, ply),
+            depth,
+            bound,
+        });
+        best_score
+    }
+
+    /// Searches captures and promotions only, until the position is quiet
+    /// enough for the static evaluation to be trusted. In check every
+    /// evasion is tried, so mates aren't missed.
+    fn quiesce(&mut self, board: &Board, mut alpha: i32, beta: i32, ply: usize, control: &SearchControl) -> i32 {
+        self.nodes += 1;
+        self.check_limits(control);
+        if self.stopped {
+            return 0;
+        }
+
+        let in_check = board.is_in_check(board.current_turn());
+        if !in_check {
+            let stand_pat = evaluate(board);
+            if stand_pat >= beta || ply >= MAX_PLY - 1 {
+                return stand_pat;
+            }
+            alpha = alpha.max(stand_pat);
+        }
+
+        let mut moves = board.legal_moves();
+        if moves.is_empty() {
+            return if in_check { -MATE + ply as i32 } else { 0 };
+        }
+        if !in_check {
+            moves.retain(|&mv| is_capture(board, mv) || mv.promotion == Some(PieceType::Queen));
+        }
+        self.order_moves(board, &mut moves, None, ply);
+
+        let mut best_score = if in_check { -INFINITY } else { alpha };
+        for mv in moves {
+            let mut child = board.clone();
+            child.make_move(mv);
+            let score = -self.quiesce(&child, -beta, -alpha, ply + 1, control);
+            if self.stopped {
+                return 0;
+            }
+            best_score = best_score.max(score);
+            alpha = alpha.max(score);
+            if alpha >= beta {
+                break;
+            }
+        }
+        best_score
+    }
+
+    /// Table move first, then captures by most valuable victim and least
+    /// valuable attacker, then killer moves, then the rest.
+    fn order_moves(&self, board: &Board, moves: &mut [ChessMove], table_move: Option<ChessMove>, ply: usize) {
+        let killers = self.killers.get(ply).copied().unwrap_or_default();
+        moves.sort_by_cached_key(|&mv| {
+            if Some(mv) == table_move {
+                return i32::MIN;
+            }
+            let mut key = 0;
+            if is_capture(board, mv) {
+                let victim = board.get_piece(mv.to).map_or(PieceType::Pawn, |piece| piece.piece_type);
+                let attacker = board.get_piece(mv.from).map_or(PieceType::Pawn, |piece| piece.piece_type);
+                key -= 100_000 + piece_value(victim) * 10 - piece_value(attacker) / 10;
+            } else if killers.contains(&Some(mv)) {
+                key -= 50_000;
+            }
+            if let Some(promotion) = mv.promotion {
+                key -= piece_value(promotion);
+            }
+            key
+        });
+    }
+}
+
+/// Whether `mv` takes a piece, counting en passant.
+fn is_capture(board: &Board, mv: ChessMove) -> bool {
+    board.get_piece(mv.to).is_some()
+        || (mv.from.file != mv.to.file && board.get_piece(mv.from).is_some_and(|piece| piece.piece_type == PieceType::Pawn))
+}
+
+/// How long to think with `time_left` on the clock, `increment` added per
+/// move and, if known, `moves_to_go` until the next time control.
+pub fn allocate_time(time_left: Duration, increment: Duration, moves_to_go: Option<u32>) -> Duration {
+    let moves = moves_to_go.unwrap_or(30).clamp(1, 30);
+    let share = time_left / moves + increment * 3 / 4;
+    // Always keep a little in hand for the overhead of sending the move
+    let safety = Duration::from_millis(50);
+    share.min(time_left.saturating_sub(safety) / 2).max(Duration::from_millis(10))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn board(fen: &str) -> Board {
+        Board::from_fen(fen).unwrap()
+    }
+
+    fn best_move(fen: &str, depth: u32) -> (ChessMove, Vec<String>) {
+        let params = SearchParams { max_depth: depth, nodes: None, mate: None, multipv: 1, skill: 20 };
+        let mut info = Vec::new();
+        let (mv, _) = Searcher::new(1).think(&board(fen), &[], &params, &SearchControl::default(), &mut |line| info.push(line));
+        (mv.expect("a move in a position with legal moves"), info)
+    }
+
+    fn queen_hangs(board: &Board, color: PieceColor) -> bool {
+        board.legal_moves().iter().any(|mv| {
+            board.get_piece(mv.to).is_some_and(|piece| piece.color == color && piece.piece_type == PieceType::Queen)
+        })
+    }
+
+    #[test]
+    fn finds_mate_in_one() {
+        let (mv, info) = best_move("6k1/5ppp/8/8/8/8/5PPP/R5K1 w - - 0 1", 3);
+        assert_eq!(mv.to_uci(), "a1a8");
+        assert!(info.last().unwrap().contains("score mate 1"));
+    }
+
+    #[test]
+    fn does_not_leave_the_queen_hanging() {
+        // Attacked by a pawn, and then by a knight, with nothing to gain by ignoring it
+        for fen in ["4k3/8/8/4p3/3Q4/8/8/4K3 w - - 0 1", "4k3/8/2n5/8/3Q4/8/8/4K3 w - - 0 1"] {
+            let (mv, _) = best_move(fen, 2);
+            let mut after = board(fen);
+            after.make_move(mv);
+            assert!(!queen_hangs(&after, PieceColor::White), "{fen}: {}", mv.to_uci());
+        }
+    }
+
+    #[test]
+    fn takes_a_hanging_queen() {
+        let (mv, _) = best_move("4k3/8/8/3q4/4P3/8/8/4K3 w - - 0 1", 2);
+        assert_eq!(mv.to_uci(), "e4d5");
+    }
+
+    #[test]
+    fn stalemate_scores_zero() {
+        let stalemate = board("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1");
+        let score = Searcher::new(1).negamax(&stalemate, 3, -INFINITY, INFINITY, 0, &SearchControl::default());
+        assert_eq!(score, 0);
+    }
+
+    #[test]
+    fn repetition_scores_zero_even_when_losing() {
+        // White is a queen down, but the position already occurred two plies ago
+        let position = board("3qk3/8/8/8/8/8/8/4K3 w - - 4 3");
+        let mut searcher = Searcher::new(1);
+        searcher.path = vec![position_hash(&position), 1, 2, 3];
+        let score = searcher.negamax(&position, 3, -INFINITY, INFINITY, 1, &SearchControl::default());
+        assert_eq!(score, 0);
+
+        searcher.path.clear();
+        let score = searcher.negamax(&position, 3, -INFINITY, INFINITY, 1, &SearchControl::default());
+        assert!(score < -500);
+    }
+
+    #[test]
+    fn every_move_is_legal() {
+        let fens = [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+            "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+            "4k3/8/8/8/8/8/4p3/4K3 w - - 0 1",
+            "rnb1kbnr/pppp1ppp/8/4p3/7q/5P2/PPPPP1PP/RNBQKBNR w KQkq - 1 2",
+            "8/P7/8/8/8/8/6k1/4K3 w - - 0 1",
+            "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3",
+        ];
+        for fen in fens {
+            for depth in [1, 3] {
+                let (mv, _) = best_move(fen, depth);
+                assert!(board(fen).legal_moves().contains(&mv), "{fen}: {}", mv.to_uci());
+            }
+        }
+    }
+
+    #[test]
+    fn time_allocation_stays_within_the_clock() {
+        let ms = Duration::from_millis;
+        for time_left in [ms(100), ms(1_000), ms(30_000), ms(600_000)] {
+            for increment in [ms(0), ms(2_000), ms(30_000)] {
+                for moves_to_go in [None, Some(1), Some(5), Some(40)] {
+                    let think = allocate_time(time_left, increment, moves_to_go);
+                    assert!(think >= ms(10));
+                    assert!(think <= (time_left / 2).max(ms(10)), "{time_left:?} {increment:?} {moves_to_go:?}: {think:?}");
+                }
+            }
+        }
+        // Without an increment the clock is shared over the moves left
+        assert_eq!(allocate_time(ms(60_000), ms(0), Some(20)), ms(3_000));
+        assert_eq!(allocate_time(ms(60_000), ms(0), None), ms(2_000));
+        // Nearly out of time it still thinks for a moment
+        assert_eq!(allocate_time(ms(20), ms(0), None), ms(10));
+    }
+}
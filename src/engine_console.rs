@@ -0,0 +1,104 @@
+use std::path::Path;
+use std::time::Duration;
+
+use egui::{Color32, RichText};
+use crate::stockfish::{LogDirection, Stockfish, UciLog};
+
+pub const LOG_FILE_PATH: &str = "engine_log.txt";
+
+/// Raw view of the UCI exchange, with a prompt for sending commands by hand.
+#[derive(Default)]
+pub struct EngineConsole {
+    pub open: bool,
+    /// Shown as a panel along the bottom edge instead of a floating window
+    pub docked: bool,
+    input: String,
+    hide_info: bool,
+    status: Option<String>,
+}
+
+impl EngineConsole {
+    /// `busy_log` is the log of an engine that is searching on another
+    /// thread, which can be watched but not sent commands.
+    pub fn show(&mut self, ctx: &egui::Context, stockfish: Option<&mut Stockfish>, busy_log: Option<&UciLog>) {
+        if !self.open {
+            return;
+        }
+        // Output keeps arriving while the engine thinks in the background
+        ctx.request_repaint_after(Duration::from_millis(250));
+
+        if self.docked {
+            egui::TopBottomPanel::bottom("engine_console")
+                .resizable(true)
+                .default_height(220.0)
+                .show(ctx, |ui| self.draw(ui, stockfish, busy_log));
+        } else {
+            let mut open = self.open;
+            egui::Window::new("Engine console")
+                .open(&mut open)
+                .default_size([560.0, 360.0])
+                .show(ctx, |ui| self.draw(ui, stockfish, busy_log));
+            self.open &= open;
+        }
+    }
+
+    fn draw(&mut self, ui: &mut egui::Ui, stockfish: Option<&mut Stockfish>, busy_log: Option<&UciLog>) {
+        let Some(log) = stockfish.as_ref().map(|stockfish| stockfish.log()).or(busy_log).cloned() else {
+            ui.label("No engine running.");
+            return;
+        };
+
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut self.docked, "Dock");
+            ui.checkbox(&mut self.hide_info, "Hide info lines");
+            if ui.button("Clear").clicked() {
+                log.clear();
+            }
+            if ui.button("Save to file").clicked() {
+                self.status = Some(match log.save(Path::new(LOG_FILE_PATH)) {
+                    Ok(()) => format!("Saved to {}", LOG_FILE_PATH),
+                    Err(err) => format!("Failed to save {}: {}", LOG_FILE_PATH, err),
+                });
+            }
+            if let Some(status) = &self.status {
+                ui.label(status);
+            }
+        });
+
+        ui.horizontal(|ui| {
+            let Some(stockfish) = stockfish else {
+                ui.label("The engine is thinking; commands can be sent once it has moved.");
+                return;
+            };
+            let response = ui.add(
+                egui::TextEdit::singleline(&mut self.input)
+                    .hint_text("UCI command, e.g. \"d\" or \"eval\"")
+                    .desired_width(ui.available_width() - 60.0),
+            );
+            let submitted = response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+            if (ui.button("Send").clicked() || submitted) && !self.input.trim().is_empty() {
+                stockfish.send_raw(self.input.trim());
+                self.input.clear();
+                response.request_focus();
+            }
+        });
+        ui.separator();
+
+        egui::ScrollArea::vertical()
+            .auto_shrink([false, false])
+            .stick_to_bottom(true)
+            .show(ui, |ui| {
+                for entry in log.entries() {
+                    if self.hide_info && entry.direction == LogDirection::Received && entry.line.starts_with("info") {
+                        continue;
+                    }
+                    let color = match entry.direction {
+                        LogDirection::Sent => Color32::from_rgb(70, 130, 220),
+                        LogDirection::Received => ui.visuals().text_color(),
+                        LogDirection::Stderr => Color32::from_rgb(220, 80, 60),
+                    };
+                    ui.label(RichText::new(entry.to_string()).monospace().color(color));
+                }
+            });
+    }
+}
@@ -0,0 +1,241 @@
+use std::io;
+use std::sync::mpsc::TryRecvError;
+use std::time::{Duration, Instant};
+
+use crate::chess::{format_numbered_line, Board, ChessMove, Game, PieceColor};
+use crate::difficulty::DifficultyProfile;
+use crate::engine_options::{self, SavedOptions};
+use crate::game_mode::Difficulty;
+use crate::stockfish::{BackgroundSearch, EngineEvent, Score, Stockfish};
+
+/// Games still going after this many plies are scored as draws. Repetitions,
+/// the 50-move rule and dead positions end most drawn games long before.
+pub const MAX_PLIES: usize = 400;
+
+/// Choices on the match setup screen.
+pub struct MatchSetup {
+    /// Names of the difficulty profiles for engines A and B, which stay
+    /// right when other profiles are added or deleted
+    pub profiles: [String; 2],
+    pub games: u32,
+    pub delay_ms: u64,
+}
+
+impl Default for MatchSetup {
+    fn default() -> Self {
+        Self {
+            profiles: [Difficulty::Easy.to_string(), Difficulty::Hard.to_string()],
+            games: 2,
+            delay_ms: 500,
+        }
+    }
+}
+
+impl MatchSetup {
+    /// The profile chosen for `side` (0 for engine A), or Medium's if it
+    /// has been deleted since.
+    pub fn profile<'a>(&self, side: usize, profiles: &'a [DifficultyProfile]) -> &'a DifficultyProfile {
+        profiles
+            .iter()
+            .find(|profile| profile.name == self.profiles[side])
+            .unwrap_or(&profiles[Difficulty::Medium as usize])
+    }
+}
+
+/// One of the two engines in a match.
+pub struct MatchSide {
+    pub profile: DifficultyProfile,
+    // None while the engine searches on its own thread
+    engine: Option<Stockfish>,
+    /// Principal variation of this side's last search, in numbered SAN
+    pub pv: String,
+    /// Score of that search, from White's point of view
+    pub score: Option<Score>,
+    pub points: f32,
+}
+
+/// A series of games between engines A and B, with colors alternating:
+/// A has White in the first game.
+pub struct EngineMatch {
+    pub sides: [MatchSide; 2],
+    pub games: u32,
+    pub delay_ms: u64,
+    pub paused: bool,
+    step_requested: bool,
+    // Side with White in the game on the board
+    white: usize,
+    last_move: Instant,
+    // The side to move's search, with that side and the position searched
+    search: Option<(usize, Board, BackgroundSearch)>,
+    // Searches that never came back
+    events: Vec<EngineEvent>,
+    /// Result of each finished game, from White's point of view
+    pub results: Vec<String>,
+}
+
+impl EngineMatch {
+    pub fn start(engine_path: &str, options: &SavedOptions, profiles: [DifficultyProfile; 2], setup: &MatchSetup) -> io::Result<Self> {
+        let [a, b] = profiles;
+        let side = |profile: DifficultyProfile| -> io::Result<MatchSide> {
+            let mut engine = Stockfish::new(engine_path)?;
+            engine_options::apply_saved(&mut engine, options);
+            engine.set_strength(profile.strength);
+            Ok(MatchSide { profile, engine: Some(engine), pv: String::new(), score: None, points: 0.0 })
+        };
+
+        Ok(Self {
+            sides: [side(a)?, side(b)?],
+            games: setup.games.max(1),
+            delay_ms: setup.delay_ms,
+            paused: false,
+            step_requested: false,
+            white: 0,
+            last_move: Instant::now(),
+            search: None,
+            events: Vec::new(),
+            results: Vec::new(),
+        })
+    }
+
+    /// Index of the side playing White in the current game.
+    pub fn white_side(&self) -> usize {
+        self.white
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.results.len() >= self.games as usize
+    }
+
+    pub fn delay(&self) -> Duration {
+        Duration::from_millis(self.delay_ms)
+    }
+
+    /// Plays a single move while paused.
+    pub fn step(&mut self) {
+        self.step_requested = true;
+    }
+
+    /// Whether the next move (or the next game) should start now.
+    pub fn move_due(&self) -> bool {
+        self.step_requested || (!self.paused && self.last_move.elapsed() >= self.delay())
+    }
+
+    /// Whether the side to move is still thinking.
+    pub fn is_searching(&self) -> bool {
+        self.search.is_some()
+    }
+
+    /// Starts the side to move's search on a thread of its own, so the
+    /// match controls stay responsive. Its move is picked up by
+    /// [`EngineMatch::poll_move`].
+    pub fn start_move(&mut self, game: &Game) {
+        self.step_requested = false;
+        self.last_move = Instant::now();
+
+        let board = game.board().clone();
+        let index = match board.current_turn() {
+            PieceColor::White => self.white_side(),
+            PieceColor::Black => 1 - self.white_side(),
+        };
+        let side = &mut self.sides[index];
+        let Some(engine) = side.engine.take() else {
+            return;
+        };
+        // Both engines may share a profile, so strength is set every move
+        let strength = side.profile.strength;
+        let limits = side.profile.search_limits();
+        let start_fen = game.start_board().to_fen();
+        let moves = game.moves().to_vec();
+        let search = BackgroundSearch::spawn(engine, move |engine| {
+            engine.set_strength(strength);
+            engine.set_position_with_moves(&start_fen, &moves);
+            engine.get_best_move(&limits)
+        });
+        self.search = Some((index, board, search));
+    }
+
+    /// The searching side's move and its White-relative score once the
+    /// search is done, keeping its PV for display. `None` while it is still
+    /// thinking or if the engine failed to answer, in which case the move
+    /// is asked for again.
+    pub fn poll_move(&mut self) -> Option<(ChessMove, Option<Score>)> {
+        let (engine, best) = match self.search.as_ref()?.2.try_finish() {
+            Ok(result) => result,
+            Err(TryRecvError::Empty) => return None,
+            Err(TryRecvError::Disconnected) => {
+                // The thread took the engine with it
+                self.search = None;
+                self.events.push(EngineEvent::Failed("the engine thread stopped unexpectedly".to_string()));
+                return None;
+            }
+        };
+        let (index, board, _) = self.search.take()?;
+        self.last_move = Instant::now();
+        let turn = board.current_turn();
+        let side = &mut self.sides[index];
+        side.score = None;
+        if let Some(info) = engine.last_info() {
+            let sans = board.uci_line_to_san(&info.pv);
+            side.pv = format_numbered_line(&sans, board.fullmove_number(), turn == PieceColor::White);
+            side.score = Some(info.score.for_white(turn));
+        }
+        side.engine = Some(engine);
+        Some((board.find_legal_move(&best?)?, side.score))
+    }
+
+    /// Scores a finished game; unfinished results count as draws.
+    pub fn record_result(&mut self, result: &str) {
+        let white = self.white_side();
+        let (white_points, black_points) = match result {
+            "1-0" => (1.0, 0.0),
+            "0-1" => (0.0, 1.0),
+            _ => (0.5, 0.5),
+        };
+        self.sides[white].points += white_points;
+        self.sides[1 - white].points += black_points;
+        self.results.push(if result == "*" { "1/2-1/2".to_string() } else { result.to_string() });
+        self.last_move = Instant::now();
+    }
+
+    /// Clears the previous game's lines before the next one starts.
+    pub fn start_next_game(&mut self) {
+        self.white = self.results.len() % 2;
+        self.step_requested = false;
+        self.last_move = Instant::now();
+        for side in &mut self.sides {
+            side.pv.clear();
+            side.score = None;
+        }
+    }
+
+    /// Supervisor events from both engines. A searching engine's events
+    /// wait until it is back.
+    pub fn take_events(&mut self) -> Vec<EngineEvent> {
+        let mut events = std::mem::take(&mut self.events);
+        events.extend(self.sides.iter_mut().flat_map(|side| side.engine.as_mut()).flat_map(Stockfish::take_events));
+        events
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use strum::IntoEnumIterator;
+
+    use super::*;
+
+    #[test]
+    fn deleted_profile_falls_back_to_medium() {
+        let mut profiles: Vec<DifficultyProfile> = Difficulty::iter().map(Difficulty::profile).collect();
+        profiles.push(DifficultyProfile::new("Blitz"));
+        profiles.push(DifficultyProfile::new("Bullet"));
+        let mut setup = MatchSetup::default();
+        setup.profiles[0] = "Blitz".to_string();
+        setup.profiles[1] = "Bullet".to_string();
+        assert_eq!(setup.profile(0, &profiles).name, "Blitz");
+
+        // Deleting "Blitz" moves "Bullet" down a place
+        profiles.retain(|profile| profile.name != "Blitz");
+        assert_eq!(setup.profile(0, &profiles).name, Difficulty::Medium.to_string());
+        assert_eq!(setup.profile(1, &profiles).name, "Bullet");
+    }
+}
@@ -0,0 +1,254 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::stockfish::{Stockfish, UciOption, UciOptionKind};
+
+pub const ENGINE_OPTIONS_PATH: &str = "engine_options.cfg";
+
+/// Option values saved per engine, keyed by the engine's `id name`.
+pub type SavedOptions = BTreeMap<String, Vec<(String, String)>>;
+
+/// Reads saved engine options from an INI-style file, one section per engine:
+///
+/// ```text
+/// [Stockfish 16.1]
+/// Hash = 256
+/// Threads = 4
+/// ```
+///
+/// A missing file is not an error; nothing has been saved yet.
+pub fn load_engine_options(path: &Path) -> io::Result<SavedOptions> {
+    let text = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(SavedOptions::new()),
+        Err(err) => return Err(err),
+    };
+    Ok(parse_engine_options(&text))
+}
+
+pub fn parse_engine_options(text: &str) -> SavedOptions {
+    let mut saved = SavedOptions::new();
+    let mut engine: Option<String> = None;
+
+    for (number, line) in text.lines().enumerate() {
+        // Option values may legitimately contain '#', so only whole-line comments
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            let name = name.trim().to_string();
+            saved.entry(name.clone()).or_default();
+            engine = Some(name);
+            continue;
+        }
+
+        let (Some(engine), Some((name, value))) = (&engine, line.split_once('=')) else {
+            eprintln!("{}:{}: ignoring line outside an [engine] section", ENGINE_OPTIONS_PATH, number + 1);
+            continue;
+        };
+        saved
+            .entry(engine.clone())
+            .or_default()
+            .push((name.trim().to_string(), value.trim().to_string()));
+    }
+
+    saved
+}
+
+pub fn save_engine_options(path: &Path, saved: &SavedOptions) -> io::Result<()> {
+    let mut text = String::from("# Engine options, one section per engine\n");
+
+    for (engine, options) in saved {
+        text.push_str(&format!("\n[{}]\n", engine));
+        for (name, value) in options {
+            text.push_str(&format!("{} = {}\n", name, value));
+        }
+    }
+
+    fs::write(path, text)
+}
+
+/// Sends the values saved for this engine, returning a message for each one
+/// it rejected.
+pub fn apply_saved(stockfish: &mut Stockfish, saved: &SavedOptions) -> Vec<String> {
+    let Some(options) = saved.get(stockfish.engine_name()) else {
+        return Vec::new();
+    };
+    options
+        .clone()
+        .into_iter()
+        .filter_map(|(name, value)| stockfish.set_declared_option(&name, &value).err())
+        .collect()
+}
+
+/// Settings form generated from the options the engine declared.
+#[derive(Default)]
+pub struct OptionsEditor {
+    pub open: bool,
+    // Engine the draft was loaded from; a different engine reloads it
+    engine: String,
+    draft: BTreeMap<String, String>,
+    messages: Vec<String>,
+}
+
+impl OptionsEditor {
+    /// Returns true when new values were sent to the engine.
+    pub fn show(&mut self, ctx: &egui::Context, stockfish: Option<&mut Stockfish>, saved: &mut SavedOptions) -> bool {
+        if !self.open {
+            return false;
+        }
+
+        let mut open = self.open;
+        let mut applied = false;
+        egui::Window::new("Engine options")
+            .open(&mut open)
+            .default_size([380.0, 480.0])
+            .show(ctx, |ui| {
+                let Some(stockfish) = stockfish else {
+                    ui.label("Start a game against the engine to edit its options.");
+                    return;
+                };
+                if self.engine != stockfish.engine_name() {
+                    self.load_draft(stockfish);
+                }
+                applied = self.draw(ui, stockfish, saved);
+            });
+        self.open &= open;
+        applied
+    }
+
+    fn load_draft(&mut self, stockfish: &Stockfish) {
+        self.engine = stockfish.engine_name().to_string();
+        self.draft = stockfish
+            .declared_options()
+            .iter()
+            .filter_map(|option| Some((option.name.clone(), stockfish.option_value(&option.name)?)))
+            .collect();
+        self.messages.clear();
+    }
+
+    fn draw(&mut self, ui: &mut egui::Ui, stockfish: &mut Stockfish, saved: &mut SavedOptions) -> bool {
+        ui.label(format!("Engine: {}", stockfish.engine_name()));
+        if stockfish.declared_options().is_empty() {
+            ui.label("The engine declared no options.");
+            return false;
+        }
+        ui.separator();
+
+        let mut pressed = None;
+        egui::ScrollArea::vertical().max_height(360.0).show(ui, |ui| {
+            egui::Grid::new("engine_options_grid").num_columns(2).striped(true).show(ui, |ui| {
+                for option in stockfish.declared_options() {
+                    ui.label(&option.name);
+                    if let UciOptionKind::Button = option.kind {
+                        if ui.button("Run").clicked() {
+                            pressed = Some(option.name.clone());
+                        }
+                    } else if let Some(value) = self.draft.get_mut(&option.name) {
+                        option_widget(ui, option, value);
+                    }
+                    ui.end_row();
+                }
+            });
+        });
+        if let Some(name) = pressed {
+            stockfish.press_button(&name);
+        }
+
+        ui.separator();
+        let mut applied = false;
+        ui.horizontal(|ui| {
+            if ui.button("Apply").clicked() {
+                applied = self.apply(stockfish);
+            }
+            if ui.button("Save").on_hover_text(format!("Apply and remember in {}", ENGINE_OPTIONS_PATH)).clicked() {
+                applied = self.apply(stockfish);
+                self.save(stockfish, saved);
+            }
+            if ui.button("Defaults").clicked() {
+                for option in stockfish.declared_options() {
+                    if let (Some(value), Some(default)) = (self.draft.get_mut(&option.name), option.default_value()) {
+                        *value = default;
+                    }
+                }
+            }
+            if ui.button("Revert").clicked() {
+                self.load_draft(stockfish);
+            }
+        });
+
+        for message in &self.messages {
+            ui.colored_label(egui::Color32::from_rgb(220, 80, 60), message);
+        }
+        applied
+    }
+
+    /// Sends every changed value that passes validation.
+    fn apply(&mut self, stockfish: &mut Stockfish) -> bool {
+        self.messages.clear();
+        let mut applied = false;
+        for (name, value) in &self.draft {
+            if stockfish.option_value(name).as_deref() == Some(value.as_str()) {
+                continue;
+            }
+            match stockfish.set_declared_option(name, value) {
+                Ok(()) => applied = true,
+                Err(err) => self.messages.push(err),
+            }
+        }
+        applied
+    }
+
+    /// Remembers the values that differ from the engine's defaults.
+    fn save(&mut self, stockfish: &Stockfish, saved: &mut SavedOptions) {
+        let changed = stockfish
+            .declared_options()
+            .iter()
+            .filter_map(|option| {
+                let value = self.draft.get(&option.name)?;
+                let valid = option.validate(value).ok()?;
+                (Some(&valid) != option.default_value().as_ref()).then(|| (option.name.clone(), valid))
+            })
+            .collect();
+        saved.insert(self.engine.clone(), changed);
+
+        if let Err(err) = save_engine_options(Path::new(ENGINE_OPTIONS_PATH), saved) {
+            self.messages.push(format!("Failed to save {}: {}", ENGINE_OPTIONS_PATH, err));
+        }
+    }
+}
+
+/// Edits one option's value, kept as the text that would be sent.
+fn option_widget(ui: &mut egui::Ui, option: &UciOption, value: &mut String) {
+    match &option.kind {
+        UciOptionKind::Check { .. } => {
+            let mut checked = value == "true";
+            if ui.checkbox(&mut checked, "").changed() {
+                *value = checked.to_string();
+            }
+        }
+        UciOptionKind::Spin { default, min, max } => {
+            let mut number = value.parse().unwrap_or(*default);
+            if ui.add(egui::DragValue::new(&mut number).clamp_range(*min..=*max)).changed() {
+                *value = number.to_string();
+            }
+        }
+        UciOptionKind::Combo { vars, .. } => {
+            egui::ComboBox::from_id_source(&option.name)
+                .selected_text(value.as_str())
+                .show_ui(ui, |ui| {
+                    for var in vars {
+                        ui.selectable_value(value, var.clone(), var);
+                    }
+                });
+        }
+        UciOptionKind::String { .. } => {
+            ui.text_edit_singleline(value);
+        }
+        UciOptionKind::Button => {}
+    }
+}
@@ -0,0 +1,74 @@
+use egui::{Align2, Color32, FontId, Id, Pos2, Rect, Stroke, Vec2};
+use crate::stockfish::{Score, Wdl};
+
+pub const EVAL_BAR_WIDTH: f32 = 24.0;
+
+/// Expected score for White from a centipawn evaluation, in 0.0..=1.0.
+/// Same logistic curve Lichess uses for its eval bar.
+pub fn win_probability(cp: i32) -> f32 {
+    1.0 / (1.0 + (-0.003_682_08 * cp as f32).exp())
+}
+
+/// Portion of the bar that belongs to White, in 0.0..=1.0.
+pub fn white_share(score: Score) -> f32 {
+    match score {
+        Score::Cp(cp) => win_probability(cp),
+        Score::Mate(n) if n > 0 => 1.0,
+        Score::Mate(0) => 0.5,
+        Score::Mate(_) => 0.0,
+    }
+}
+
+/// Short label for a White-relative score: "+1.3", "-0.4", "M3", "-M2".
+pub fn score_label(score: Score) -> String {
+    match score {
+        Score::Cp(cp) => format!("{:+.1}", cp as f32 / 100.0),
+        Score::Mate(n) if n >= 0 => format!("M{}", n),
+        Score::Mate(n) => format!("-M{}", -n),
+    }
+}
+
+pub fn wdl_label(wdl: Wdl) -> String {
+    format!(
+        "W {:.1}%  D {:.1}%  L {:.1}%",
+        wdl.win as f32 / 10.0,
+        wdl.draw as f32 / 10.0,
+        wdl.loss as f32 / 10.0,
+    )
+}
+
+/// Draws a vertical evaluation bar. `score` must be from White's point of
+/// view; when `board_flipped` is false White sits at the bottom of the bar,
+/// matching the board.
+pub fn draw_eval_bar(ui: &egui::Ui, rect: Rect, score: Option<Score>, board_flipped: bool) {
+    let target = score.map(white_share).unwrap_or(0.5);
+    let share = ui.ctx().animate_value_with_time(Id::new("eval_bar"), target, 0.4);
+
+    let painter = ui.painter();
+    painter.rect_filled(rect, 2.0, Color32::from_gray(40));
+
+    let white_height = rect.height() * share;
+    let white_rect = if board_flipped {
+        Rect::from_min_size(rect.min, Vec2::new(rect.width(), white_height))
+    } else {
+        Rect::from_min_size(
+            Pos2::new(rect.left(), rect.bottom() - white_height),
+            Vec2::new(rect.width(), white_height),
+        )
+    };
+    painter.rect_filled(white_rect, 2.0, Color32::from_gray(235));
+    painter.rect_stroke(rect, 2.0, Stroke::new(1.0, Color32::DARK_GRAY));
+
+    if let Some(score) = score {
+        // Put the label at the end of the side that is ahead, inside its colour
+        let white_ahead = white_share(score) >= 0.5;
+        let at_bottom = white_ahead != board_flipped;
+        let (anchor, pos) = if at_bottom {
+            (Align2::CENTER_BOTTOM, rect.center_bottom() - Vec2::new(0.0, 4.0))
+        } else {
+            (Align2::CENTER_TOP, rect.center_top() + Vec2::new(0.0, 4.0))
+        };
+        let color = if white_ahead { Color32::from_gray(40) } else { Color32::from_gray(235) };
+        painter.text(pos, anchor, score_label(score), FontId::proportional(10.0), color);
+    }
+}
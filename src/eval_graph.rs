@@ -0,0 +1,93 @@
+use egui::{Color32, Pos2, Sense, Shape, Stroke, Vec2};
+use crate::stockfish::Score;
+
+/// Evaluations beyond this many pawns are drawn at the edge of the graph.
+pub const GRAPH_CLAMP: f32 = 10.0;
+
+/// A dot drawn on the graph, e.g. to flag a blunder.
+pub struct GraphMarker {
+    pub ply: usize,
+    pub color: Color32,
+}
+
+/// Graph height for a White-relative score: pawns clamped to
+/// ±[`GRAPH_CLAMP`], with mates pinned to the edges.
+pub fn graph_value(score: Score) -> f32 {
+    match score {
+        Score::Cp(cp) => (cp as f32 / 100.0).clamp(-GRAPH_CLAMP, GRAPH_CLAMP),
+        Score::Mate(n) if n > 0 => GRAPH_CLAMP,
+        Score::Mate(0) => 0.0,
+        Score::Mate(_) => -GRAPH_CLAMP,
+    }
+}
+
+/// Draws one point per position (`None` where no evaluation is known yet)
+/// and returns the ply the user clicked, if any.
+pub fn draw_eval_graph(
+    ui: &mut egui::Ui,
+    values: &[Option<f32>],
+    markers: &[GraphMarker],
+    current_ply: usize,
+) -> Option<usize> {
+    let size = Vec2::new(ui.available_width(), 120.0);
+    let (rect, response) = ui.allocate_exact_size(size, Sense::click_and_drag());
+    let painter = ui.painter_at(rect);
+
+    // White's half on top, Black's below
+    let (top, bottom) = rect.split_top_bottom_at_fraction(0.5);
+    painter.rect_filled(top, 0.0, Color32::from_gray(225));
+    painter.rect_filled(bottom, 0.0, Color32::from_gray(60));
+    painter.line_segment([rect.left_center(), rect.right_center()], Stroke::new(1.0, Color32::GRAY));
+
+    let last_ply = values.len().saturating_sub(1).max(1);
+    let x_for = |ply: usize| rect.left() + rect.width() * ply as f32 / last_ply as f32;
+    let y_for = |value: f32| rect.center().y - value / GRAPH_CLAMP * rect.height() / 2.0;
+    let point_for = |ply: usize, value: f32| Pos2::new(x_for(ply), y_for(value));
+
+    let x = x_for(current_ply.min(last_ply));
+    painter.line_segment([Pos2::new(x, rect.top()), Pos2::new(x, rect.bottom())], Stroke::new(1.0, Color32::from_rgb(70, 130, 220)));
+
+    // Separate polylines so unknown evaluations leave gaps
+    let mut segment = Vec::new();
+    for (ply, value) in values.iter().enumerate() {
+        match value {
+            Some(value) => segment.push(point_for(ply, *value)),
+            None => flush_segment(&painter, &mut segment),
+        }
+    }
+    flush_segment(&painter, &mut segment);
+
+    for marker in markers {
+        if let Some(Some(value)) = values.get(marker.ply) {
+            painter.circle_filled(point_for(marker.ply, *value), 3.5, marker.color);
+        }
+    }
+
+    let pointer = response.interact_pointer_pos().or(response.hover_pos())?;
+    let ply = (((pointer.x - rect.left()) / rect.width()) * last_ply as f32).round() as usize;
+    let ply = ply.min(values.len().saturating_sub(1));
+
+    if let Some(Some(value)) = values.get(ply) {
+        response.clone().on_hover_text(format!("Ply {}: {:+.1}", ply, value));
+    }
+
+    if response.clicked() || response.dragged() {
+        Some(ply)
+    } else {
+        None
+    }
+}
+
+fn flush_segment(painter: &egui::Painter, segment: &mut Vec<Pos2>) {
+    let color = Color32::from_rgb(200, 120, 40);
+    match segment.len() {
+        0 => {}
+        1 => {
+            painter.circle_filled(segment[0], 1.5, color);
+        }
+        _ => {
+            painter.add(Shape::line(segment.clone(), Stroke::new(2.0, color)));
+        }
+    }
+    segment.clear();
+}
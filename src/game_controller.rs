@@ -0,0 +1,482 @@
+//! Turn, clock and termination logic for one game, with no UI attached.
+//! A front end either feeds moves in itself or hands the controller two
+//! [`Player`]s and calls [`GameController::tick`]; either way it follows
+//! the game through [`GameEvent`]s.
+
+use std::time::Duration;
+
+use crate::chess::{Board, ChessMove, Game, GameState, Outcome, PieceColor, Termination};
+use crate::clock::ChessClock;
+use crate::player::{Player, PlayerAction};
+
+/// Something that happened in the game, in the order it happened.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GameEvent {
+    MoveMade { side: PieceColor, mv: ChessMove, san: String },
+    DrawOffered(PieceColor),
+    DrawDeclined(PieceColor),
+    /// Both clocks, sent on every tick while a clock runs
+    ClockTick { white: Duration, black: Duration },
+    GameOver(Outcome),
+    /// A player's action wasn't allowed, e.g. an illegal move
+    Rejected { side: PieceColor, reason: String },
+}
+
+/// Referees a game: accepts legal moves while it is running, runs the
+/// clock, and records how the game ended.
+pub struct GameController {
+    game: Game,
+    state: GameState,
+    clock: Option<ChessClock>,
+    outcome: Option<Outcome>,
+    // Side with an open draw offer; it lapses when the opponent moves
+    draw_offer: Option<PieceColor>,
+    // White's and Black's, when the controller asks for moves itself
+    players: Option<[Box<dyn Player>; 2]>,
+    events: Vec<GameEvent>,
+}
+
+fn index(side: PieceColor) -> usize {
+    match side {
+        PieceColor::White => 0,
+        PieceColor::Black => 1,
+    }
+}
+
+impl GameController {
+    /// Starts a game from `game`'s current position. The clock, if any,
+    /// starts for the side to move.
+    pub fn new(game: Game, clock: Option<ChessClock>) -> Self {
+        let state = game.board().check_game_state();
+        let mut controller = Self {
+            game,
+            state,
+            clock,
+            outcome: None,
+            draw_offer: None,
+            players: None,
+            events: Vec::new(),
+        };
+        let side = controller.side_to_move();
+        if let Some(clock) = &mut controller.clock {
+            clock.start(side);
+        }
+        controller.record_board_result();
+        controller
+    }
+
+    /// Lets [`GameController::tick`] ask these players for their moves.
+    pub fn with_players(mut self, white: Box<dyn Player>, black: Box<dyn Player>) -> Self {
+        self.players = Some([white, black]);
+        self
+    }
+
+    pub fn player(&self, side: PieceColor) -> Option<&dyn Player> {
+        self.players.as_ref().map(|players| players[index(side)].as_ref())
+    }
+
+    pub fn player_mut(&mut self, side: PieceColor) -> Option<&mut (dyn Player + 'static)> {
+        self.players.as_mut().map(|players| players[index(side)].as_mut())
+    }
+
+    /// Everything that happened since the last call.
+    pub fn take_events(&mut self) -> Vec<GameEvent> {
+        std::mem::take(&mut self.events)
+    }
+
+    fn emit(&mut self, event: GameEvent) {
+        if let Some(players) = &mut self.players {
+            for (player, side) in players.iter_mut().zip([PieceColor::White, PieceColor::Black]) {
+                player.notify(side, &event);
+            }
+        }
+        self.events.push(event);
+    }
+
+    /// Checks the clock, then asks both players, the side to move first,
+    /// whether they have something to do. Call it regularly: every frame,
+    /// or in a loop for a headless game.
+    pub fn tick(&mut self) {
+        self.check_flag();
+        if let Some(clock) = &self.clock && clock.running_side().is_some() {
+            let (white, black) = (clock.remaining(PieceColor::White), clock.remaining(PieceColor::Black));
+            self.emit(GameEvent::ClockTick { white, black });
+        }
+
+        let to_move = self.side_to_move();
+        for side in [to_move, to_move.opposite()] {
+            if self.is_over() {
+                break;
+            }
+            let action = match &mut self.players {
+                Some(players) => players[index(side)].poll(side, &self.game, self.clock.as_ref()),
+                None => None,
+            };
+            if let Some(action) = action && let Err(reason) = self.act(side, action) {
+                self.emit(GameEvent::Rejected { side, reason });
+            }
+        }
+    }
+
+    /// Carries out `action` for `side`.
+    pub fn act(&mut self, side: PieceColor, action: PlayerAction) -> Result<(), String> {
+        match action {
+            PlayerAction::Move(_) if side != self.side_to_move() => Err(format!("{} is not to move", side)),
+            PlayerAction::Move(mv) => self.make_move(mv),
+            PlayerAction::Resign => {
+                self.resign(side);
+                Ok(())
+            }
+            PlayerAction::OfferDraw => {
+                self.offer_draw(side);
+                Ok(())
+            }
+            PlayerAction::AcceptDraw if self.accept_draw(side) => Ok(()),
+            PlayerAction::AcceptDraw => Err("there is no draw offer to accept".to_string()),
+            PlayerAction::DeclineDraw => {
+                self.decline_draw();
+                Ok(())
+            }
+            PlayerAction::ClaimDraw if self.claim_draw() => Ok(()),
+            PlayerAction::ClaimDraw => Err("no draw can be claimed here".to_string()),
+            PlayerAction::Invalid(reason) => Err(reason),
+        }
+    }
+
+    pub fn game(&self) -> &Game {
+        &self.game
+    }
+
+    pub fn board(&self) -> &Board {
+        self.game.board()
+    }
+
+    pub fn state(&self) -> GameState {
+        self.state
+    }
+
+    pub fn side_to_move(&self) -> PieceColor {
+        self.game.board().current_turn()
+    }
+
+    pub fn clock(&self) -> Option<&ChessClock> {
+        self.clock.as_ref()
+    }
+
+    pub fn outcome(&self) -> Option<Outcome> {
+        self.outcome
+    }
+
+    pub fn is_over(&self) -> bool {
+        self.outcome.is_some()
+    }
+
+    pub fn draw_offer(&self) -> Option<PieceColor> {
+        self.draw_offer
+    }
+
+    /// Plays `mv` for the side to move and presses its clock.
+    pub fn make_move(&mut self, mv: ChessMove) -> Result<(), String> {
+        // A move made after the flag fell doesn't count
+        self.check_flag();
+        if self.is_over() {
+            return Err("the game is over".to_string());
+        }
+        if !self.game.board().get_valid_moves(mv.from).contains(&mv) {
+            return Err(format!("illegal move {}", mv.to_uci()));
+        }
+
+        let mover = self.side_to_move();
+        let san = self.game.board().move_to_san(mv);
+        self.game.make_move(mv);
+        self.state = self.game.board().check_game_state();
+        if let Some(clock) = &mut self.clock {
+            clock.press();
+        }
+        self.emit(GameEvent::MoveMade { side: mover, mv, san });
+        // Moving instead of answering a draw offer declines it
+        if self.draw_offer == Some(mover.opposite()) {
+            self.decline_draw();
+        }
+        self.record_board_result();
+        Ok(())
+    }
+
+    /// Takes back the last move and reopens the game if it had ended.
+    /// Not allowed in a game on the clock.
+    pub fn take_back(&mut self) -> Result<ChessMove, String> {
+        if self.clock.is_some() {
+            return Err("moves can't be taken back in a game on the clock".to_string());
+        }
+        let mv = self.game.undo().ok_or("there is no move to take back")?;
+        self.state = self.game.board().check_game_state();
+        self.outcome = None;
+        self.draw_offer = None;
+        Ok(mv)
+    }
+
+    fn record_board_result(&mut self) {
+        match self.state {
+            GameState::Checkmate => self.end(Outcome {
+                winner: Some(self.side_to_move().opposite()),
+                termination: Termination::Checkmate,
+            }),
+            GameState::Stalemate => self.end(Outcome { winner: None, termination: Termination::Stalemate }),
+            GameState::InProgress | GameState::Check => {}
+        }
+    }
+
+    fn end(&mut self, outcome: Outcome) {
+        self.outcome = Some(outcome);
+        self.draw_offer = None;
+        if let Some(clock) = &mut self.clock {
+            clock.stop();
+        }
+        self.emit(GameEvent::GameOver(outcome));
+    }
+
+    /// Ends the game if the side to move has run out of time, and returns
+    /// whether it did. Losing on time is a draw when the opponent couldn't
+    /// possibly mate.
+    pub fn check_flag(&mut self) -> bool {
+        if self.is_over() {
+            return false;
+        }
+        let Some(side) = self.clock.as_mut().and_then(ChessClock::flag) else {
+            return false;
+        };
+        let outcome = if self.game.board().has_mating_material(side.opposite()) {
+            Outcome { winner: Some(side.opposite()), termination: Termination::Timeout }
+        } else {
+            Outcome { winner: None, termination: Termination::TimeoutVsInsufficientMaterial }
+        };
+        self.end(outcome);
+        true
+    }
+
+    pub fn resign(&mut self, side: PieceColor) {
+        if !self.is_over() {
+            self.end(Outcome { winner: Some(side.opposite()), termination: Termination::Resignation });
+        }
+    }
+
+    pub fn offer_draw(&mut self, side: PieceColor) {
+        if !self.is_over() && self.draw_offer != Some(side) {
+            self.draw_offer = Some(side);
+            self.emit(GameEvent::DrawOffered(side));
+        }
+    }
+
+    /// Ends the game drawn if `side`'s opponent has a draw offer open.
+    pub fn accept_draw(&mut self, side: PieceColor) -> bool {
+        if self.draw_offer != Some(side.opposite()) || self.is_over() {
+            return false;
+        }
+        self.end(Outcome { winner: None, termination: Termination::DrawAgreement });
+        true
+    }
+
+    pub fn decline_draw(&mut self) {
+        if let Some(side) = self.draw_offer.take() {
+            self.emit(GameEvent::DrawDeclined(side));
+        }
+    }
+
+    pub fn claimable_draw(&self) -> Option<Termination> {
+        if self.is_over() {
+            return None;
+        }
+        self.game.claimable_draw()
+    }
+
+    /// Ends the game drawn if threefold repetition or the 50-move rule
+    /// applies.
+    pub fn claim_draw(&mut self) -> bool {
+        let Some(termination) = self.claimable_draw() else {
+            return false;
+        };
+        self.end(Outcome { winner: None, termination });
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+    use std::net::{TcpListener, TcpStream};
+    use std::time::Instant;
+
+    use super::*;
+    use crate::chess::Position;
+    use crate::clock::TimeControl;
+    use crate::player::{NetworkPlayer, ScriptedPlayer};
+
+    fn controller(fen: &str, clock: Option<ChessClock>) -> GameController {
+        GameController::new(Game::from_board(Board::from_fen(fen).unwrap()), clock)
+    }
+
+    fn play(controller: &mut GameController, moves: &str) {
+        for uci in moves.split_whitespace() {
+            let mv = controller.board().find_legal_move(uci).unwrap();
+            controller.make_move(mv).unwrap();
+        }
+    }
+
+    /// White has no time left, Black a minute.
+    fn white_flagged() -> ChessClock {
+        ChessClock::new(TimeControl::sudden_death(Duration::ZERO), TimeControl::sudden_death(Duration::from_secs(60)))
+    }
+
+    #[test]
+    fn checkmate_ends_the_game() {
+        let mut controller = GameController::new(Game::new(), None);
+        play(&mut controller, "f2f3 e7e5 g2g4 d8h4");
+        let outcome = Outcome { winner: Some(PieceColor::Black), termination: Termination::Checkmate };
+        assert_eq!(controller.outcome(), Some(outcome));
+        assert!(controller.take_events().contains(&GameEvent::GameOver(outcome)));
+        // Nothing changes a finished game
+        controller.resign(PieceColor::Black);
+        assert_eq!(controller.outcome(), Some(outcome));
+    }
+
+    #[test]
+    fn stalemate_is_a_draw() {
+        let mut controller = controller("7k/8/5K2/8/8/8/8/6Q1 w - - 0 1", None);
+        play(&mut controller, "g1g6");
+        assert_eq!(controller.outcome(), Some(Outcome { winner: None, termination: Termination::Stalemate }));
+    }
+
+    #[test]
+    fn flag_fall_loses_when_the_opponent_can_mate() {
+        let mut controller = GameController::new(Game::new(), Some(white_flagged()));
+        controller.tick();
+        assert_eq!(controller.outcome(), Some(Outcome { winner: Some(PieceColor::Black), termination: Termination::Timeout }));
+    }
+
+    #[test]
+    fn flag_fall_draws_against_a_bare_king() {
+        let mut controller = controller("4k3/8/8/8/8/8/4P3/4K3 w - - 0 1", Some(white_flagged()));
+        controller.tick();
+        assert_eq!(
+            controller.outcome(),
+            Some(Outcome { winner: None, termination: Termination::TimeoutVsInsufficientMaterial })
+        );
+    }
+
+    #[test]
+    fn draw_offer_lapses_when_the_opponent_moves() {
+        let mut controller = GameController::new(Game::new(), None);
+        controller.offer_draw(PieceColor::White);
+        // The offer stands through the offering side's own move
+        play(&mut controller, "e2e4");
+        assert_eq!(controller.draw_offer(), Some(PieceColor::White));
+        play(&mut controller, "e7e5");
+        assert_eq!(controller.draw_offer(), None);
+        assert!(!controller.accept_draw(PieceColor::Black));
+        let events = controller.take_events();
+        assert!(matches!(events.last(), Some(GameEvent::DrawDeclined(PieceColor::White))));
+    }
+
+    #[test]
+    fn accept_draw_needs_an_open_offer() {
+        let mut controller = GameController::new(Game::new(), None);
+        assert!(!controller.accept_draw(PieceColor::Black));
+        assert!(controller.act(PieceColor::Black, PlayerAction::AcceptDraw).is_err());
+        assert!(!controller.is_over());
+
+        controller.offer_draw(PieceColor::White);
+        // Only the opponent can accept
+        assert!(!controller.accept_draw(PieceColor::White));
+        assert!(controller.accept_draw(PieceColor::Black));
+        assert_eq!(controller.outcome(), Some(Outcome { winner: None, termination: Termination::DrawAgreement }));
+    }
+
+    #[test]
+    fn threefold_repetition_can_be_claimed() {
+        let mut controller = GameController::new(Game::new(), None);
+        play(&mut controller, "g1f3 g8f6 f3g1 f6g8 g1f3 g8f6 f3g1");
+        assert!(!controller.claim_draw());
+        play(&mut controller, "f6g8");
+        assert!(controller.claim_draw());
+        assert_eq!(
+            controller.outcome(),
+            Some(Outcome { winner: None, termination: Termination::ThreefoldRepetition })
+        );
+    }
+
+    #[test]
+    fn fifty_move_rule_can_be_claimed() {
+        let mut controller = controller("4k3/8/8/8/8/8/8/R3K3 w - - 98 60", None);
+        play(&mut controller, "a1a2");
+        assert!(!controller.claim_draw());
+        play(&mut controller, "e8d8");
+        assert!(controller.claim_draw());
+        assert_eq!(controller.outcome(), Some(Outcome { winner: None, termination: Termination::FiftyMoveRule }));
+    }
+
+    #[test]
+    fn take_back_is_refused_with_a_clock() {
+        let clock = ChessClock::new(
+            TimeControl::sudden_death(Duration::from_secs(60)),
+            TimeControl::sudden_death(Duration::from_secs(60)),
+        );
+        let mut controller = GameController::new(Game::new(), Some(clock));
+        play(&mut controller, "e2e4");
+        assert!(controller.take_back().is_err());
+        assert_eq!(controller.game().moves().len(), 1);
+
+        let mut controller = GameController::new(Game::new(), None);
+        let mv = controller.board().find_legal_move("e2e4").unwrap();
+        controller.make_move(mv).unwrap();
+        assert_eq!(controller.take_back(), Ok(mv));
+        assert!(controller.game().moves().is_empty());
+    }
+
+    #[test]
+    fn scripted_players_play_through_tick() {
+        let white = ScriptedPlayer::new("White", ["f3", "g4"]);
+        let black = ScriptedPlayer::new("Black", ["e5", "Qh4#"]);
+        let mut controller = GameController::new(Game::new(), None).with_players(Box::new(white), Box::new(black));
+        for _ in 0..4 {
+            controller.tick();
+        }
+
+        let outcome = Outcome { winner: Some(PieceColor::Black), termination: Termination::Checkmate };
+        let made = |side, from: &str, to: &str, san: &str| GameEvent::MoveMade {
+            side,
+            mv: ChessMove::new(Position::from_uci(from).unwrap(), Position::from_uci(to).unwrap()),
+            san: san.to_string(),
+        };
+        assert_eq!(
+            controller.take_events(),
+            [
+                made(PieceColor::White, "f2", "f3", "f3"),
+                made(PieceColor::Black, "e7", "e5", "e5"),
+                made(PieceColor::White, "g2", "g4", "g4"),
+                made(PieceColor::Black, "d8", "h4", "Qh4#"),
+                GameEvent::GameOver(outcome),
+            ]
+        );
+    }
+
+    #[test]
+    fn network_player_illegal_move_is_rejected() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut peer = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let black = NetworkPlayer::from_stream(listener.accept().unwrap().0).unwrap();
+        let white = ScriptedPlayer::new("White", ["e4"]);
+        let mut controller = GameController::new(Game::new(), None).with_players(Box::new(white), Box::new(black));
+        writeln!(peer, "move e7e4").unwrap();
+
+        let started = Instant::now();
+        let mut events = Vec::new();
+        while !events.iter().any(|event| matches!(event, GameEvent::Rejected { .. })) {
+            assert!(started.elapsed() < Duration::from_secs(5), "no rejection in {:?}", events);
+            controller.tick();
+            events.extend(controller.take_events());
+        }
+        let reason = "illegal move e7e4".to_string();
+        assert!(events.contains(&GameEvent::Rejected { side: PieceColor::Black, reason }));
+        assert_eq!(controller.game().moves().len(), 1);
+    }
+}
@@ -1,10 +1,15 @@
+use std::ops::RangeInclusive;
+
 use strum::{EnumIter, Display};
 use crate::chess::PieceColor;
+use crate::difficulty::{STOCKFISH_MAX_ELO, STOCKFISH_MIN_ELO};
+use crate::engine;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, EnumIter, Display)]
 pub enum GameMode {
     TwoPlayer,
     VsStockfish,
+    EngineVsEngine,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, EnumIter, Display)]
@@ -14,6 +19,25 @@ pub enum Difficulty {
     Hard,
 }
 
+/// Which engine plays the computer side.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, EnumIter, Display)]
+pub enum EngineKind {
+    Stockfish,
+    #[strum(serialize = "Built-in")]
+    BuiltIn,
+}
+
+impl EngineKind {
+    /// The `UCI_Elo` range the engine is known to advertise, for before
+    /// it has started and said so itself.
+    pub fn default_elo_range(self) -> RangeInclusive<u32> {
+        match self {
+            EngineKind::Stockfish => STOCKFISH_MIN_ELO..=STOCKFISH_MAX_ELO,
+            EngineKind::BuiltIn => engine::MIN_ELO..=engine::MAX_ELO,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, EnumIter, Display)]
 pub enum PlayerColor {
     White,
@@ -0,0 +1,11 @@
+//! Chess rules, the game controller, the built-in engine and the UCI engine bridge, shared by
+//! the GUI and the command-line tools under `src/bin`.
+
+pub mod chess;
+pub mod clock;
+pub mod difficulty;
+pub mod engine;
+pub mod game_controller;
+pub mod game_mode;
+pub mod player;
+pub mod stockfish;
@@ -1,8 +1,15 @@
 mod app;
-mod chess;
-mod game_mode;
-mod stockfish;
+mod board_editor;
+mod clock_display;
+mod engine_console;
+mod engine_match;
+mod engine_options;
+mod eval_bar;
+mod eval_graph;
+mod move_input;
+mod review;
 
+use chess_game::{chess, clock, difficulty, engine, game_controller, game_mode, player, stockfish};
 use eframe::{egui, NativeOptions};
 
 fn main() -> Result<(), eframe::Error> {
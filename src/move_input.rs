@@ -0,0 +1,215 @@
+use egui::text::{CCursor, CCursorRange};
+use egui::{Color32, Key};
+use crate::chess::{Board, ChessMove};
+
+/// Suggestions shown under the field at most.
+const MAX_SUGGESTIONS: usize = 12;
+
+/// Typed move entry: one move in SAN, UCI or coordinates, or a whole line
+/// of moves pasted in at once.
+#[derive(Default)]
+pub struct MoveInput {
+    text: String,
+    // Why the last entry couldn't be played
+    error: Option<String>,
+}
+
+impl MoveInput {
+    pub fn clear(&mut self) {
+        self.text.clear();
+        self.error = None;
+    }
+
+    /// Draws the field and the moves it could complete to. Returns the
+    /// moves to play once an entry is submitted and every move in it is
+    /// legal in turn; nothing is played otherwise.
+    pub fn show(&mut self, ui: &mut egui::Ui, board: &Board, enabled: bool) -> Vec<ChessMove> {
+        let mut submit = false;
+
+        ui.add_enabled_ui(enabled, |ui| {
+            ui.horizontal(|ui| {
+                ui.label("Move:");
+                // Focus stays put on Tab, which completes the move instead
+                let output = egui::TextEdit::singleline(&mut self.text)
+                    .hint_text("Nf3, g1f3, g1-f3, or a line like e4 e5 Nf3")
+                    .desired_width(280.0)
+                    .lock_focus(true)
+                    .show(ui);
+                if output.response.changed() {
+                    self.error = None;
+                }
+
+                let suggestions = self.suggestions(board);
+                if output.response.has_focus() && ui.input(|i| i.key_pressed(Key::Tab)) && !suggestions.is_empty() {
+                    self.replace_last_word(&common_prefix(&suggestions));
+                    let mut state = output.state;
+                    state.cursor.set_char_range(Some(CCursorRange::one(CCursor::new(self.text.chars().count()))));
+                    state.store(ui.ctx(), output.response.id);
+                }
+                if output.response.lost_focus() && ui.input(|i| i.key_pressed(Key::Enter)) {
+                    submit = true;
+                    output.response.request_focus();
+                }
+                if ui.button("Play").clicked() {
+                    submit = true;
+                }
+            });
+
+            let suggestions = self.suggestions(board);
+            if !suggestions.is_empty() {
+                ui.horizontal_wrapped(|ui| {
+                    for suggestion in suggestions.iter().take(MAX_SUGGESTIONS) {
+                        if ui.small_button(suggestion).clicked() {
+                            self.replace_last_word(suggestion);
+                            submit = true;
+                        }
+                    }
+                    if suggestions.len() > MAX_SUGGESTIONS {
+                        ui.label(format!("and {} more", suggestions.len() - MAX_SUGGESTIONS));
+                    }
+                });
+            }
+        });
+
+        if let Some(error) = &self.error {
+            ui.colored_label(Color32::from_rgb(220, 80, 60), error);
+        }
+
+        if !submit || !enabled {
+            return Vec::new();
+        }
+        match read_line(board, &self.text) {
+            Ok(moves) => {
+                self.clear();
+                moves
+            }
+            Err(err) => {
+                self.error = Some(err);
+                Vec::new()
+            }
+        }
+    }
+
+    /// Legal moves the word being typed could become, in the position the
+    /// moves before it lead to.
+    fn suggestions(&self, board: &Board) -> Vec<String> {
+        let (before, last) = split_last_word(&self.text);
+        if last.is_empty() {
+            return Vec::new();
+        }
+        match play_words(board, &move_words(before)) {
+            Ok((position, _)) => position.complete_move(strip_move_number(last)),
+            Err(_) => Vec::new(),
+        }
+    }
+
+    fn replace_last_word(&mut self, word: &str) {
+        let (before, _) = split_last_word(&self.text);
+        self.text = format!("{}{}", before, word);
+    }
+}
+
+/// Everything up to the last word, and the last word.
+fn split_last_word(text: &str) -> (&str, &str) {
+    let start = text.rfind(char::is_whitespace).map_or(0, |i| i + 1);
+    text.split_at(start)
+}
+
+/// "12.e4" -> "e4", "12..." -> "".
+fn strip_move_number(word: &str) -> &str {
+    match word.rsplit_once('.') {
+        Some((number, rest)) if number.chars().all(|c| c.is_ascii_digit() || c == '.') => rest,
+        _ => word,
+    }
+}
+
+/// The moves in a pasted line, without move numbers or a result.
+fn move_words(text: &str) -> Vec<&str> {
+    text.split_whitespace()
+        .map(strip_move_number)
+        .filter(|word| !word.is_empty() && !matches!(*word, "1-0" | "0-1" | "1/2-1/2" | "*"))
+        .collect()
+}
+
+/// Plays `words` one after another, each read in the position the previous
+/// ones lead to.
+fn play_words(board: &Board, words: &[&str]) -> Result<(Board, Vec<ChessMove>), String> {
+    let mut board = board.clone();
+    let mut moves = Vec::new();
+    for (index, word) in words.iter().enumerate() {
+        let mv = board.read_move(word).map_err(|err| match index {
+            0 => err,
+            _ => format!("move {}: {}", index + 1, err),
+        })?;
+        board.make_move(mv);
+        moves.push(mv);
+    }
+    Ok((board, moves))
+}
+
+/// The moves an entry asks for: a single move, which may contain a space
+/// ("e2 e4"), or a line of them.
+fn read_line(board: &Board, text: &str) -> Result<Vec<ChessMove>, String> {
+    if let Ok(mv) = board.read_move(text) {
+        return Ok(vec![mv]);
+    }
+    let words = move_words(text);
+    if words.is_empty() {
+        return Err("no move entered".to_string());
+    }
+    play_words(board, &words).map(|(_, moves)| moves)
+}
+
+/// Longest start shared by all of `words`.
+fn common_prefix(words: &[String]) -> String {
+    let mut prefix = words[0].as_str();
+    for word in &words[1..] {
+        let shared = prefix.chars().zip(word.chars()).take_while(|(a, b)| a == b).count();
+        prefix = &prefix[..prefix.char_indices().nth(shared).map_or(prefix.len(), |(i, _)| i)];
+    }
+    prefix.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn uci(moves: &[ChessMove]) -> Vec<String> {
+        moves.iter().map(|mv| mv.to_uci()).collect()
+    }
+
+    #[test]
+    fn move_numbers_are_stripped() {
+        assert_eq!(strip_move_number("12.e4"), "e4");
+        assert_eq!(strip_move_number("12...Nc6"), "Nc6");
+        assert_eq!(strip_move_number("12..."), "");
+        assert_eq!(strip_move_number("e4"), "e4");
+        assert_eq!(move_words("1. e4 e5 2.Nf3 1-0"), ["e4", "e5", "Nf3"]);
+    }
+
+    #[test]
+    fn pasted_lines_are_played_in_order() {
+        let board = Board::new();
+        assert_eq!(uci(&read_line(&board, "1. e4 e5 2. Nf3 Nc6 3.Bb5").unwrap()), ["e2e4", "e7e5", "g1f3", "b8c6", "f1b5"]);
+        assert_eq!(read_line(&board, "1. e4 e5 2. Ke3"), Err("move 3: Ke3 is not a legal move here".to_string()));
+        assert_eq!(read_line(&board, "1."), Err("no move entered".to_string()));
+    }
+
+    #[test]
+    fn a_space_may_split_one_move_or_separate_two() {
+        let board = Board::new();
+        assert_eq!(uci(&read_line(&board, "e2 e4").unwrap()), ["e2e4"]);
+        // Also reads as the UCI move e4e5, which isn't legal here
+        assert_eq!(uci(&read_line(&board, "e4 e5").unwrap()), ["e2e4", "e7e5"]);
+
+        // With a pawn on e4 the same text is its advance
+        let board = Board::from_fen("4k3/8/8/8/4P3/8/8/4K3 w - - 0 1").unwrap();
+        assert_eq!(uci(&read_line(&board, "e4 e5").unwrap()), ["e4e5"]);
+    }
+
+    #[test]
+    fn common_prefix_of_suggestions() {
+        let words = ["Nbd2".to_string(), "Nbc3".to_string(), "Nba3".to_string()];
+        assert_eq!(common_prefix(&words), "Nb");
+    }
+}
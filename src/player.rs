@@ -0,0 +1,259 @@
+//! The two sides of a game as the [`GameController`] sees them. Anything
+//! that can come up with moves is a [`Player`]: someone at the keyboard, a
+//! UCI engine, an opponent across the network, or a fixed list of moves.
+//!
+//! [`GameController`]: crate::game_controller::GameController
+
+use std::collections::VecDeque;
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::sync::mpsc::{self, Receiver, Sender, TryRecvError};
+use std::thread;
+use std::time::Duration;
+
+use crate::chess::{ChessMove, Game, Outcome, PieceColor, Termination};
+use crate::clock::ChessClock;
+use crate::difficulty::DifficultyProfile;
+use crate::game_controller::GameEvent;
+use crate::stockfish::{SearchLimits, Stockfish};
+
+/// What a player wants to do.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PlayerAction {
+    Move(ChessMove),
+    Resign,
+    OfferDraw,
+    AcceptDraw,
+    DeclineDraw,
+    ClaimDraw,
+    /// Input that isn't a valid action, such as an illegal move; it is
+    /// always rejected, with this reason
+    Invalid(String),
+}
+
+pub trait Player {
+    fn name(&self) -> String;
+
+    /// Asked on every tick, for both sides. `side` is the colour this player
+    /// has; moves are only taken from the side to move. Return `None` when
+    /// there is nothing to do yet.
+    fn poll(&mut self, side: PieceColor, game: &Game, clock: Option<&ChessClock>) -> Option<PlayerAction>;
+
+    /// Everything that happens in the game, including this player's own
+    /// moves.
+    fn notify(&mut self, _side: PieceColor, _event: &GameEvent) {}
+}
+
+/// Engine search limits for a game on the clock: both sides' time and
+/// increments, plus `side`'s moves to the next time control.
+pub fn clock_limits(clock: &ChessClock, side: PieceColor) -> SearchLimits {
+    let ms = |time: Duration| time.as_millis() as u64;
+    SearchLimits::clock(
+        ms(clock.remaining(PieceColor::White)),
+        ms(clock.remaining(PieceColor::Black)),
+        ms(clock.increment(PieceColor::White)),
+        ms(clock.increment(PieceColor::Black)),
+        clock.moves_to_go(side),
+    )
+}
+
+/// Looks up a move written in SAN ("Nf3"), UCI ("g1f3") or coordinates
+/// ("g1-f3"); see [`Board::read_move`](crate::chess::Board::read_move).
+pub fn parse_move(game: &Game, text: &str) -> Option<ChessMove> {
+    game.board().read_move(text).ok()
+}
+
+/// A person. The front end sends their actions through the sender returned
+/// by [`HumanPlayer::new`] whenever they click or type something.
+pub struct HumanPlayer {
+    name: String,
+    input: Receiver<PlayerAction>,
+}
+
+impl HumanPlayer {
+    pub fn new(name: &str) -> (Self, Sender<PlayerAction>) {
+        let (sender, input) = mpsc::channel();
+        (Self { name: name.to_string(), input }, sender)
+    }
+}
+
+impl Player for HumanPlayer {
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn poll(&mut self, _side: PieceColor, _game: &Game, _clock: Option<&ChessClock>) -> Option<PlayerAction> {
+        self.input.try_recv().ok()
+    }
+}
+
+/// A UCI engine playing at one of the difficulty profiles. Searching blocks
+/// the tick that asks for the move.
+pub struct EnginePlayer {
+    engine: Stockfish,
+    profile: DifficultyProfile,
+}
+
+impl EnginePlayer {
+    pub fn new(engine: Stockfish, profile: DifficultyProfile) -> Self {
+        Self { engine, profile }
+    }
+
+    /// The engine itself, e.g. for a console or analysis between moves.
+    pub fn engine(&mut self) -> &mut Stockfish {
+        &mut self.engine
+    }
+}
+
+impl Player for EnginePlayer {
+    fn name(&self) -> String {
+        format!("{} ({})", self.engine.engine_name(), self.profile.name)
+    }
+
+    fn poll(&mut self, side: PieceColor, game: &Game, clock: Option<&ChessClock>) -> Option<PlayerAction> {
+        if game.board().current_turn() != side || !self.engine.is_alive() {
+            return None;
+        }
+        let limits = match clock {
+            Some(clock) => clock_limits(clock, side).with_depth(self.profile.depth).with_nodes(self.profile.nodes),
+            None => self.profile.search_limits(),
+        };
+        self.engine.set_strength(self.profile.strength);
+        self.engine.set_position_with_moves(&game.start_board().to_fen(), game.moves());
+        let best = self.engine.get_best_move(&limits)?;
+        game.board().find_legal_move(&best).map(PlayerAction::Move)
+    }
+
+    fn notify(&mut self, _side: PieceColor, event: &GameEvent) {
+        if let GameEvent::GameOver(_) = event {
+            self.engine.new_game();
+        }
+    }
+}
+
+/// Plays a fixed list of moves, in UCI or SAN, then waits forever. For
+/// replaying openings and for test harnesses.
+pub struct ScriptedPlayer {
+    name: String,
+    moves: VecDeque<String>,
+}
+
+impl ScriptedPlayer {
+    pub fn new<S: Into<String>>(name: &str, moves: impl IntoIterator<Item = S>) -> Self {
+        Self {
+            name: name.to_string(),
+            moves: moves.into_iter().map(Into::into).collect(),
+        }
+    }
+
+    pub fn moves_left(&self) -> usize {
+        self.moves.len()
+    }
+}
+
+impl Player for ScriptedPlayer {
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn poll(&mut self, side: PieceColor, game: &Game, _clock: Option<&ChessClock>) -> Option<PlayerAction> {
+        if game.board().current_turn() != side {
+            return None;
+        }
+        let text = self.moves.pop_front()?;
+        // An unplayable entry resigns rather than stalling the game
+        Some(parse_move(game, &text).map_or(PlayerAction::Resign, PlayerAction::Move))
+    }
+}
+
+/// An opponent on another machine, over a TCP connection carrying one
+/// command per line: `move <uci>`, `resign`, `draw offer`, `draw accept`
+/// and `draw decline`. The local side's actions are sent the same way.
+/// Anything else, or an illegal move, is rejected. A dropped connection
+/// counts as resigning.
+pub struct NetworkPlayer {
+    peer: String,
+    writer: TcpStream,
+    lines: Receiver<String>,
+    // Whether the open draw offer, if any, came from the peer
+    peer_offered: bool,
+}
+
+impl NetworkPlayer {
+    pub fn connect(address: impl ToSocketAddrs) -> io::Result<Self> {
+        Self::from_stream(TcpStream::connect(address)?)
+    }
+
+    /// Wraps an established connection, e.g. one accepted by a listener.
+    pub fn from_stream(stream: TcpStream) -> io::Result<Self> {
+        let peer = stream.peer_addr()?.to_string();
+        let reader = BufReader::new(stream.try_clone()?);
+        let (sender, lines) = mpsc::channel();
+        thread::spawn(move || {
+            for line in reader.lines() {
+                let Ok(line) = line else { break };
+                if sender.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+        Ok(Self { peer, writer: stream, lines, peer_offered: false })
+    }
+
+    fn send(&mut self, line: &str) {
+        // A broken connection shows up on the reading side
+        let _ = writeln!(self.writer, "{}", line);
+    }
+}
+
+impl Player for NetworkPlayer {
+    fn name(&self) -> String {
+        self.peer.clone()
+    }
+
+    fn poll(&mut self, _side: PieceColor, game: &Game, _clock: Option<&ChessClock>) -> Option<PlayerAction> {
+        let line = match self.lines.try_recv() {
+            Ok(line) => line,
+            Err(TryRecvError::Empty) => return None,
+            Err(TryRecvError::Disconnected) => return Some(PlayerAction::Resign),
+        };
+        match line.trim().split_once(' ').unwrap_or((line.trim(), "")) {
+            ("move", uci) => Some(
+                game.board()
+                    .find_legal_move(uci.trim())
+                    .map_or_else(|| PlayerAction::Invalid(format!("illegal move {}", uci.trim())), PlayerAction::Move),
+            ),
+            ("resign", _) => Some(PlayerAction::Resign),
+            ("draw", "offer") => Some(PlayerAction::OfferDraw),
+            ("draw", "accept") => Some(PlayerAction::AcceptDraw),
+            ("draw", "decline") => Some(PlayerAction::DeclineDraw),
+            _ => Some(PlayerAction::Invalid(format!("unknown command {}", line.trim()))),
+        }
+    }
+
+    /// Passes on what the local side did; the peer already knows what it
+    /// did itself.
+    fn notify(&mut self, side: PieceColor, event: &GameEvent) {
+        match event {
+            GameEvent::MoveMade { side: mover, mv, .. } if *mover != side => self.send(&format!("move {}", mv.to_uci())),
+            GameEvent::DrawOffered(offerer) => {
+                self.peer_offered = *offerer == side;
+                if !self.peer_offered {
+                    self.send("draw offer");
+                }
+            }
+            GameEvent::DrawDeclined(offerer) => {
+                if *offerer == side {
+                    self.send("draw decline");
+                }
+                self.peer_offered = false;
+            }
+            GameEvent::GameOver(Outcome { winner, termination }) => match termination {
+                Termination::Resignation if *winner == Some(side) => self.send("resign"),
+                Termination::DrawAgreement if self.peer_offered => self.send("draw accept"),
+                _ => {}
+            },
+            _ => {}
+        }
+    }
+}
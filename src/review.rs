@@ -0,0 +1,272 @@
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+
+use strum::{Display, EnumIter};
+use crate::chess::{Board, Game, GameState, MoveAnnotation, PieceColor};
+use crate::engine_options::{self, SavedOptions};
+use crate::eval_bar;
+use crate::eval_graph::{self, GRAPH_CLAMP};
+use crate::stockfish::{EngineEvent, Score, SearchLimits, Stockfish};
+
+/// How much search each position gets during a review.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReviewBudget {
+    Depth(u32),
+    Nodes(u64),
+}
+
+impl ReviewBudget {
+    pub fn search_limits(self) -> SearchLimits {
+        match self {
+            ReviewBudget::Depth(depth) => SearchLimits::depth(depth),
+            ReviewBudget::Nodes(nodes) => SearchLimits::nodes(nodes),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, EnumIter, Display)]
+pub enum MoveClass {
+    Best,
+    Excellent,
+    Good,
+    Inaccuracy,
+    Mistake,
+    Blunder,
+}
+
+impl MoveClass {
+    /// Thresholds are drops in the mover's winning percentage, the same
+    /// scale Lichess uses (5/10/15 points for ?!/?/??).
+    pub fn from_win_drop(drop: f32, played_best: bool) -> Self {
+        if played_best {
+            MoveClass::Best
+        } else if drop < 2.0 {
+            MoveClass::Excellent
+        } else if drop < 5.0 {
+            MoveClass::Good
+        } else if drop < 10.0 {
+            MoveClass::Inaccuracy
+        } else if drop < 15.0 {
+            MoveClass::Mistake
+        } else {
+            MoveClass::Blunder
+        }
+    }
+
+    pub fn is_error(self) -> bool {
+        self >= MoveClass::Inaccuracy
+    }
+
+    /// PGN numeric annotation glyph.
+    pub fn nag(self) -> Option<u8> {
+        match self {
+            MoveClass::Inaccuracy => Some(6),
+            MoveClass::Mistake => Some(2),
+            MoveClass::Blunder => Some(4),
+            _ => None,
+        }
+    }
+
+    pub fn symbol(self) -> &'static str {
+        match self {
+            MoveClass::Inaccuracy => "?!",
+            MoveClass::Mistake => "?",
+            MoveClass::Blunder => "??",
+            _ => "",
+        }
+    }
+}
+
+/// Engine verdict on one position of the game.
+#[derive(Debug, Clone)]
+pub struct PositionEval {
+    /// From White's point of view
+    pub score: Score,
+    /// White's winning chances in percent
+    pub white_win: f32,
+    pub best_move: Option<String>,
+}
+
+impl PositionEval {
+    /// Graph height, with checkmates on the board pinned to the winner's edge.
+    pub fn graph_value(&self) -> f32 {
+        match self.score {
+            Score::Mate(0) if self.white_win > 50.0 => GRAPH_CLAMP,
+            Score::Mate(0) if self.white_win < 50.0 => -GRAPH_CLAMP,
+            score => eval_graph::graph_value(score),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ReviewedMove {
+    pub san: String,
+    pub class: MoveClass,
+    /// Drop in the mover's winning percentage
+    pub win_drop: f32,
+    pub best_san: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct GameReview {
+    /// One entry per position, including the start and final positions
+    pub evals: Vec<PositionEval>,
+    pub moves: Vec<ReviewedMove>,
+    pub white_accuracy: f32,
+    pub black_accuracy: f32,
+}
+
+pub enum ReviewMessage {
+    Progress { done: usize, total: usize },
+    Finished(GameReview),
+    /// The engine couldn't be started or died for good
+    Failed(String),
+}
+
+pub fn score_to_win_percent(score: Score) -> f32 {
+    eval_bar::white_share(score) * 100.0
+}
+
+/// Per-move accuracy from the mover's win-percentage drop (Lichess formula).
+fn move_accuracy(drop: f32) -> f32 {
+    (103.1668 * (-0.04354 * drop).exp() - 3.1669).clamp(0.0, 100.0)
+}
+
+/// Evaluates the position after `ply` moves of `game`; `board` is that position.
+fn evaluate_position(stockfish: &mut Stockfish, game: &Game, ply: usize, board: &Board, budget: ReviewBudget) -> PositionEval {
+    let side = board.current_turn();
+    match board.check_game_state() {
+        GameState::Checkmate => {
+            let white_win = if side == PieceColor::White { 0.0 } else { 100.0 };
+            return PositionEval { score: Score::Mate(0), white_win, best_move: None };
+        }
+        GameState::Stalemate => {
+            return PositionEval { score: Score::Cp(0), white_win: 50.0, best_move: None };
+        }
+        _ => {}
+    }
+
+    stockfish.set_position_with_moves(&game.start_board().to_fen(), &game.moves()[..ply]);
+    let best_move = stockfish.get_best_move(&budget.search_limits());
+    let score = stockfish
+        .last_info()
+        .map(|info| info.score.for_white(side))
+        .unwrap_or(Score::Cp(0));
+
+    PositionEval {
+        score,
+        white_win: score_to_win_percent(score),
+        best_move,
+    }
+}
+
+impl GameReview {
+    fn from_evals(game: &Game, evals: Vec<PositionEval>) -> Self {
+        let mut moves = Vec::new();
+        let mut accuracies = [Vec::new(), Vec::new()];
+        let mut board = game.start_board().clone();
+
+        for (ply, mv) in game.moves().iter().enumerate() {
+            let mover = board.current_turn();
+            let (before, after) = match mover {
+                PieceColor::White => (evals[ply].white_win, evals[ply + 1].white_win),
+                PieceColor::Black => (100.0 - evals[ply].white_win, 100.0 - evals[ply + 1].white_win),
+            };
+            let drop = (before - after).max(0.0);
+
+            let best = evals[ply].best_move.as_deref().and_then(|uci| board.find_legal_move(uci));
+            let class = MoveClass::from_win_drop(drop, best == Some(*mv));
+            let best_san = best
+                .filter(|_| class.is_error())
+                .map(|best| board.move_to_san(best));
+
+            moves.push(ReviewedMove {
+                san: game.san_moves()[ply].clone(),
+                class,
+                win_drop: drop,
+                best_san,
+            });
+            accuracies[(mover == PieceColor::Black) as usize].push(move_accuracy(drop));
+            board.make_move(*mv);
+        }
+
+        let average = |values: &[f32]| {
+            if values.is_empty() {
+                100.0
+            } else {
+                values.iter().sum::<f32>() / values.len() as f32
+            }
+        };
+
+        Self {
+            white_accuracy: average(&accuracies[0]),
+            black_accuracy: average(&accuracies[1]),
+            evals,
+            moves,
+        }
+    }
+
+    /// Per-ply PGN annotations: NAGs for errors, `[%eval]` for every move
+    /// and the engine's preferred move where the played one fell short.
+    pub fn annotations(&self) -> Vec<MoveAnnotation> {
+        self.moves
+            .iter()
+            .zip(self.evals.iter().skip(1))
+            .map(|(reviewed, eval)| {
+                let eval_text = match eval.score {
+                    Score::Cp(cp) => format!("{:.2}", cp as f32 / 100.0),
+                    Score::Mate(n) => format!("#{}", n),
+                };
+                let mut comment = format!("[%eval {}]", eval_text);
+                if let Some(best) = &reviewed.best_san {
+                    comment.push_str(&format!(" {}. Best was {}.", reviewed.class, best));
+                }
+                MoveAnnotation {
+                    nag: reviewed.class.nag(),
+                    comment: Some(comment),
+                }
+            })
+            .collect()
+    }
+}
+
+/// Reviews every position of `game` on a background thread with its own
+/// engine process, reporting progress through the returned channel.
+pub fn spawn_review(engine_path: &str, options: SavedOptions, game: Game, budget: ReviewBudget) -> Receiver<ReviewMessage> {
+    let (sender, receiver) = mpsc::channel();
+    let engine_path = engine_path.to_string();
+
+    thread::spawn(move || {
+        let mut stockfish = match Stockfish::new(&engine_path) {
+            Ok(stockfish) => stockfish,
+            Err(err) => {
+                let _ = sender.send(ReviewMessage::Failed(format!("could not start the engine: {}", err)));
+                return;
+            }
+        };
+        // Rejected values were already reported when the game's engine started
+        engine_options::apply_saved(&mut stockfish, &options);
+        let total = game.moves().len() + 1;
+        let mut evals = Vec::with_capacity(total);
+        let mut board = game.start_board().clone();
+
+        for ply in 0..total {
+            evals.push(evaluate_position(&mut stockfish, &game, ply, &board, budget));
+            if let Some(event) = stockfish.take_events().into_iter().find(|event| matches!(event, EngineEvent::Failed(_))) {
+                let _ = sender.send(ReviewMessage::Failed(event.to_string()));
+                return;
+            }
+            if sender.send(ReviewMessage::Progress { done: ply + 1, total }).is_err() {
+                // Nobody is waiting for the result any more
+                return;
+            }
+            if let Some(mv) = game.moves().get(ply) {
+                board.make_move(*mv);
+            }
+        }
+
+        let _ = sender.send(ReviewMessage::Finished(GameReview::from_evals(&game, evals)));
+    });
+
+    receiver
+}
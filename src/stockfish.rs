@@ -1,58 +1,1085 @@
+use std::collections::VecDeque;
 use std::process::{Command, Child, Stdio};
-use std::io::{BufReader, BufRead, Write};
-use std::time::Duration;
-use std::thread;
+use std::io::{self, BufReader, BufRead, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender, TryRecvError};
+use std::fs;
+use std::ops::RangeInclusive;
+use std::path::Path;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use std::thread::{self, JoinHandle};
+use crate::chess::{ChessMove, PieceColor, START_FEN};
+use crate::difficulty::EngineStrength;
+use crate::engine::{self, BUILTIN_ENGINE};
 
-pub struct Stockfish {
-    process: Child,
+/// Engine score as reported in a UCI `info ... score` field.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Score {
+    /// Centipawns
+    Cp(i32),
+    /// Mate in N moves (negative when the side is getting mated)
+    Mate(i32),
 }
 
-impl Stockfish {
-    pub fn new(path: &str) -> Self {
-        let process = Command::new(path)
+impl Score {
+    /// Scores arrive from the point of view of the side to move; this
+    /// converts them to White's point of view.
+    pub fn for_white(self, side_to_move: PieceColor) -> Self {
+        match (self, side_to_move) {
+            (s, PieceColor::White) => s,
+            (Score::Cp(cp), PieceColor::Black) => Score::Cp(-cp),
+            (Score::Mate(n), PieceColor::Black) => Score::Mate(-n),
+        }
+    }
+}
+
+/// Win/draw/loss probabilities in per mille, sent when `UCI_ShowWDL` is on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Wdl {
+    pub win: u32,
+    pub draw: u32,
+    pub loss: u32,
+}
+
+impl Wdl {
+    pub fn for_white(self, side_to_move: PieceColor) -> Self {
+        match side_to_move {
+            PieceColor::White => self,
+            PieceColor::Black => Self { win: self.loss, draw: self.draw, loss: self.win },
+        }
+    }
+}
+
+/// A parsed `info` line. Only lines carrying a score are kept.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SearchInfo {
+    pub depth: u32,
+    pub multipv: usize,
+    pub score: Score,
+    pub wdl: Option<Wdl>,
+    pub nodes: u64,
+    pub pv: Vec<String>,
+}
+
+impl SearchInfo {
+    pub fn parse(line: &str) -> Option<Self> {
+        let mut tokens = line.split_whitespace();
+        if tokens.next() != Some("info") {
+            return None;
+        }
+
+        let mut depth = 0;
+        let mut multipv = 1;
+        let mut score = None;
+        let mut wdl = None;
+        let mut nodes = 0;
+        let mut pv = Vec::new();
+
+        while let Some(token) = tokens.next() {
+            match token {
+                "depth" => depth = tokens.next()?.parse().ok()?,
+                "multipv" => multipv = tokens.next()?.parse().ok()?,
+                "nodes" => nodes = tokens.next()?.parse().ok()?,
+                "score" => {
+                    let kind = tokens.next()?;
+                    let value = tokens.next()?.parse().ok()?;
+                    score = match kind {
+                        "cp" => Some(Score::Cp(value)),
+                        "mate" => Some(Score::Mate(value)),
+                        _ => None,
+                    };
+                }
+                "wdl" => {
+                    wdl = Some(Wdl {
+                        win: tokens.next()?.parse().ok()?,
+                        draw: tokens.next()?.parse().ok()?,
+                        loss: tokens.next()?.parse().ok()?,
+                    });
+                }
+                // The principal variation runs to the end of the line
+                "pv" => {
+                    pv = tokens.by_ref().map(String::from).collect();
+                }
+                // Free text, nothing useful can follow it
+                "string" => break,
+                _ => {}
+            }
+        }
+
+        Some(Self {
+            depth,
+            multipv,
+            score: score?,
+            wdl,
+            nodes,
+            pv,
+        })
+    }
+}
+
+/// Limits for a `go` command. Any combination may be set; the engine stops
+/// at whichever is reached first, and manages its own time when the clock
+/// fields are given.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SearchLimits {
+    pub movetime_ms: Option<u64>,
+    pub depth: Option<u32>,
+    pub nodes: Option<u64>,
+    /// Search for a mate in this many moves
+    pub mate: Option<u32>,
+    pub wtime_ms: Option<u64>,
+    pub btime_ms: Option<u64>,
+    pub winc_ms: Option<u64>,
+    pub binc_ms: Option<u64>,
+    pub movestogo: Option<u32>,
+}
+
+impl SearchLimits {
+    pub fn movetime(ms: u64) -> Self {
+        Self { movetime_ms: Some(ms), ..Self::default() }
+    }
+
+    pub fn depth(depth: u32) -> Self {
+        Self { depth: Some(depth), ..Self::default() }
+    }
+
+    pub fn nodes(nodes: u64) -> Self {
+        Self { nodes: Some(nodes), ..Self::default() }
+    }
+
+    pub fn mate(moves: u32) -> Self {
+        Self { mate: Some(moves), ..Self::default() }
+    }
+
+    /// Remaining time and increment for both sides, as on a game clock.
+    pub fn clock(wtime_ms: u64, btime_ms: u64, winc_ms: u64, binc_ms: u64, movestogo: Option<u32>) -> Self {
+        Self {
+            wtime_ms: Some(wtime_ms),
+            btime_ms: Some(btime_ms),
+            winc_ms: Some(winc_ms),
+            binc_ms: Some(binc_ms),
+            movestogo,
+            ..Self::default()
+        }
+    }
+
+    pub fn with_depth(mut self, depth: Option<u32>) -> Self {
+        self.depth = depth;
+        self
+    }
+
+    pub fn with_nodes(mut self, nodes: Option<u64>) -> Self {
+        self.nodes = nodes;
+        self
+    }
+
+    /// Longest the search may legitimately take, if the limits bound it at
+    /// all. With clock limits the engine never uses more than the time left.
+    pub fn deadline(&self) -> Option<Duration> {
+        let clock = match (self.wtime_ms, self.btime_ms) {
+            (Some(w), Some(b)) => Some(w.max(b)),
+            (w, b) => w.or(b),
+        };
+        match (self.movetime_ms, clock) {
+            (Some(movetime), Some(clock)) => Some(movetime.min(clock)),
+            (movetime, clock) => movetime.or(clock),
+        }
+        .map(Duration::from_millis)
+    }
+
+    /// Arguments for `go`. With no limits at all this is `infinite`, which
+    /// only ends on `stop`.
+    pub fn to_go_args(&self) -> String {
+        let fields = [
+            ("wtime", self.wtime_ms),
+            ("btime", self.btime_ms),
+            ("winc", self.winc_ms),
+            ("binc", self.binc_ms),
+            ("movestogo", self.movestogo.map(u64::from)),
+            ("depth", self.depth.map(u64::from)),
+            ("nodes", self.nodes),
+            ("mate", self.mate.map(u64::from)),
+            ("movetime", self.movetime_ms),
+        ];
+
+        let args: Vec<String> = fields
+            .iter()
+            .filter_map(|(name, value)| value.map(|v| format!("{} {}", name, v)))
+            .collect();
+
+        if args.is_empty() {
+            "infinite".to_string()
+        } else {
+            args.join(" ")
+        }
+    }
+}
+
+/// The kind of an engine option and its declared constraints.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UciOptionKind {
+    Check { default: bool },
+    Spin { default: i64, min: i64, max: i64 },
+    Combo { default: String, vars: Vec<String> },
+    Button,
+    String { default: String },
+}
+
+/// An option the engine declared in reply to `uci`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UciOption {
+    pub name: String,
+    pub kind: UciOptionKind,
+}
+
+impl UciOption {
+    /// Parses an `option name ... type ...` line.
+    pub fn parse(line: &str) -> Option<Self> {
+        let mut tokens = line.split_whitespace();
+        if tokens.next() != Some("option") || tokens.next() != Some("name") {
+            return None;
+        }
+
+        // Names and values may contain spaces, so collect words up to the
+        // next keyword
+        let mut name = Vec::new();
+        let mut option_type = None;
+        let mut default = None;
+        let mut min = None;
+        let mut max = None;
+        let mut vars = Vec::new();
+        let mut current: Option<&str> = None;
+        let mut words: Vec<&str> = Vec::new();
+
+        let mut finish = |key: Option<&str>, words: &mut Vec<&str>| {
+            let value = words.join(" ");
+            words.clear();
+            match key {
+                None => name.push(value),
+                Some("type") => option_type = Some(value),
+                Some("default") => default = Some(value),
+                Some("min") => min = value.parse::<i64>().ok(),
+                Some("max") => max = value.parse::<i64>().ok(),
+                Some("var") => vars.push(value),
+                _ => {}
+            }
+        };
+
+        for token in tokens {
+            match token {
+                "type" | "default" | "min" | "max" | "var" => {
+                    finish(current, &mut words);
+                    current = Some(token);
+                }
+                _ => words.push(token),
+            }
+        }
+        finish(current, &mut words);
+
+        let name = name.concat();
+        if name.is_empty() {
+            return None;
+        }
+        // Engines write "<empty>" for an empty string default
+        let default = default.map(|value| if value == "<empty>" { String::new() } else { value });
+
+        let kind = match option_type?.as_str() {
+            "check" => UciOptionKind::Check { default: default? == "true" },
+            "spin" => UciOptionKind::Spin { default: default?.parse().ok()?, min: min?, max: max? },
+            "combo" => UciOptionKind::Combo { default: default?, vars },
+            "button" => UciOptionKind::Button,
+            "string" => UciOptionKind::String { default: default.unwrap_or_default() },
+            _ => return None,
+        };
+        Some(Self { name, kind })
+    }
+
+    /// The declared default, as it would be sent with `setoption`.
+    pub fn default_value(&self) -> Option<String> {
+        match &self.kind {
+            UciOptionKind::Check { default } => Some(default.to_string()),
+            UciOptionKind::Spin { default, .. } => Some(default.to_string()),
+            UciOptionKind::Combo { default, .. } | UciOptionKind::String { default } => Some(default.clone()),
+            UciOptionKind::Button => None,
+        }
+    }
+
+    /// Checks a value against the declared type and range, returning it in
+    /// the form to send.
+    pub fn validate(&self, value: &str) -> Result<String, String> {
+        let value = value.trim();
+        match &self.kind {
+            UciOptionKind::Check { .. } => match value {
+                "true" | "false" => Ok(value.to_string()),
+                _ => Err(format!("{}: expected true or false", self.name)),
+            },
+            UciOptionKind::Spin { min, max, .. } => match value.parse::<i64>() {
+                Ok(number) if (*min..=*max).contains(&number) => Ok(number.to_string()),
+                _ => Err(format!("{}: expected a whole number from {} to {}", self.name, min, max)),
+            },
+            UciOptionKind::Combo { vars, .. } => vars
+                .iter()
+                .find(|var| var.eq_ignore_ascii_case(value))
+                .cloned()
+                .ok_or_else(|| format!("{}: expected one of {}", self.name, vars.join(", "))),
+            UciOptionKind::String { .. } => Ok(value.to_string()),
+            UciOptionKind::Button => Err(format!("{} is a button and takes no value", self.name)),
+        }
+    }
+}
+
+/// What the engine said about itself during the `uci` handshake.
+#[derive(Debug, Clone, Default)]
+struct EngineId {
+    name: String,
+    options: Vec<UciOption>,
+}
+
+/// Longest an engine may take to answer `uci` at startup.
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Extra time past a search's deadline before the watchdog sends `stop`,
+/// and again before it gives up on the process.
+const WATCHDOG_GRACE: Duration = Duration::from_secs(2);
+/// Deadline the watchdog assumes for searches bounded only by depth or
+/// nodes, so a hung engine can't block the caller forever.
+const UNBOUNDED_SEARCH_TIMEOUT: Duration = Duration::from_secs(60);
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+const STDERR_LINES: usize = 50;
+/// Restarts allowed without a successful search in between.
+const MAX_RESTARTS: u32 = 3;
+
+/// Something the supervisor noticed, for the UI to report.
+#[derive(Debug, Clone)]
+pub enum EngineEvent {
+    /// The process exited on its own
+    Exited { status: String, stderr: Vec<String> },
+    /// A search overran its deadline and the engine had to be stopped or killed
+    Unresponsive,
+    /// A new process is running with the previous options and position
+    Restarted,
+    /// Restarting failed or was attempted too often; the engine is gone
+    Failed(String),
+}
+
+impl std::fmt::Display for EngineEvent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EngineEvent::Exited { status, stderr } => {
+                write!(f, "Engine exited unexpectedly ({})", status)?;
+                if let Some(last) = stderr.last() {
+                    write!(f, ": {}", last)?;
+                }
+                Ok(())
+            }
+            EngineEvent::Unresponsive => write!(f, "Engine stopped responding and was interrupted"),
+            EngineEvent::Restarted => write!(f, "Engine restarted"),
+            EngineEvent::Failed(reason) => write!(f, "Engine unavailable: {}", reason),
+        }
+    }
+}
+
+/// Lines of UCI traffic kept for the console; older ones are dropped.
+pub const LOG_CAPACITY: usize = 5000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogDirection {
+    Sent,
+    Received,
+    Stderr,
+}
+
+#[derive(Debug, Clone)]
+pub struct LogEntry {
+    pub time: SystemTime,
+    pub direction: LogDirection,
+    pub line: String,
+}
+
+impl LogEntry {
+    /// Time of day in UTC with milliseconds, e.g. "14:03:27.512".
+    pub fn timestamp(&self) -> String {
+        let ms = self.time.duration_since(UNIX_EPOCH).unwrap_or_default().as_millis() % 86_400_000;
+        format!("{:02}:{:02}:{:02}.{:03}", ms / 3_600_000, ms / 60_000 % 60, ms / 1000 % 60, ms % 1000)
+    }
+}
+
+impl std::fmt::Display for LogEntry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let arrow = match self.direction {
+            LogDirection::Sent => ">>",
+            LogDirection::Received => "<<",
+            LogDirection::Stderr => "!!",
+        };
+        write!(f, "{} {} {}", self.timestamp(), arrow, self.line)
+    }
+}
+
+/// Ring buffer of everything exchanged with the engine. Cheap to clone; all
+/// clones share the same buffer, and it outlives engine restarts.
+#[derive(Clone, Default)]
+pub struct UciLog(Arc<Mutex<VecDeque<LogEntry>>>);
+
+impl UciLog {
+    fn push(&self, direction: LogDirection, line: &str) {
+        let mut entries = self.0.lock().unwrap();
+        if entries.len() == LOG_CAPACITY {
+            entries.pop_front();
+        }
+        entries.push_back(LogEntry {
+            time: SystemTime::now(),
+            direction,
+            line: line.to_string(),
+        });
+    }
+
+    pub fn entries(&self) -> Vec<LogEntry> {
+        self.0.lock().unwrap().iter().cloned().collect()
+    }
+
+    pub fn clear(&self) {
+        self.0.lock().unwrap().clear();
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let text: String = self.entries().iter().map(|entry| format!("{}\n", entry)).collect();
+        fs::write(path, text)
+    }
+}
+
+/// Where the engine runs.
+enum Backend {
+    Process(Child),
+    /// The built-in engine, on a thread of this process
+    Builtin(JoinHandle<()>),
+}
+
+/// Feeds the built-in engine one command per message, as a pipe would
+/// deliver them line by line.
+struct ChannelWriter {
+    sender: Sender<String>,
+    buffer: Vec<u8>,
+}
+
+impl Write for ChannelWriter {
+    fn write(&mut self, bytes: &[u8]) -> io::Result<usize> {
+        self.buffer.extend_from_slice(bytes);
+        while let Some(end) = self.buffer.iter().position(|&b| b == b'\n') {
+            let line: Vec<u8> = self.buffer.drain(..=end).collect();
+            let line = String::from_utf8_lossy(&line).trim_end().to_string();
+            self.sender
+                .send(line)
+                .map_err(|_| io::Error::new(io::ErrorKind::BrokenPipe, "engine thread has stopped"))?;
+        }
+        Ok(bytes.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// A running engine process and the threads draining its output.
+struct EngineProcess {
+    backend: Backend,
+    input: Box<dyn Write + Send>,
+    // Lines from the engine's stdout, read on a separate thread so output
+    // never backs up while a ponder search runs unattended
+    output: Receiver<String>,
+    // Tail of stderr, kept for crash reports
+    stderr: Arc<Mutex<VecDeque<String>>>,
+}
+
+impl EngineProcess {
+    fn spawn(path: &str, log: &UciLog) -> io::Result<Self> {
+        if path == BUILTIN_ENGINE {
+            return Ok(Self::spawn_builtin(log));
+        }
+        let mut child = Command::new(path)
             .stdin(Stdio::piped())
             .stdout(Stdio::piped())
-            .spawn()
-            .expect("Failed to start stockfish process");
+            .stderr(Stdio::piped())
+            .spawn()?;
 
-        // Wait for Stockfish to initialize
-        thread::sleep(Duration::from_millis(500));
+        let stdout = child.stdout.take().expect("stdout is piped");
+        let (sender, output) = mpsc::channel();
+        let stdout_log = log.clone();
+        thread::spawn(move || {
+            for line in BufReader::new(stdout).lines() {
+                let Ok(line) = line else { break };
+                stdout_log.push(LogDirection::Received, &line);
+                if sender.send(line).is_err() {
+                    break;
+                }
+            }
+        });
 
-        Self { process }
+        let stderr = Arc::new(Mutex::new(VecDeque::new()));
+        let stderr_pipe = child.stderr.take().expect("stderr is piped");
+        let stderr_tail = Arc::clone(&stderr);
+        let stderr_log = log.clone();
+        thread::spawn(move || {
+            for line in BufReader::new(stderr_pipe).lines() {
+                let Ok(line) = line else { break };
+                stderr_log.push(LogDirection::Stderr, &line);
+                let mut tail = stderr_tail.lock().unwrap();
+                if tail.len() == STDERR_LINES {
+                    tail.pop_front();
+                }
+                tail.push_back(line);
+            }
+        });
+
+        let input = Box::new(child.stdin.take().expect("stdin is piped"));
+        Ok(Self { backend: Backend::Process(child), input, output, stderr })
+    }
+
+    fn spawn_builtin(log: &UciLog) -> Self {
+        let (engine_sender, engine_output) = mpsc::channel::<String>();
+        let (commands, handle) = engine::spawn(engine_sender);
+
+        // Same logging as for a process's stdout
+        let (sender, output) = mpsc::channel();
+        let output_log = log.clone();
+        thread::spawn(move || {
+            for line in engine_output {
+                output_log.push(LogDirection::Received, &line);
+                if sender.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+
+        Self {
+            backend: Backend::Builtin(handle),
+            input: Box::new(ChannelWriter { sender: commands, buffer: Vec::new() }),
+            output,
+            stderr: Arc::new(Mutex::new(VecDeque::new())),
+        }
+    }
+
+    /// Sends `uci` and collects the engine's name and options up to `uciok`.
+    fn handshake(&mut self, log: &UciLog) -> io::Result<EngineId> {
+        log.push(LogDirection::Sent, "uci");
+        writeln!(self.input, "uci")?;
+
+        let started = Instant::now();
+        let mut id = EngineId::default();
+        loop {
+            let remaining = HANDSHAKE_TIMEOUT.saturating_sub(started.elapsed());
+            match self.output.recv_timeout(remaining) {
+                Ok(line) if line.trim() == "uciok" => return Ok(id),
+                Ok(line) => {
+                    if let Some(name) = line.strip_prefix("id name ") {
+                        id.name = name.trim().to_string();
+                    } else if let Some(option) = UciOption::parse(&line) {
+                        id.options.push(option);
+                    }
+                }
+                Err(RecvTimeoutError::Timeout) => {
+                    self.kill();
+                    return Err(io::Error::new(io::ErrorKind::TimedOut, "engine did not answer the UCI handshake"));
+                }
+                Err(RecvTimeoutError::Disconnected) => {
+                    self.exit_status();
+                    return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "engine exited during the UCI handshake"));
+                }
+            }
+        }
+    }
+
+    fn stderr_tail(&self) -> Vec<String> {
+        self.stderr.lock().unwrap().iter().cloned().collect()
+    }
+
+    /// Waits for the engine to end and describes how it did.
+    fn exit_status(&mut self) -> String {
+        match &mut self.backend {
+            Backend::Process(child) => child.wait().map(|status| status.to_string()).unwrap_or_else(|err| err.to_string()),
+            Backend::Builtin(handle) if handle.is_finished() => "engine thread stopped".to_string(),
+            Backend::Builtin(_) => "engine thread stopped answering".to_string(),
+        }
+    }
+
+    /// Ends the engine at once. The built-in engine can't be killed; it
+    /// ends by itself once its command channel is dropped with `self`.
+    fn kill(&mut self) {
+        if let Backend::Process(child) = &mut self.backend {
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+    }
+
+    /// Asks the engine to quit, killing it if it hasn't after a grace period.
+    fn shut_down(&mut self) {
+        let _ = writeln!(self.input, "quit");
+        let Backend::Process(child) = &mut self.backend else {
+            return;
+        };
+        let started = Instant::now();
+        while started.elapsed() < WATCHDOG_GRACE {
+            if let Ok(Some(_)) = child.try_wait() {
+                return;
+            }
+            thread::sleep(Duration::from_millis(20));
+        }
+        self.kill();
+    }
+}
+
+/// Stops a search running on another thread, which then returns its
+/// best move so far.
+struct StopHandle(Arc<AtomicBool>);
+
+impl StopHandle {
+    fn stop(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+}
+
+pub struct Stockfish {
+    path: String,
+    // None once the engine has died and couldn't be brought back
+    process: Option<EngineProcess>,
+    id: EngineId,
+    // Every option sent, in order, so a restarted engine can be configured the same way
+    options: Vec<(String, String)>,
+    position: Option<String>,
+    restarts: u32,
+    events: Vec<EngineEvent>,
+    log: UciLog,
+    // Latest scored info line per `multipv` index, best line first
+    lines: Vec<SearchInfo>,
+    ponder_move: Option<String>,
+    pondering: bool,
+    // How long the running search may take, and when it started
+    search_deadline: Option<(Instant, Duration)>,
+    // Deadline for a ponder search once it turns into a real one
+    ponder_deadline: Option<Duration>,
+    // Set through the latest stop handle
+    stop_requested: Arc<AtomicBool>,
+}
+
+impl Stockfish {
+    pub fn new(path: &str) -> io::Result<Self> {
+        let log = UciLog::default();
+        let mut process = EngineProcess::spawn(path, &log)?;
+        let id = process.handshake(&log)?;
+
+        Ok(Self {
+            path: path.to_string(),
+            process: Some(process),
+            id,
+            options: Vec::new(),
+            position: None,
+            restarts: 0,
+            events: Vec::new(),
+            log,
+            lines: Vec::new(),
+            ponder_move: None,
+            pondering: false,
+            search_deadline: None,
+            ponder_deadline: None,
+            stop_requested: Arc::new(AtomicBool::new(false)),
+        })
     }
 
     pub fn set_skill_level(&mut self, level: u8) {
-        self.send_command(&format!("setoption name Skill Level value {}", level));
+        self.set_option("Skill Level", &level.to_string());
+    }
+
+    /// The ratings the engine accepts for `UCI_Elo`, if it has that option.
+    pub fn elo_range(&self) -> Option<RangeInclusive<u32>> {
+        match self.declared_option("UCI_Elo")?.kind {
+            UciOptionKind::Spin { min, max, .. } => Some(min.max(0) as u32..=max.max(0) as u32),
+            _ => None,
+        }
+    }
+
+    /// Sets skill or target rating. A rating outside the range the engine
+    /// advertises is brought to the nearest end of it.
+    pub fn set_strength(&mut self, strength: EngineStrength) {
+        match strength {
+            EngineStrength::Skill(level) => {
+                self.set_option("UCI_LimitStrength", "false");
+                self.set_skill_level(level);
+            }
+            EngineStrength::Elo(elo) => {
+                self.set_skill_level(20);
+                self.set_option("UCI_LimitStrength", "true");
+                let elo = self.elo_range().map_or(elo, |range| elo.clamp(*range.start(), *range.end()));
+                self.set_option("UCI_Elo", &elo.to_string());
+            }
+        }
+    }
+
+    pub fn set_show_wdl(&mut self, enabled: bool) {
+        self.set_option("UCI_ShowWDL", &enabled.to_string());
+    }
+
+    pub fn set_multipv(&mut self, count: usize) {
+        self.set_option("MultiPV", &count.max(1).to_string());
+    }
+
+    /// Lets the engine think on the opponent's time. Only takes effect for
+    /// searches started with [`Stockfish::start_ponder`].
+    pub fn set_ponder(&mut self, enabled: bool) {
+        self.set_option("Ponder", &enabled.to_string());
+    }
+
+    /// Sets an option the engine declared, after checking the value against
+    /// its declaration. Undeclared options are rejected.
+    pub fn set_declared_option(&mut self, name: &str, value: &str) -> Result<(), String> {
+        let option = self
+            .declared_option(name)
+            .ok_or_else(|| format!("{} has no option named {}", self.engine_name(), name))?;
+        let (name, value) = (option.name.clone(), option.validate(value)?);
+        self.set_option(&name, &value);
+        Ok(())
+    }
+
+    /// Triggers a `button` option such as `Clear Hash`.
+    pub fn press_button(&mut self, name: &str) {
+        self.stop_ponder();
+        self.send_command(&format!("setoption name {}", name));
+    }
+
+    /// The engine's `id name`, or the executable path if it didn't send one.
+    pub fn engine_name(&self) -> &str {
+        if self.id.name.is_empty() { &self.path } else { &self.id.name }
+    }
+
+    /// Options the engine declared in reply to `uci`.
+    pub fn declared_options(&self) -> &[UciOption] {
+        &self.id.options
+    }
+
+    pub fn declared_option(&self, name: &str) -> Option<&UciOption> {
+        self.id.options.iter().find(|option| option.name.eq_ignore_ascii_case(name))
+    }
+
+    /// The value last sent for an option, or its declared default.
+    pub fn option_value(&self, name: &str) -> Option<String> {
+        self.options
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.clone())
+            .or_else(|| self.declared_option(name)?.default_value())
+    }
+
+    fn set_option(&mut self, name: &str, value: &str) {
+        // Options must not change while the engine is searching
+        self.stop_ponder();
+        match self.options.iter_mut().find(|(n, _)| n == name) {
+            Some(option) => option.1 = value.to_string(),
+            None => self.options.push((name.to_string(), value.to_string())),
+        }
+        self.send_command(&format!("setoption name {} value {}", name, value));
+    }
+
+    /// Tells the engine the next search belongs to a different game, so it
+    /// can drop what it learned about the previous one.
+    pub fn new_game(&mut self) {
+        self.stop_ponder();
+        self.send_command("ucinewgame");
+    }
+
+    /// Sends the game's starting position plus every move since, so the
+    /// engine knows about repetitions and the 50-move count.
+    pub fn set_position_with_moves(&mut self, start_fen: &str, moves: &[ChessMove]) {
+        self.stop_ponder();
+        let mut command = if start_fen == START_FEN {
+            "position startpos".to_string()
+        } else {
+            format!("position fen {}", start_fen)
+        };
+        if !moves.is_empty() {
+            command.push_str(" moves");
+            for mv in moves {
+                command.push(' ');
+                command.push_str(&mv.to_uci());
+            }
+        }
+        self.send_command(&command);
+        self.position = Some(command);
+    }
+
+    /// The deepest scored `info` line seen during the last search, from the
+    /// point of view of the side that was to move.
+    pub fn last_info(&self) -> Option<&SearchInfo> {
+        self.lines.first()
+    }
+
+    /// Candidate lines from the last search, ordered by `multipv`.
+    pub fn lines(&self) -> &[SearchInfo] {
+        &self.lines
+    }
+
+    /// The reply the engine expects, from the last `bestmove ... ponder ...`.
+    pub fn ponder_move(&self) -> Option<&str> {
+        self.ponder_move.as_deref()
+    }
+
+    pub fn is_pondering(&self) -> bool {
+        self.pondering
     }
 
-    pub fn set_position(&mut self, fen: &str) {
-        self.send_command(&format!("position fen {}", fen));
+    /// False once the engine has died and couldn't be restarted.
+    pub fn is_alive(&self) -> bool {
+        self.process.is_some()
     }
 
-    pub fn get_best_move(&mut self, time_ms: u64) -> Option<String> {
-        self.send_command(&format!("go movetime {}", time_ms));
-        
-        let stdout = self.process.stdout.as_mut().expect("Failed to open stdout");
-        let reader = BufReader::new(stdout);
+    /// Everything sent to and received from the engine, across restarts.
+    pub fn log(&self) -> &UciLog {
+        &self.log
+    }
 
-        for line in reader.lines() {
-            let line = line.expect("Failed to read line");
-            if line.starts_with("bestmove") {
-                return line.split_whitespace().nth(1).map(String::from);
+    /// Sends a command typed by the user. Whatever the engine answers only
+    /// shows up in the log.
+    pub fn send_raw(&mut self, command: &str) {
+        self.stop_ponder();
+        self.send_command(command);
+    }
+
+    /// A handle for stopping the next search from another thread. Handles
+    /// taken earlier stop working, so a late `stop` can't cut short a
+    /// later search.
+    fn stop_handle(&mut self) -> StopHandle {
+        self.stop_requested = Arc::new(AtomicBool::new(false));
+        StopHandle(self.stop_requested.clone())
+    }
+
+    /// Supervisor events since the last call, oldest first.
+    pub fn take_events(&mut self) -> Vec<EngineEvent> {
+        std::mem::take(&mut self.events)
+    }
+
+    /// Searches the current position within `limits` and blocks until the
+    /// engine answers with `bestmove`. Returns `None` if the engine died or
+    /// hung; it will have been restarted where possible.
+    pub fn get_best_move(&mut self, limits: &SearchLimits) -> Option<String> {
+        self.stop_ponder();
+        self.discard_output();
+        self.send_command(&format!("go {}", limits.to_go_args()));
+        let deadline = limits.deadline().unwrap_or(UNBOUNDED_SEARCH_TIMEOUT);
+        self.search_deadline = Some((Instant::now(), deadline));
+        self.wait_for_best_move()
+    }
+
+    /// Starts searching the position set with the expected reply already
+    /// played, without waiting for a result. Follow up with
+    /// [`Stockfish::ponder_hit`] if the opponent plays that reply, otherwise
+    /// with a new position and search (which stop this one).
+    pub fn start_ponder(&mut self, limits: &SearchLimits) {
+        self.stop_ponder();
+        self.discard_output();
+        self.send_command(&format!("go ponder {}", limits.to_go_args()));
+        self.lines.clear();
+        self.pondering = true;
+        // The clock only starts running on `ponderhit`
+        self.search_deadline = None;
+        self.ponder_deadline = limits.deadline();
+    }
+
+    /// The opponent played the expected move: the ponder search becomes a
+    /// normal one and its result is returned.
+    pub fn ponder_hit(&mut self) -> Option<String> {
+        if !self.pondering {
+            return None;
+        }
+        self.pondering = false;
+        self.send_command("ponderhit");
+        let deadline = self.ponder_deadline.take().unwrap_or(UNBOUNDED_SEARCH_TIMEOUT);
+        self.search_deadline = Some((Instant::now(), deadline));
+        self.wait_for_best_move()
+    }
+
+    /// Abandons a running ponder search.
+    pub fn stop_ponder(&mut self) {
+        if self.pondering {
+            self.pondering = false;
+            self.send_command("stop");
+            // A stopped search must answer straight away
+            self.search_deadline = Some((Instant::now(), Duration::ZERO));
+            self.wait_for_best_move();
+        }
+    }
+
+    fn wait_for_best_move(&mut self) -> Option<String> {
+        self.lines.clear();
+        self.ponder_move = None;
+        let mut stop_sent = false;
+
+        loop {
+            let Some(process) = &self.process else {
+                return None;
+            };
+            if !stop_sent && self.stop_requested.swap(false, Ordering::Relaxed) {
+                self.send_command("stop");
+                stop_sent = true;
+                // A stopped search must answer straight away
+                self.search_deadline = Some((Instant::now(), Duration::ZERO));
+                continue;
+            }
+
+            match process.output.recv_timeout(POLL_INTERVAL) {
+                Ok(line) => {
+                    if line.starts_with("bestmove") {
+                        self.restarts = 0;
+                        self.search_deadline = None;
+                        let mut tokens = line.split_whitespace().skip(1);
+                        let best = tokens.next().map(String::from);
+                        if tokens.next() == Some("ponder") {
+                            self.ponder_move = tokens.next().map(String::from);
+                        }
+                        return best;
+                    }
+                    if let Some(info) = SearchInfo::parse(&line) {
+                        let index = info.multipv.saturating_sub(1);
+                        if index < self.lines.len() {
+                            self.lines[index] = info;
+                        } else if index == self.lines.len() {
+                            self.lines.push(info);
+                        }
+                    }
+                }
+                Err(RecvTimeoutError::Timeout) => {
+                    let Some((started, deadline)) = self.search_deadline else {
+                        continue;
+                    };
+                    let elapsed = started.elapsed();
+                    if !stop_sent && elapsed > deadline + WATCHDOG_GRACE {
+                        self.send_command("stop");
+                        stop_sent = true;
+                    } else if stop_sent && elapsed > deadline + WATCHDOG_GRACE * 2 {
+                        // Not even `stop` got an answer: the process is hung
+                        self.events.push(EngineEvent::Unresponsive);
+                        self.restart();
+                        return None;
+                    }
+                }
+                Err(RecvTimeoutError::Disconnected) => {
+                    // The reader thread hit EOF: the engine has exited
+                    self.handle_exit();
+                    return None;
+                }
+            }
+        }
+    }
+
+    /// Drops output nobody waited for, such as answers to console commands,
+    /// so it can't be mistaken for the result of the next search.
+    fn discard_output(&mut self) {
+        if let Some(process) = &self.process {
+            while process.output.try_recv().is_ok() {}
+        }
+    }
+
+    fn handle_exit(&mut self) {
+        let Some(mut process) = self.process.take() else {
+            return;
+        };
+        let status = process.exit_status();
+        self.events.push(EngineEvent::Exited {
+            status,
+            stderr: process.stderr_tail(),
+        });
+        self.restart();
+    }
+
+    /// Replaces the engine process and brings it back to the state the old
+    /// one was in: same options, same position.
+    fn restart(&mut self) {
+        if let Some(mut old) = self.process.take() {
+            old.kill();
+        }
+        self.pondering = false;
+        self.search_deadline = None;
+
+        self.restarts += 1;
+        if self.restarts > MAX_RESTARTS {
+            self.events.push(EngineEvent::Failed(format!("crashed {} times in a row", MAX_RESTARTS)));
+            return;
+        }
+
+        let spawned = EngineProcess::spawn(&self.path, &self.log)
+            .and_then(|mut process| process.handshake(&self.log).map(|_| process));
+        match spawned {
+            Ok(process) => {
+                self.process = Some(process);
+                for (name, value) in self.options.clone() {
+                    self.send_command(&format!("setoption name {} value {}", name, value));
+                }
+                if let Some(position) = self.position.clone() {
+                    self.send_command(&position);
+                }
+                self.events.push(EngineEvent::Restarted);
             }
+            Err(err) => self.events.push(EngineEvent::Failed(err.to_string())),
         }
-        None
     }
 
     fn send_command(&mut self, command: &str) {
-        let stdin = self.process.stdin.as_mut().expect("Failed to open stdin");
-        writeln!(stdin, "{}", command).expect("Failed to write to stdin");
+        let Some(process) = &mut self.process else {
+            return;
+        };
+        self.log.push(LogDirection::Sent, command);
+        if writeln!(process.input, "{}", command).is_err() {
+            // Broken pipe: the engine is gone
+            self.handle_exit();
+        }
     }
 }
 
 impl Drop for Stockfish {
     fn drop(&mut self) {
-        self.send_command("quit");
-        let _ = self.process.wait();
+        if let Some(process) = &mut self.process {
+            process.shut_down();
+        }
+    }
+}
+
+/// A search running on a thread of its own, so the caller stays responsive
+/// while the engine thinks. The engine comes back with the result.
+pub struct BackgroundSearch {
+    result: Receiver<(Stockfish, Option<String>)>,
+    stop: StopHandle,
+    log: UciLog,
+}
+
+impl BackgroundSearch {
+    /// Moves the engine onto a new thread and runs `search` there.
+    pub fn spawn<F>(mut stockfish: Stockfish, search: F) -> Self
+    where
+        F: FnOnce(&mut Stockfish) -> Option<String> + Send + 'static,
+    {
+        let stop = stockfish.stop_handle();
+        let log = stockfish.log().clone();
+        let (sender, result) = mpsc::channel();
+        thread::spawn(move || {
+            let best = search(&mut stockfish);
+            // Nobody is listening if the search was abandoned meanwhile
+            let _ = sender.send((stockfish, best));
+        });
+        Self { result, stop, log }
+    }
+
+    /// The engine and its move once the search is done.
+    pub fn try_finish(&self) -> Result<(Stockfish, Option<String>), TryRecvError> {
+        self.result.try_recv()
+    }
+
+    /// Stops the search and takes the engine back, dropping the move it
+    /// found. The engine answers `stop` straight away, and the watchdog
+    /// kills it if it doesn't. `None` if the search thread died.
+    pub fn cancel(self) -> Option<Stockfish> {
+        self.stop.stop();
+        self.result.recv().ok().map(|(stockfish, _)| stockfish)
+    }
+
+    /// The engine's log, which keeps filling while it searches.
+    pub fn log(&self) -> &UciLog {
+        &self.log
     }
 }